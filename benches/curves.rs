@@ -0,0 +1,30 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lightwire::curves::{builtin, Curve, LutCurve, BUILTIN_CURVES};
+
+/// Volumes spread over the whole range, so table lookups don't all hit one entry.
+fn volumes() -> Vec<f32> {
+    (0..=100).map(|i| i as f32 / 100.0).collect()
+}
+
+fn bench_curves(c: &mut Criterion) {
+    let volumes = volumes();
+    for name in BUILTIN_CURVES {
+        let exact = builtin(name).unwrap();
+        let lut = LutCurve::new(builtin(name).unwrap());
+
+        let mut group = c.benchmark_group(*name);
+        group.bench_function("apply", |b| {
+            b.iter(|| volumes.iter().map(|&v| exact.apply(black_box(v))).sum::<f32>())
+        });
+        group.bench_function("apply_lut", |b| {
+            b.iter(|| volumes.iter().map(|&v| lut.apply(black_box(v))).sum::<f32>())
+        });
+        group.bench_function("inverse", |b| {
+            b.iter(|| volumes.iter().map(|&v| exact.inverse(black_box(v))).sum::<f32>())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_curves);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+//! Lists the Cargo features that gate a provider, i.e. those with a module
+//! of the same name under `src/provider`, so the factory can name providers
+//! left out of a build without keeping a list of its own.
+
+use std::path::PathBuf;
+
+fn main() {
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo"));
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=src/provider");
+
+    let manifest: toml::Table = std::fs::read_to_string(root.join("Cargo.toml"))
+        .expect("failed to read Cargo.toml")
+        .parse()
+        .expect("failed to parse Cargo.toml");
+    let providers: Vec<String> = manifest
+        .get("features")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|features| features.keys())
+        .filter(|feature| root.join("src/provider").join(format!("{}.rs", feature)).exists())
+        .map(|feature| format!("{:?}", feature))
+        .collect();
+
+    let out = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo")).join("provider_features.rs");
+    let source = format!(
+        "/// Every provider feature in Cargo.toml, enabled or not.\nconst PROVIDER_FEATURES: &[&str] = &[{}];\n",
+        providers.join(", ")
+    );
+    std::fs::write(out, source).expect("failed to write provider_features.rs");
+}
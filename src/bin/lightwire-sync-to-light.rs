@@ -1,6 +1,6 @@
 use clap::Parser;
 use anyhow::Result;
-use lightwire::{ProviderRegistry, provider::LifxProvider};
+use lightwire::cli::{self, sync_to_light, SyncToLightOpts};
 
 #[derive(Parser, Debug)]
 #[command(name = "lightwire-sync-to-light")]
@@ -11,54 +11,17 @@ struct Cli {
     #[arg(long)]
     dry_run: bool,
     #[arg(long)]
-    provider: Option<String>,
-    #[arg(long)]
-    once: bool,
-    #[arg(long, default_value = "true")]
-    daemon: bool,
+    config: Option<String>,
+    #[command(flatten)]
+    opts: SyncToLightOpts,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    tracing_subscriber::fmt()
-        .with_max_level(if cli.verbose { tracing::Level::DEBUG } else { tracing::Level::INFO })
-        .init();
-
-    let mut registry = ProviderRegistry::new();
-    let lifx_provider = LifxProvider::default();
-    registry.register(Box::new(lifx_provider));
-
-    let lights = registry.discover_all().await?;
-
-    if lights.is_empty() {
-        println!("No lights found on the network.");
-        return Ok(());
-    }
-
-    println!("Found {} light(s):", lights.len());
-    for light in &lights {
-        println!("  - {} ({})", light.label(), light.id().0);
-    }
-
-    println!("\nWatching PipeWire for volume changes...");
-
-    if cli.dry_run {
-        println!("DRY RUN: Would update light brightness when PipeWire volumes change");
-    }
-
-    if !cli.daemon && !cli.once {
-        println!("Running once and exiting...");
-    }
-
-    loop {
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-        if cli.once {
-            break;
-        }
-    }
+    cli::init_tracing(cli.verbose);
 
-    Ok(())
+    let config = cli::load_config(cli.config.as_deref())?;
+    sync_to_light::run(cli.opts, config, cli.dry_run).await
 }
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use crate::provider::{Light, LightId, LightState};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("Failed to access cache: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid cache file: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+/// A light as it was last discovered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedLight {
+    pub provider: String,
+    pub state: LightState,
+}
+
+impl Light for CachedLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.provider
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+/// Snapshot of discovered light states, so startup can skip waiting on discovery.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateCache {
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub lights: Vec<CachedLight>,
+}
+
+impl StateCache {
+    pub fn from_lights(lights: &[Box<dyn Light>]) -> Self {
+        Self {
+            saved_at: now(),
+            lights: lights
+                .iter()
+                .map(|light| CachedLight { provider: light.provider_name().to_string(), state: light.to_state() })
+                .collect(),
+        }
+    }
+
+    /// `lights.json` under the user's cache directory.
+    pub fn default_path() -> PathBuf {
+        let dirs = ProjectDirs::from("com", "lightwire", "lightwire")
+            .expect("Failed to determine project directories");
+        dirs.cache_dir().join("lights.json")
+    }
+
+    /// Reads the cache, returning `None` if it is missing or older than `ttl`.
+    pub fn load(path: &Path, ttl: Duration) -> Result<Option<Self>, CacheError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let cache: Self = serde_json::from_str(&contents)?;
+        if cache.is_stale(ttl) {
+            return Ok(None);
+        }
+        Ok(Some(cache))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn is_stale(&self, ttl: Duration) -> bool {
+        now().saturating_sub(self.saved_at) > ttl.as_secs()
+    }
+
+    pub fn into_lights(self) -> Vec<Box<dyn Light>> {
+        self.lights.into_iter().map(|light| Box::new(light) as Box<dyn Light>).collect()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Brightness;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lightwire-cache-{}-{}", std::process::id(), name))
+    }
+
+    fn cache() -> StateCache {
+        StateCache {
+            saved_at: now(),
+            lights: vec![CachedLight {
+                provider: "lifx".to_string(),
+                state: LightState::new(LightId("lifx:abc".to_string()), "Desk".to_string(), Brightness::new(0.3), true),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("lights.json");
+        cache().save(&path).unwrap();
+        let loaded = StateCache::load(&path, Duration::from_secs(60)).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lights = loaded.into_lights();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].provider_name(), "lifx");
+        assert_eq!(lights[0].state().brightness, Brightness::new(0.3));
+    }
+
+    #[test]
+    fn test_load_missing_and_stale() {
+        let path = temp_path("stale.json");
+        assert!(StateCache::load(&path, Duration::from_secs(60)).unwrap().is_none());
+
+        let mut stale = cache();
+        stale.saved_at -= 120;
+        stale.save(&path).unwrap();
+        let loaded = StateCache::load(&path, Duration::from_secs(60)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.is_none());
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::Write;
+use anyhow::Result;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use super::Cli;
+
+#[derive(clap::Args, Debug)]
+pub struct CompletionsOpts {
+    /// Shell to generate the completion script for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+pub async fn run(opts: CompletionsOpts) -> Result<()> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    // Generated into a buffer since writing straight to a closed pipe panics.
+    let mut script = Vec::new();
+    clap_complete::generate(opts.shell, &mut command, name, &mut script);
+    std::io::stdout().write_all(&script)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions_cover_subcommands() {
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut Cli::command(), "lightwire", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("sync-to-light"));
+        assert!(script.contains("completions"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use crate::config::{Config, ConfigIssue};
+
+/// The keys `config set` can change.
+const SETTABLE_KEYS: &[&str] = &["pipewire.node_prefix", "curves.default", "lifx.discovery_timeout_ms"];
+
+#[derive(clap::Args, Debug)]
+pub struct ConfigOpts {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Load the config and check it for semantic problems
+    Validate,
+    /// Change one setting in the config file, e.g. `curves.default gamma`
+    Set {
+        /// One of pipewire.node_prefix, curves.default or lifx.discovery_timeout_ms
+        key: String,
+        value: String,
+    },
+}
+
+pub async fn run(opts: ConfigOpts, config_path: Option<&str>, dry_run: bool) -> Result<()> {
+    match opts.command {
+        ConfigCommand::Validate => validate(config_path),
+        ConfigCommand::Set { key, value } => set(config_path, &key, &value, dry_run),
+    }
+}
+
+fn set(config_path: Option<&str>, key: &str, value: &str, dry_run: bool) -> Result<()> {
+    edit_file(config_path, &format!("{} = {}", key, value), dry_run, |config| set_key(config, key, value))
+}
+
+/// Applies `edit`, described by `change` as `key = value`, to the config
+/// file and saves it if the result is valid.
+/// Edits the file itself rather than the merged config, so environment
+/// overrides and the other config formats aren't baked into it.
+pub(super) fn edit_file(
+    config_path: Option<&str>,
+    change: &str,
+    dry_run: bool,
+    edit: impl FnOnce(&mut Config) -> Result<()>,
+) -> Result<()> {
+    let path = match config_path {
+        Some(path) => PathBuf::from(shellexpand::tilde(path).into_owned()),
+        None => Config::user_config_dir().join("config.toml"),
+    };
+    if matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml" | "json")) {
+        bail!("{} is not TOML; config set can only rewrite TOML files", path.display());
+    }
+    let mut config = if path.exists() {
+        Config::load_from_path(path.clone()).context("Failed to load config")?
+    } else {
+        Config::default()
+    };
+
+    edit(&mut config)?;
+    let issues = config.validate();
+    if !issues.is_empty() {
+        for issue in &issues {
+            println!("  - {}", issue);
+        }
+        bail!("Not saving {}: {} problem(s) found", path.display(), issues.len());
+    }
+
+    if dry_run {
+        println!("DRY RUN: Would set {} in {}", change, path.display());
+        return Ok(());
+    }
+    config.save_to_path(&path)?;
+    println!("Set {} in {}", change, path.display());
+    Ok(())
+}
+
+fn set_key(config: &mut Config, key: &str, value: &str) -> Result<()> {
+    match key {
+        "pipewire.node_prefix" => config.pipewire.node_prefix = value.to_string(),
+        "curves.default" => config.curves.default = value.to_string(),
+        "lifx.discovery_timeout_ms" => {
+            config.lifx.discovery_timeout_ms =
+                value.parse().with_context(|| format!("{} must be a whole number of milliseconds", key))?;
+        }
+        _ => bail!("Unknown key {}; settable keys are {}", key, SETTABLE_KEYS.join(", ")),
+    }
+    Ok(())
+}
+
+fn validate(config_path: Option<&str>) -> Result<()> {
+    let config = match super::load_config(config_path) {
+        Ok(config) => config,
+        Err(e) => bail!("Failed to load config: {}", e),
+    };
+
+    let mut issues = config.validate();
+    let config_dir = config.pipewire_config_dir();
+    if !is_writable(&config_dir) {
+        issues.push(ConfigIssue::new("pipewire.config_dir", format!("{} is not writable", config_dir.display())));
+    }
+
+    if issues.is_empty() {
+        println!("Config is valid.");
+        return Ok(());
+    }
+
+    for issue in &issues {
+        println!("  - {}", issue);
+    }
+    bail!("{} problem(s) found in config", issues.len())
+}
+
+/// Probes the directory, or its nearest existing ancestor since populate
+/// creates it on demand, by creating and removing a file.
+pub(super) fn is_writable(dir: &Path) -> bool {
+    let Some(existing) = dir.ancestors().find(|path| path.exists()) else {
+        return false;
+    };
+    let probe = existing.join(format!(".lightwire-write-test-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => std::fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_key() {
+        let mut config = Config::default();
+        set_key(&mut config, "curves.default", "gamma").unwrap();
+        set_key(&mut config, "pipewire.node_prefix", "lights").unwrap();
+        set_key(&mut config, "lifx.discovery_timeout_ms", "2500").unwrap();
+        assert_eq!(config.curves.default, "gamma");
+        assert_eq!(config.pipewire.node_prefix, "lights");
+        assert_eq!(config.lifx.discovery_timeout_ms, 2500);
+
+        assert!(set_key(&mut config, "lifx.discovery_timeout_ms", "soon").is_err());
+        assert!(set_key(&mut config, "lifx.port", "1").is_err());
+        assert_eq!(config.lifx.discovery_timeout_ms, 2500);
+    }
+
+    #[test]
+    fn test_set_leaves_file_alone_on_error() {
+        let path = std::env::temp_dir().join(format!("lightwire-{}-set.toml", std::process::id()));
+        std::fs::write(&path, "[curves]\ndefault = \"linear\"\n").unwrap();
+        let config_path = path.to_str().unwrap();
+
+        assert!(set(Some(config_path), "curves.default", "wobbly", false).is_err());
+        assert!(set(Some(config_path), "nope", "1", false).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[curves]\ndefault = \"linear\"\n");
+
+        set(Some(config_path), "curves.default", "gamma", false).unwrap();
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.curves.default, "gamma");
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::JoinHandle;
+use crate::LightId;
+
+/// How long the daemon waits for a client to send its command.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(clap::Args, Debug)]
+pub struct ControlOpts {
+    /// The socket a sync listens on for `pause`, `resume`, `status`,
+    /// `disable` and `enable`; defaults to one in the runtime directory
+    #[arg(long = "control-socket", value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LightControlOpts {
+    /// Light id, e.g. lifx:d073d5123456
+    pub light_id: String,
+    /// Also set the light's `enabled` flag in the config file, so the change
+    /// outlasts the running sync
+    #[arg(long)]
+    pub save: bool,
+    #[command(flatten)]
+    pub control: ControlOpts,
+}
+
+/// The socket a running sync listens on, e.g. `$XDG_RUNTIME_DIR/lightwire/control.sock`.
+pub fn default_socket_path() -> PathBuf {
+    ProjectDirs::from("com", "lightwire", "lightwire")
+        .and_then(|dirs| dirs.runtime_dir().map(|dir| dir.join("control.sock")))
+        .unwrap_or_else(|| std::env::temp_dir().join("lightwire-control.sock"))
+}
+
+/// Sends `command` (`pause` or `resume`) to a running sync and prints its
+/// reply.
+pub async fn run(opts: ControlOpts, command: &str) -> Result<()> {
+    let path = opts.socket.unwrap_or_else(default_socket_path);
+    let reply = send(&path, command).await?;
+    println!("{}", reply);
+    Ok(())
+}
+
+/// Takes a light out of, or puts it back into, a running sync, and with
+/// `--save` records that in the config file as well.
+pub async fn run_light(opts: LightControlOpts, config_path: Option<&str>, enable: bool, dry_run: bool) -> Result<()> {
+    let command = if enable { "enable" } else { "disable" };
+    if dry_run {
+        println!("DRY RUN: Would {} {}", command, opts.light_id);
+    } else {
+        let path = opts.control.socket.unwrap_or_else(default_socket_path);
+        let reply = send(&path, &format!("{} {}", command, opts.light_id)).await?;
+        println!("{}", reply);
+    }
+    if opts.save {
+        let change = format!("lights.lights.\"{}\".enabled = {}", opts.light_id, enable);
+        super::config::edit_file(config_path, &change, dry_run, |config| {
+            config.lights.lights.entry(opts.light_id.clone()).or_default().enabled = Some(enable);
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+pub async fn send(path: &Path, command: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("no sync is listening on {}", path.display()))?;
+    stream.write_all(format!("{}\n", command).as_bytes()).await?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).await?;
+    let reply = reply.trim();
+    match reply.strip_prefix("error: ") {
+        Some(error) => anyhow::bail!("{}", error),
+        None => Ok(reply.to_string()),
+    }
+}
+
+/// What a running sync is told to skip.
+#[derive(Debug, Default)]
+struct ControlState {
+    paused: AtomicBool,
+    /// The lights in the sync, which are the only ones that can be disabled.
+    lights: HashSet<LightId>,
+    disabled: Mutex<HashSet<LightId>>,
+}
+
+/// Lets `lightwire pause`/`resume`/`status`, or SIGUSR1 as a toggle, pause a
+/// running sync, and `lightwire disable`/`enable` take single lights out of
+/// it. While paused the sync keeps watching PipeWire but sends nothing to
+/// the lights; a disabled light is left alone in both directions. The
+/// socket file is removed on drop.
+pub struct SyncControl {
+    state: Arc<ControlState>,
+    /// The socket file, if one could be bound.
+    path: Option<PathBuf>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SyncControl {
+    /// Control for a sync of `lights` that answers SIGUSR1 but has no
+    /// socket yet. Without SIGUSR1 handled, the signal would kill the sync.
+    pub fn new(lights: impl IntoIterator<Item = LightId>) -> Self {
+        let state = Arc::new(ControlState { lights: lights.into_iter().collect(), ..Default::default() });
+        let mut tasks = Vec::new();
+        match signal(SignalKind::user_defined1()) {
+            Ok(mut toggle) => {
+                let shared = state.clone();
+                tasks.push(tokio::spawn(async move {
+                    while toggle.recv().await.is_some() {
+                        let paused = toggle_paused(&shared);
+                        tracing::info!("SIGUSR1: {}", if paused { "pausing" } else { "resuming" });
+                    }
+                }));
+            }
+            Err(e) => tracing::warn!("Cannot handle SIGUSR1: {}", e),
+        }
+        Self { state, path: None, tasks }
+    }
+
+    /// Listens on `path` for a sync of `lights`, replacing a socket left
+    /// behind by a sync that didn't shut down cleanly. Fails if another sync
+    /// is listening there.
+    pub async fn listen(path: PathBuf, lights: impl IntoIterator<Item = LightId>) -> Result<Self> {
+        let mut control = Self::new(lights);
+        control.bind(path).await?;
+        Ok(control)
+    }
+
+    async fn bind(&mut self, path: PathBuf) -> Result<()> {
+        if UnixStream::connect(&path).await.is_ok() {
+            anyhow::bail!("another sync is already listening on {}", path.display());
+        }
+        let _ = std::fs::remove_file(&path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(&path).with_context(|| format!("cannot listen on {}", path.display()))?;
+
+        let shared = self.state.clone();
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => serve(stream, &shared).await,
+                    Err(e) => tracing::warn!("Control socket accept failed: {}", e),
+                }
+            }
+        }));
+        tracing::info!("Listening for sync control on {}", path.display());
+        self.path = Some(path);
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_disabled(&self, id: &LightId) -> bool {
+        self.state.disabled.lock().expect("disabled lights poisoned").contains(id)
+    }
+}
+
+impl Drop for SyncControl {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Starts control for a sync of `lights` on `path`, or the default socket.
+/// SIGUSR1 still toggles pausing if the socket can't be used.
+pub async fn sync_control(path: Option<PathBuf>, lights: impl IntoIterator<Item = LightId>) -> SyncControl {
+    let mut control = SyncControl::new(lights);
+    if let Err(e) = control.bind(path.unwrap_or_else(default_socket_path)).await {
+        tracing::warn!("Control socket unavailable; only SIGUSR1 can pause: {:#}", e);
+    }
+    control
+}
+
+/// Flips between paused and running, returning whether the sync is now paused.
+fn toggle_paused(state: &ControlState) -> bool {
+    !state.paused.fetch_xor(true, Ordering::SeqCst)
+}
+
+/// Reads one command from a client and writes the reply.
+async fn serve(stream: UnixStream, state: &ControlState) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if !matches!(tokio::time::timeout(READ_TIMEOUT, reader.read_line(&mut line)).await, Ok(Ok(_))) {
+        return;
+    }
+    let reply = respond(line.trim(), state);
+    if let Err(e) = reader.into_inner().write_all(format!("{}\n", reply).as_bytes()).await {
+        tracing::debug!("Failed to answer control command: {}", e);
+    }
+}
+
+/// Applies a control command, returning the reply: the resulting state, or
+/// an `error: ` line.
+fn respond(command: &str, state: &ControlState) -> String {
+    let (verb, argument) = command.split_once(' ').unwrap_or((command, ""));
+    match (verb, argument.trim()) {
+        ("pause", "") => {
+            if !state.paused.swap(true, Ordering::SeqCst) {
+                tracing::info!("Paused; volume changes won't be sent to lights");
+            }
+        }
+        ("resume", "") => {
+            if state.paused.swap(false, Ordering::SeqCst) {
+                tracing::info!("Resumed");
+            }
+        }
+        ("status", "") => {}
+        (verb @ ("enable" | "disable"), "") => return format!("error: {} needs a light id", verb),
+        (verb @ ("enable" | "disable"), id) => {
+            let id = LightId(id.to_string());
+            if !state.lights.contains(&id) {
+                return format!("error: {} is not part of this sync", id.0);
+            }
+            let mut disabled = state.disabled.lock().expect("disabled lights poisoned");
+            let changed = if verb == "disable" { disabled.insert(id.clone()) } else { disabled.remove(&id) };
+            if changed {
+                tracing::info!("{} {}", if verb == "disable" { "Disabled" } else { "Enabled" }, id.0);
+            }
+            return format!("{}d {}", verb, id.0);
+        }
+        _ => return format!("error: unknown command '{}'", command),
+    }
+    status(state)
+}
+
+/// `paused` or `running`, followed by any disabled lights.
+fn status(state: &ControlState) -> String {
+    let mode = if state.paused.load(Ordering::SeqCst) { "paused" } else { "running" };
+    let disabled = state.disabled.lock().expect("disabled lights poisoned");
+    if disabled.is_empty() {
+        return mode.to_string();
+    }
+    let mut ids: Vec<&str> = disabled.iter().map(|id| id.0.as_str()).collect();
+    ids.sort();
+    format!("{} (disabled: {})", mode, ids.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> ControlState {
+        ControlState {
+            lights: [LightId("lifx:desk".to_string()), LightId("lifx:lamp".to_string())].into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_respond() {
+        let state = state();
+        assert_eq!(respond("status", &state), "running");
+        assert_eq!(respond("pause", &state), "paused");
+        assert_eq!(respond("pause", &state), "paused");
+        assert_eq!(respond("resume", &state), "running");
+        assert_eq!(respond("stop", &state), "error: unknown command 'stop'");
+    }
+
+    #[test]
+    fn test_respond_enable_disable() {
+        let state = state();
+        assert_eq!(respond("disable lifx:lamp", &state), "disabled lifx:lamp");
+        assert_eq!(respond("disable lifx:desk", &state), "disabled lifx:desk");
+        assert_eq!(respond("status", &state), "running (disabled: lifx:desk, lifx:lamp)");
+        assert_eq!(respond("enable lifx:desk", &state), "enabled lifx:desk");
+        assert_eq!(respond("status", &state), "running (disabled: lifx:lamp)");
+
+        assert_eq!(respond("disable lifx:attic", &state), "error: lifx:attic is not part of this sync");
+        assert_eq!(respond("enable", &state), "error: enable needs a light id");
+        assert_eq!(respond("pause now", &state), "error: unknown command 'pause now'");
+    }
+
+    #[tokio::test]
+    async fn test_control_over_socket() {
+        let path = std::env::temp_dir().join(format!("lightwire-test-{}.sock", std::process::id()));
+        let lamp = LightId("lifx:lamp".to_string());
+        let control = SyncControl::listen(path.clone(), [lamp.clone()]).await.unwrap();
+        assert!(SyncControl::listen(path.clone(), []).await.is_err());
+
+        assert_eq!(send(&path, "pause").await.unwrap(), "paused");
+        assert!(control.is_paused());
+        assert_eq!(send(&path, "status").await.unwrap(), "paused");
+        assert_eq!(send(&path, "resume").await.unwrap(), "running");
+        assert!(!control.is_paused());
+        assert!(send(&path, "bogus").await.is_err());
+
+        assert_eq!(send(&path, "disable lifx:lamp").await.unwrap(), "disabled lifx:lamp");
+        assert!(control.is_disabled(&lamp));
+
+        drop(control);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_control_without_socket() {
+        let control = sync_control(Some(PathBuf::from("/proc/lightwire/control.sock")), []).await;
+        assert!(control.path.is_none());
+        assert!(toggle_paused(&control.state));
+        assert!(control.is_paused());
+        assert!(!toggle_paused(&control.state));
+        assert!(!control.is_paused());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use crate::{Config, Curve, CurvesConfig, LightId};
+
+/// Rows in the ASCII plot, and columns per row.
+const PLOT_HEIGHT: usize = 10;
+const PLOT_WIDTH: usize = 41;
+
+#[derive(clap::Args, Debug)]
+pub struct CurveOpts {
+    #[command(subcommand)]
+    pub command: CurveCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CurveCommand {
+    /// List the built-in curves and the custom curves defined in the config
+    List,
+    /// Print how a curve maps volume to brightness
+    Preview {
+        /// A built-in or custom curve, or a light id to preview the curve it uses
+        name: String,
+        /// Evenly spaced volumes to tabulate, including 0 and 1
+        #[arg(long, default_value = "11")]
+        points: usize,
+    },
+}
+
+pub async fn run(opts: CurveOpts, config: Config) -> Result<()> {
+    match opts.command {
+        CurveCommand::List => list(&config),
+        CurveCommand::Preview { name, points } => preview(&config, &name, points),
+    }
+}
+
+fn list(config: &Config) -> Result<()> {
+    print!("{}", render_list(&config.curves)?);
+    Ok(())
+}
+
+/// Built-in curve names, then custom curves with their parameters, marking
+/// the default with `*`.
+fn render_list(curves: &CurvesConfig) -> Result<String> {
+    let marker = |name: &str| if name == curves.default { "*" } else { " " };
+    let mut out = String::from("Built-in curves:\n");
+    for name in crate::curves::BUILTIN_CURVES {
+        if let Some(curve) = crate::curves::builtin(name) {
+            out.push_str(&format!("{} {}\n", marker(curve.name()), curve.name()));
+        }
+    }
+
+    let mut custom: Vec<_> = curves.custom.iter().collect();
+    if !custom.is_empty() {
+        custom.sort_by_key(|(name, _)| *name);
+        out.push_str("\nCustom curves:\n");
+        for (name, curve) in custom {
+            out.push_str(&format!("{} {}: {}\n", marker(name), name, serde_json::to_string(curve)?));
+        }
+    }
+    Ok(out)
+}
+
+fn preview(config: &Config, name: &str, points: usize) -> Result<()> {
+    if points < 2 {
+        bail!("--points must be at least 2");
+    }
+    let curves = config.curves.registry()?;
+    let id = LightId(name.to_string());
+    let name = if !curves.contains(name) && config.lights.get(&id).is_some() {
+        config.curve_name_for_light(&id)
+    } else {
+        name
+    };
+    let curve = curves.resolve(name)?;
+
+    println!("Curve {}:\n", name);
+    print!("{}", render_table(curve.as_ref(), points));
+    println!();
+    print!("{}", render_plot(curve.as_ref()));
+    Ok(())
+}
+
+/// Volume, brightness and `|inverse(apply(volume)) - volume|` at each point.
+fn render_table(curve: &dyn Curve, points: usize) -> String {
+    let mut out = format!("{:>8}  {:>10}  {:>10}\n", "volume", "brightness", "round-trip");
+    for i in 0..points {
+        let volume = i as f32 / (points - 1) as f32;
+        let brightness = curve.apply(volume);
+        let error = (curve.inverse(brightness) - volume).abs();
+        out.push_str(&format!("{:>8.3}  {:>10.4}  {:>10.2e}\n", volume, brightness, error));
+    }
+    out
+}
+
+/// Brightness against volume, with full brightness at the top.
+fn render_plot(curve: &dyn Curve) -> String {
+    let rows: Vec<usize> = (0..PLOT_WIDTH)
+        .map(|column| {
+            let brightness = curve.apply(column as f32 / (PLOT_WIDTH - 1) as f32);
+            (((1.0 - brightness) * (PLOT_HEIGHT - 1) as f32).round() as usize).min(PLOT_HEIGHT - 1)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for i in 0..PLOT_HEIGHT {
+        let label = match i {
+            0 => "1",
+            _ if i == PLOT_HEIGHT - 1 => "0",
+            _ => " ",
+        };
+        let line: String = rows.iter().map(|&row| if row == i { '*' } else { ' ' }).collect();
+        out.push_str(&format!("{} |{}\n", label, line.trim_end()));
+    }
+    out.push_str(&format!("  +{}\n", "-".repeat(PLOT_WIDTH)));
+    out.push_str(&format!("   0{:>width$}\n", "1", width = PLOT_WIDTH - 1));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LinearCurve;
+
+    #[test]
+    fn test_render_list() {
+        let mut curves = CurvesConfig::default();
+        let list = render_list(&curves).unwrap();
+        assert!(list.contains("* perceptual\n"));
+        assert!(list.contains("  srgb\n"));
+        assert!(!list.contains("Custom curves"));
+
+        curves.default = "soft".to_string();
+        curves.custom.insert("soft".to_string(), crate::CurveConfig::Gamma { gamma: Some(1.5), lut: false });
+        let list = render_list(&curves).unwrap();
+        assert!(list.contains("  perceptual\n"));
+        assert!(list.ends_with("Custom curves:\n* soft: {\"type\":\"gamma\",\"gamma\":1.5,\"lut\":false}\n"));
+    }
+
+    #[test]
+    fn test_render_table() {
+        let table = render_table(&LinearCurve, 3);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("round-trip"));
+        assert!(lines[2].trim_start().starts_with("0.500      0.5000"));
+    }
+
+    #[test]
+    fn test_render_plot_linear_is_diagonal() {
+        let plot = render_plot(&LinearCurve);
+        let lines: Vec<&str> = plot.lines().collect();
+        assert_eq!(lines.len(), PLOT_HEIGHT + 2);
+        assert!(lines[0].ends_with('*'));
+        assert!(lines[PLOT_HEIGHT - 1].starts_with("0 |*"));
+        assert!(lines.iter().all(|line| line.len() <= PLOT_WIDTH + 3));
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::IsTerminal;
+use anyhow::{bail, Result};
+use crate::Config;
+
+#[derive(clap::Args, Debug)]
+pub struct DoctorOpts {}
+
+pub async fn run(_opts: DoctorOpts, config: Config) -> Result<()> {
+    let registry = super::default_registry(&config)?;
+
+    let results = registry.health_check_all().await;
+    let mut names: Vec<&String> = results.keys().collect();
+    names.sort();
+
+    let color = std::io::stdout().is_terminal();
+    let mut failed = 0;
+    println!("Checking {} provider(s):", names.len());
+    for name in names {
+        match &results[name] {
+            Ok(()) => {
+                let capabilities = registry.get(name).map(|provider| provider.capabilities()).unwrap_or_default();
+                println!("  {} {} ({})", paint("✓", "32", color), name, capabilities);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("  {} {}: {}", paint("✗", "31", color), name, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} provider(s) failed their health check", failed);
+    }
+    Ok(())
+}
+
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use crate::config::{Config, CurvesConfig, KasaConfig, LifxConfig, MqttConfig, PipewireConfig, WledConfig, YeelightConfig, EsphomeConfig, GoveeConfig, HomeAssistantConfig};
+
+#[derive(clap::Args, Debug)]
+pub struct InitOpts {
+    /// Overwrite an existing config.toml
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// The sections worth showing a new user; per-light overrides start empty.
+#[derive(Serialize)]
+struct StarterConfig {
+    pipewire: PipewireConfig,
+    curves: CurvesConfig,
+    lifx: LifxConfig,
+    kasa: KasaConfig,
+    mqtt: MqttConfig,
+    wled: WledConfig,
+    yeelight: YeelightConfig,
+    esphome: EsphomeConfig,
+    govee: GoveeConfig,
+    homeassistant: HomeAssistantConfig,
+}
+
+const HEADER: &str = "\
+# lightwire configuration
+#
+# Every value below is the built-in default. Per-light overrides go in
+# [lights.lights.\"<light id>\"] tables, e.g.
+#
+#   [lights.lights.\"lifx:d073d5123456\"]
+#   curve = \"gamma\"
+#   min_brightness = 0.1
+#   mute_action = \"power_off\"
+
+";
+
+pub async fn run(opts: InitOpts, dry_run: bool) -> Result<()> {
+    let config_dir = Config::user_config_dir();
+    let path = config_dir.join("config.toml");
+
+    if path.exists() && !opts.force {
+        bail!("{} already exists (use --force to overwrite)", path.display());
+    }
+
+    let contents = starter_config()?;
+
+    if dry_run {
+        println!("DRY RUN: Would write {}:\n{}", path.display(), contents);
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&config_dir)
+        .with_context(|| format!("Failed to create {}", config_dir.display()))?;
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}
+
+fn starter_config() -> Result<String> {
+    let starter = StarterConfig {
+        pipewire: PipewireConfig::default(),
+        curves: CurvesConfig::default(),
+        lifx: LifxConfig::default(),
+        kasa: KasaConfig::default(),
+        mqtt: MqttConfig::default(),
+        wled: WledConfig::default(),
+        yeelight: YeelightConfig::default(),
+        esphome: EsphomeConfig::default(),
+        govee: GoveeConfig::default(),
+        homeassistant: HomeAssistantConfig::default(),
+    };
+    let curves_comment = format!(
+        "# Volume-to-brightness curve, one of: {}\n[curves]\n",
+        crate::curves::BUILTIN_CURVES.join(", ")
+    );
+    let body = toml::to_string_pretty(&starter)?
+        .replace("[pipewire]\n", "# Where drop-ins are written, how their nodes are named, and how often\n# volume changes are sent on to lights; volume_scale = \"db\" makes curves\n# follow perceived loudness\n[pipewire]\n")
+        .replace("[curves]\n", &curves_comment)
+        .replace("[lifx]\n", "# LIFX LAN discovery\n[lifx]\n")
+        .replace("[kasa]\n", "# TP-Link Kasa bulbs, disabled unless enabled = true\n[kasa]\n")
+        .replace("[mqtt]\n", "# Zigbee2MQTT lights via an MQTT broker, disabled unless enabled = true\n[mqtt]\n")
+        .replace("[wled]\n", "# WLED controllers by host, e.g. hosts = [\"192.168.1.40\"]\n[wled]\n")
+        .replace("[yeelight]\n", "# Yeelight bulbs with LAN Control on, disabled unless enabled = true\n[yeelight]\n")
+        .replace("[esphome]\n", "# ESPHome devices over the native API, e.g. devices = [{ host = \"den.local\" }]\n[esphome]\n")
+        .replace("[govee]\n", "# Govee lights with the LAN API on; needs a build with --features govee\n[govee]\n")
+        .replace("[homeassistant]\n", "# Every light.* entity in Home Assistant; set token to a long-lived access token\n[homeassistant]\n");
+    Ok(format!("{}{}", HEADER, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starter_config_round_trips() {
+        let contents = starter_config().unwrap();
+        let config: Config = toml::from_str(&contents).unwrap();
+        assert_eq!(config.pipewire.node_prefix, "lightwire");
+        assert_eq!(config.curves.default, "perceptual");
+        assert_eq!(config.lifx.port, 56700);
+    }
+}
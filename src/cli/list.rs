@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use serde::Serialize;
+use crate::{Config, Light};
+
+#[derive(clap::Args, Debug)]
+pub struct ListOpts {
+    /// Print lights as a JSON array
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct LightInfo {
+    id: String,
+    label: String,
+    provider: String,
+    brightness: f32,
+    power: bool,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl LightInfo {
+    fn from_light(light: &dyn Light) -> Self {
+        let state = light.state();
+        Self {
+            id: light.id().0.clone(),
+            label: light.label().to_string(),
+            provider: light.provider_name().to_string(),
+            brightness: state.brightness.as_f32(),
+            power: state.power,
+            metadata: light.metadata().cloned().unwrap_or_default(),
+        }
+    }
+}
+
+pub async fn run(opts: ListOpts, config: Config) -> Result<()> {
+    let registry = super::default_registry(&config)?;
+
+    let lights = super::discover_with_progress(&registry).await;
+
+    if opts.json {
+        let infos: Vec<LightInfo> = lights.iter().map(|light| LightInfo::from_light(light.as_ref())).collect();
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+        return Ok(());
+    }
+
+    if lights.is_empty() {
+        println!("No lights found on the network.");
+        return Ok(());
+    }
+
+    println!("Found {} light(s):", lights.len());
+    for light in &lights {
+        let state = light.state();
+        println!("  - {} ({}): brightness={:.2}, power={}",
+            light.label(),
+            light.id().0,
+            state.brightness.as_f32(),
+            state.power
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+pub mod completions;
+pub mod config;
+pub mod control;
+pub mod curve;
+pub mod doctor;
+pub mod init;
+pub mod list;
+pub mod populate;
+pub mod set;
+pub mod shutdown;
+pub mod status;
+pub mod sync;
+pub mod sync_to_light;
+pub mod sync_to_pipewire;
+pub mod systemd;
+
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use tokio::sync::mpsc;
+use crate::{Config, DiscoveryProgress, DropinConfig, Light, ProviderRegistry, pipewire::dedupe_slugs, provider::factory};
+
+pub use completions::CompletionsOpts;
+pub use config::ConfigOpts;
+pub use control::{ControlOpts, LightControlOpts, SyncControl};
+pub use curve::CurveOpts;
+pub use doctor::DoctorOpts;
+pub use init::InitOpts;
+pub use list::ListOpts;
+pub use populate::PopulateOpts;
+pub use set::SetOpts;
+pub use shutdown::Shutdown;
+pub use status::StatusOpts;
+pub use sync::SyncOpts;
+pub use sync_to_light::SyncToLightOpts;
+pub use sync_to_pipewire::SyncToPipewireOpts;
+pub use systemd::SystemdOpts;
+
+#[derive(Parser, Debug)]
+#[command(name = "lightwire")]
+#[command(about = "Control smart-bulb brightness as virtual PipeWire node's volume", long_about = None)]
+pub struct Cli {
+    #[arg(short, long)]
+    pub verbose: bool,
+    #[arg(long)]
+    pub dry_run: bool,
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Serve Prometheus metrics at http://ADDR/metrics, e.g. 127.0.0.1:9187
+    #[cfg(feature = "metrics")]
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<std::net::SocketAddr>,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Populate(PopulateOpts),
+    SyncToPipewire(SyncToPipewireOpts),
+    SyncToLight(SyncToLightOpts),
+    /// Sync lights and PipeWire both ways without feedback loops
+    Sync(SyncOpts),
+    /// Pause a running sync, which keeps watching but stops writing to lights
+    Pause(ControlOpts),
+    /// Resume a paused sync
+    Resume(ControlOpts),
+    /// Show each light's state next to its node's volume, and whether a sync is running
+    Status(StatusOpts),
+    /// Take a light out of a running sync without restarting it
+    Disable(LightControlOpts),
+    /// Put a disabled light back into a running sync
+    Enable(LightControlOpts),
+    /// Set a single light's brightness
+    Set(SetOpts),
+    /// List discovered lights
+    List(ListOpts),
+    /// Check that each provider can reach the network
+    Doctor(DoctorOpts),
+    /// Inspect or edit the config file
+    Config(ConfigOpts),
+    /// Inspect brightness curves
+    #[command(alias = "curves")]
+    Curve(CurveOpts),
+    /// Write a starter config.toml
+    Init(InitOpts),
+    /// Generate a systemd user unit that keeps a sync running
+    Systemd(SystemdOpts),
+    /// Print a shell completion script
+    Completions(CompletionsOpts),
+}
+
+pub async fn run(cli: Cli) -> Result<()> {
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = cli.metrics_addr {
+        crate::metrics::install(addr)?;
+    }
+    match cli.command {
+        Commands::Populate(opts) => populate::run(opts, load_config(cli.config.as_deref())?, cli.dry_run).await,
+        Commands::SyncToPipewire(opts) => sync_to_pipewire::run(opts, load_config(cli.config.as_deref())?, cli.dry_run).await,
+        Commands::SyncToLight(opts) => sync_to_light::run(opts, load_config(cli.config.as_deref())?, cli.dry_run).await,
+        Commands::Sync(opts) => sync::run(opts, load_config(cli.config.as_deref())?, cli.dry_run).await,
+        Commands::Pause(opts) => control::run(opts, "pause").await,
+        Commands::Resume(opts) => control::run(opts, "resume").await,
+        Commands::Status(opts) => status::run(opts, load_config(cli.config.as_deref())?).await,
+        Commands::Disable(opts) => control::run_light(opts, cli.config.as_deref(), false, cli.dry_run).await,
+        Commands::Enable(opts) => control::run_light(opts, cli.config.as_deref(), true, cli.dry_run).await,
+        Commands::Set(opts) => set::run(opts, load_config(cli.config.as_deref())?, cli.dry_run).await,
+        Commands::List(opts) => list::run(opts, load_config(cli.config.as_deref())?).await,
+        Commands::Doctor(opts) => doctor::run(opts, load_config(cli.config.as_deref())?).await,
+        Commands::Config(opts) => config::run(opts, cli.config.as_deref(), cli.dry_run).await,
+        Commands::Curve(opts) => curve::run(opts, load_config(cli.config.as_deref())?).await,
+        Commands::Init(opts) => init::run(opts, cli.dry_run).await,
+        Commands::Systemd(opts) => systemd::run(opts, cli.config.as_deref(), cli.dry_run).await,
+        Commands::Completions(opts) => completions::run(opts).await,
+    }
+}
+
+/// Loads the file given with `--config`, or the default config location otherwise.
+pub fn load_config(path: Option<&str>) -> Result<Config> {
+    match path {
+        Some(path) => Ok(Config::load_from_path(PathBuf::from(shellexpand::tilde(path).into_owned()))?),
+        None => Ok(Config::load()?),
+    }
+}
+
+/// Logs go to stderr so command output on stdout stays pipeable. `RUST_LOG`
+/// directives (e.g. `lightwire::provider::lifx=trace`) refine the default
+/// level set by `--verbose`.
+pub fn init_tracing(verbose: bool) {
+    let level = if verbose { tracing::Level::DEBUG } else { tracing::Level::INFO };
+    let filter = tracing_subscriber::EnvFilter::builder()
+        .with_default_directive(tracing_subscriber::filter::LevelFilter::from_level(level).into())
+        .from_env_lossy();
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+/// One drop-in per light, named after its configured label if it has one,
+/// with node names made unique across them.
+pub fn dropins_for(config: &Config, lights: &[Box<dyn Light>]) -> Vec<DropinConfig> {
+    let mut dropins: Vec<DropinConfig> = lights
+        .iter()
+        .map(|light| {
+            DropinConfig::new(
+                light.provider_name().to_string(),
+                configured_label(config, light.as_ref()).unwrap_or(light.label()).to_string(),
+                light.id().clone(),
+                config.pipewire.node_prefix.clone(),
+                config.curve_name_for_light(light.id()).to_string(),
+            )
+        })
+        .collect();
+    dedupe_slugs(&mut dropins);
+    dropins
+}
+
+/// The label set for the light in `[lights]`, if any.
+pub fn configured_label<'a>(config: &'a Config, light: &dyn Light) -> Option<&'a str> {
+    config.lights.get(light.id()).and_then(|light_config| light_config.label.as_deref())
+}
+
+/// A registry of the providers named by `--provider` (comma-separated), or of
+/// every provider enabled in the config when none are named.
+pub fn registry_for(config: &Config, providers: Option<&str>) -> Result<ProviderRegistry> {
+    let names = match providers {
+        Some(list) => factory::parse_names(list)?,
+        None => factory::enabled_names(config),
+    };
+    let mut registry = ProviderRegistry::new();
+    // Groups can only be presented when every member's provider is registered.
+    let groups = config
+        .light_groups()
+        .into_iter()
+        .filter(|group| group.members.iter().all(|member| member.provider().is_some_and(|p| names.contains(&p))))
+        .collect();
+    registry.set_groups(groups);
+    for name in names {
+        registry.register(factory::build(name, config)?)?;
+    }
+    Ok(registry)
+}
+
+/// Discovers every light, showing each provider's progress on stderr when
+/// it is a terminal.
+pub async fn discover_with_progress(registry: &ProviderRegistry) -> Vec<Box<dyn Light>> {
+    if !std::io::stderr().is_terminal() {
+        return registry.discover_all_detailed().await.0;
+    }
+    let (tx, mut rx) = mpsc::channel(64);
+    let printer = tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                DiscoveryProgress::Started { provider } => eprintln!("Searching {}...", provider),
+                DiscoveryProgress::Found { provider, id, label: Some(label) } => {
+                    eprintln!("  {}: found {} ({})", provider, label, id.0)
+                }
+                DiscoveryProgress::Found { provider, id, label: None } => eprintln!("  {}: found {}", provider, id.0),
+                DiscoveryProgress::Finished { provider, count } => eprintln!("  {}: done, {} light(s)", provider, count),
+                DiscoveryProgress::Failed { provider, error } => eprintln!("  {}: failed: {}", provider, error),
+            }
+        }
+    });
+    let (lights, _errors) = registry.discover_all_with_progress(Some(&tx)).await;
+    drop(tx);
+    let _ = printer.await;
+    lights
+}
+
+pub fn default_registry(config: &Config) -> Result<ProviderRegistry> {
+    registry_for(config, None)
+}
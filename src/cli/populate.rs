@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use crate::{DropinConfig, LightFilter, LightId, ManagedNode};
+use crate::config::Config;
+
+#[derive(clap::Args, Debug)]
+pub struct PopulateOpts {
+    /// Comma-separated providers to use instead of those enabled in the config
+    #[arg(long)]
+    pub provider: Option<String>,
+    /// Only use lights matching a label pattern such as `Office*`, or
+    /// `key=pattern` terms like `provider=lifx,group=Upstairs`
+    #[arg(long = "match", value_name = "PATTERN")]
+    pub filter: Option<LightFilter>,
+    #[arg(long)]
+    pub config_dir: Option<String>,
+    /// Also remove drop-ins for lights that were not discovered
+    #[arg(long)]
+    pub clean: bool,
+    #[arg(long, default_value = "true")]
+    pub set_brightness: bool,
+    /// Write drop-ins to a directory, a `.tar` archive, or `-` for stdout
+    /// instead of the live PipeWire config directory
+    #[arg(long)]
+    pub output: Option<String>,
+    /// Restart PipeWire afterwards if any drop-in changed, to load the new nodes
+    #[arg(long)]
+    pub restart: bool,
+    /// Rename lights whose name differs from the label set for them in the config
+    #[arg(long)]
+    pub push_labels: bool,
+}
+
+/// Where generated drop-ins go.
+enum Output {
+    Dir { path: PathBuf, live: bool },
+    Tar(PathBuf),
+    Stdout,
+}
+
+impl Output {
+    fn from_opts(opts: &PopulateOpts, config: &Config) -> Self {
+        let expand = |p: &str| PathBuf::from(shellexpand::tilde(p).into_owned());
+        match opts.output.as_deref() {
+            Some("-") => Output::Stdout,
+            Some(p) if p.ends_with(".tar") => Output::Tar(expand(p)),
+            Some(p) => Output::Dir { path: expand(p), live: false },
+            None => Output::Dir {
+                path: opts.config_dir.as_deref().map(expand).unwrap_or_else(|| config.pipewire_config_dir()),
+                live: true,
+            },
+        }
+    }
+
+    /// Fails if drop-ins couldn't be written to the directory, so the user
+    /// gets a hint before discovery instead of a raw IO error after it.
+    fn preflight(&self, dry_run: bool) -> Result<()> {
+        let Output::Dir { path, live } = self else {
+            return Ok(());
+        };
+        if super::config::is_writable(path) {
+            return Ok(());
+        }
+        let flag = if *live { "--config-dir" } else { "--output" };
+        if dry_run {
+            println!("DRY RUN: {} is not writable, so a real run would fail", path.display());
+            return Ok(());
+        }
+        bail!(
+            "{} is not writable and can't be created. Check that you own it or its parent \
+             (ls -ld {}), or pass {} to write drop-ins somewhere else.",
+            path.display(),
+            path.display(),
+            flag
+        )
+    }
+
+    /// Progress messages go to stderr when stdout carries the configs.
+    fn status(&self, message: impl Display) {
+        match self {
+            Output::Stdout => eprintln!("{}", message),
+            _ => println!("{}", message),
+        }
+    }
+}
+
+pub async fn run(opts: PopulateOpts, config: Config, dry_run: bool) -> Result<()> {
+    if opts.clean && opts.filter.is_some() {
+        bail!("--clean cannot be combined with --match; it would remove the drop-ins of every light the pattern leaves out");
+    }
+
+    let registry = super::registry_for(&config, opts.provider.as_deref())?;
+    let output = Output::from_opts(&opts, &config);
+    output.preflight(dry_run)?;
+
+    let lights = registry.discover_filtered(&opts.filter.clone().unwrap_or_default()).await?;
+
+    if lights.is_empty() {
+        output.status("No lights found on the network.");
+        return Ok(());
+    }
+
+    let lights = config.lights.retain_enabled(lights);
+
+    if opts.push_labels {
+        for light in &lights {
+            let Some(label) = super::configured_label(&config, light.as_ref()).filter(|label| *label != light.label()) else {
+                continue;
+            };
+            if dry_run {
+                output.status(format!("DRY RUN: Would rename {} ({}) to {}", light.label(), light.id().0, label));
+                continue;
+            }
+            match registry.set_label(light.provider_name(), light.id(), label).await {
+                Ok(()) => output.status(format!("Renamed {} ({}) to {}", light.label(), light.id().0, label)),
+                Err(e) => tracing::warn!("Failed to rename {} ({}): {}", light.label(), light.id().0, e),
+            }
+        }
+    }
+
+    let curves = config.curves.registry()?;
+    let dropins = super::dropins_for(&config, &lights);
+    for (light, dropin) in lights.iter().zip(&dropins) {
+        curves.resolve(&dropin.curve)?;
+
+        output.status(format!("Found: {} ({})", light.label(), light.id().0));
+
+        if let Output::Stdout = &output {
+            println!("# ==> {} <==", dropin.filename());
+            println!("{}", dropin.generate());
+        }
+    }
+
+    if let Output::Dir { path, live } = &output {
+        if dry_run {
+            println!("DRY RUN: Would write to: {}", path.display());
+        }
+        let plan = Reconcile::plan(path, &config.pipewire.node_prefix, &dropins, opts.clean)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        plan.apply(path, dry_run)?;
+
+        println!("\n{} light(s) configured: {}", lights.len(), plan.summary());
+        if plan.stale > 0 {
+            println!("Kept {} drop-in(s) for lights that were not discovered (use --clean to remove them)", plan.stale);
+        }
+        println!("PipeWire config directory: {}", path.display());
+        if plan.is_empty() {
+            println!("No changes.");
+        } else if *live && opts.restart {
+            restart_pipewire(dry_run).await?;
+            if !dry_run {
+                verify_nodes(&config.pipewire.node_prefix, &dropins, opts.clean).await;
+            }
+        } else if *live {
+            println!("\nTo load new nodes, run: {}", RESTART_COMMAND.join(" "));
+        }
+        return Ok(());
+    }
+
+    if let Output::Tar(path) = &output {
+        if dry_run {
+            println!("DRY RUN: Would write {} drop-in(s) to {}", dropins.len(), path.display());
+        } else {
+            write_tar(path, &dropins).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("Wrote {} drop-in(s) to {}", dropins.len(), path.display());
+        }
+    }
+
+    output.status(format!("\n{} light(s) configured.", lights.len()));
+
+    Ok(())
+}
+
+/// Restarting the service is the only way PipeWire picks up new drop-ins.
+const RESTART_COMMAND: [&str; 4] = ["systemctl", "--user", "restart", "pipewire"];
+
+async fn restart_pipewire(dry_run: bool) -> Result<()> {
+    let command = RESTART_COMMAND.join(" ");
+    if dry_run {
+        println!("DRY RUN: Would run: {}", command);
+        return Ok(());
+    }
+    let output = tokio::process::Command::new(RESTART_COMMAND[0])
+        .args(&RESTART_COMMAND[1..])
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", command))?;
+    if !output.status.success() {
+        bail!(
+            "{} exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    println!("\nRestarted PipeWire to load the new nodes.");
+    Ok(())
+}
+
+/// How long PipeWire gets to bring the drop-ins' nodes up after a restart.
+const RESTART_SETTLE: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Waits for the running lightwire nodes to match the drop-ins, then
+/// reports any that are missing or, after `--clean`, that should have gone
+/// away. Without it, nodes of kept stale drop-ins are expected to remain.
+async fn verify_nodes(node_prefix: &str, dropins: &[DropinConfig], clean: bool) {
+    let expected: Vec<String> = dropins.iter().map(DropinConfig::node_name).collect();
+    let deadline = tokio::time::Instant::now() + RESTART_SETTLE;
+    let (missing, leftover) = loop {
+        let running = match crate::managed_nodes(node_prefix).await {
+            Ok(running) => running,
+            Err(e) => {
+                tracing::warn!("Could not list PipeWire nodes to check the restart: {}", e);
+                return;
+            }
+        };
+        let (missing, mut leftover) = node_differences(&expected, &running);
+        if !clean {
+            leftover.clear();
+        }
+        if (missing.is_empty() && leftover.is_empty()) || tokio::time::Instant::now() >= deadline {
+            break (missing, leftover);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    };
+    if missing.is_empty() && leftover.is_empty() {
+        println!("All {} node(s) are up.", expected.len());
+        return;
+    }
+    for node in missing {
+        println!("  - {} did not appear", node);
+    }
+    for node in leftover {
+        println!("  - {} is still present without a drop-in", node);
+    }
+}
+
+/// Expected node names that aren't running, and running nodes that aren't expected.
+fn node_differences(expected: &[String], running: &[ManagedNode]) -> (Vec<String>, Vec<String>) {
+    let missing = expected
+        .iter()
+        .filter(|name| !running.iter().any(|node| &node.name == *name))
+        .cloned()
+        .collect();
+    let leftover = running
+        .iter()
+        .filter(|node| !expected.contains(&node.name))
+        .map(|node| node.name.clone())
+        .collect();
+    (missing, leftover)
+}
+
+/// The writes and deletes that bring a drop-in directory in line with the
+/// discovered lights. Existing drop-ins are matched by the light id inside them.
+#[derive(Debug, Default)]
+struct Reconcile {
+    added: Vec<DropinConfig>,
+    /// New config, plus the file it replaces (which may have a different name).
+    changed: Vec<(DropinConfig, PathBuf)>,
+    removed: Vec<PathBuf>,
+    unchanged: usize,
+    /// Drop-ins for lights that weren't discovered, kept because pruning was off.
+    stale: usize,
+}
+
+impl Reconcile {
+    /// With `prune`, drop-ins for undiscovered lights and unreadable
+    /// `<prefix>-*.conf` files are removed.
+    fn plan(dir: &Path, node_prefix: &str, dropins: &[DropinConfig], prune: bool) -> std::io::Result<Self> {
+        let mut plan = Self::default();
+        let mut existing: HashMap<LightId, (PathBuf, String)> = HashMap::new();
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        for path in entries {
+            let filename = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            if !filename.starts_with(&format!("{}-", node_prefix)) || !filename.ends_with(".conf") {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)?;
+            match DropinConfig::parse(&contents) {
+                Ok(parsed) => {
+                    if let Some((duplicate, _)) = existing.insert(parsed.light_id, (path, contents)) {
+                        plan.drop_stale(duplicate, prune);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Could not read {}: {}", filename, e);
+                    plan.drop_stale(path, prune);
+                }
+            }
+        }
+
+        for dropin in dropins {
+            match existing.remove(&dropin.light_id) {
+                Some((path, contents)) if path == dir.join(dropin.filename()) && contents == dropin.generate() => {
+                    plan.unchanged += 1;
+                }
+                Some((path, _)) => plan.changed.push((dropin.clone(), path)),
+                None => plan.added.push(dropin.clone()),
+            }
+        }
+        for (path, _) in existing.into_values() {
+            plan.drop_stale(path, prune);
+        }
+        plan.removed.sort();
+        Ok(plan)
+    }
+
+    fn drop_stale(&mut self, path: PathBuf, prune: bool) {
+        if prune {
+            self.removed.push(path);
+        } else {
+            self.stale += 1;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "+{} ~{} -{} ={}",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged
+        )
+    }
+
+    fn apply(&self, dir: &Path, dry_run: bool) -> Result<()> {
+        for path in &self.removed {
+            let filename = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            if dry_run {
+                println!("Would remove: {}", filename);
+            } else {
+                match std::fs::remove_file(path) {
+                    Ok(_) => println!("Removed: {}", filename),
+                    Err(e) => tracing::warn!("Failed to remove {}: {}", filename, e),
+                }
+            }
+        }
+
+        let writes = self
+            .added
+            .iter()
+            .map(|dropin| (dropin, None, "create"))
+            .chain(self.changed.iter().map(|(dropin, old)| (dropin, Some(old), "update")));
+        for (dropin, old, verb) in writes {
+            if dry_run {
+                println!("Would {}: {}", verb, dropin.filename());
+                println!("--- Config ---");
+                println!("{}", dropin.generate());
+                println!("--- End Config ---");
+                continue;
+            }
+            std::fs::create_dir_all(dir)?;
+            if let Some(old) = old.filter(|old| **old != dir.join(dropin.filename())) {
+                std::fs::remove_file(old)?;
+            }
+            dropin.write_to(dir)?;
+            println!("{}: {}", if verb == "create" { "Created" } else { "Updated" }, dropin.filename());
+        }
+        Ok(())
+    }
+}
+
+/// Packs the drop-ins into a flat tar archive, one `.conf` per entry.
+fn write_tar(path: &Path, dropins: &[DropinConfig]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut archive = tar::Builder::new(std::fs::File::create(path)?);
+    for dropin in dropins {
+        let contents = dropin.generate();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        );
+        header.set_cksum();
+        archive.append_data(&mut header, dropin.filename(), contents.as_bytes())?;
+    }
+    archive.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_differences() {
+        let expected = vec!["lightwire.lifx.desk".to_string(), "lightwire.lifx.lamp".to_string()];
+        let running = vec![
+            ManagedNode { id: 40, name: "lightwire.lifx.desk".to_string() },
+            ManagedNode { id: 41, name: "lightwire.lifx.old".to_string() },
+        ];
+        let (missing, leftover) = node_differences(&expected, &running);
+        assert_eq!(missing, vec!["lightwire.lifx.lamp"]);
+        assert_eq!(leftover, vec!["lightwire.lifx.old"]);
+    }
+
+    fn dropin(id: &str, label: &str, curve: &str) -> DropinConfig {
+        DropinConfig::new(
+            "lifx".to_string(),
+            label.to_string(),
+            LightId(id.to_string()),
+            "lightwire".to_string(),
+            curve.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let dir = std::env::temp_dir().join(format!("lightwire-{}-reconcile", std::process::id()));
+        let desk = dropin("lifx:desk", "Desk", "perceptual");
+        let shelf = dropin("lifx:shelf", "Shelf", "perceptual");
+        let gone = dropin("lifx:gone", "Gone", "perceptual");
+
+        let plan = Reconcile::plan(&dir, "lightwire", &[desk.clone(), gone.clone()], false).unwrap();
+        assert_eq!(plan.summary(), "+2 ~0 -0 =0");
+        plan.apply(&dir, false).unwrap();
+
+        let plan = Reconcile::plan(&dir, "lightwire", &[desk.clone(), gone.clone()], false).unwrap();
+        assert!(plan.is_empty());
+        assert_eq!(plan.summary(), "+0 ~0 -0 =2");
+
+        let renamed = dropin("lifx:desk", "Desk Lamp", "gamma");
+        let next = [renamed.clone(), shelf.clone()];
+        let kept = Reconcile::plan(&dir, "lightwire", &next, false).unwrap();
+        assert_eq!((kept.summary().as_str(), kept.stale), ("+1 ~1 -0 =0", 1));
+
+        let plan = Reconcile::plan(&dir, "lightwire", &next, true).unwrap();
+        assert_eq!(plan.summary(), "+1 ~1 -1 =0");
+        plan.apply(&dir, false).unwrap();
+
+        let mut files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, vec![renamed.filename(), shelf.filename()]);
+    }
+
+    #[test]
+    fn test_write_tar() {
+        let dropin = dropin("lifx:abc", "Desk Lamp", "perceptual");
+        let path = std::env::temp_dir().join(format!("lightwire-{}-dropins.tar", std::process::id()));
+        write_tar(&path, std::slice::from_ref(&dropin)).unwrap();
+
+        let mut archive = tar::Archive::new(std::fs::File::open(&path).unwrap());
+        let names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(names, vec![dropin.filename()]);
+    }
+
+    #[test]
+    fn test_preflight_unwritable_dir() {
+        // A directory can't be created below a regular file, whoever we run as.
+        let file = std::env::temp_dir().join(format!("lightwire-{}-preflight", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let output = Output::Dir { path: file.join("pipewire.conf.d"), live: true };
+
+        let error = output.preflight(false).unwrap_err().to_string();
+        assert!(error.contains("--config-dir"), "{}", error);
+        assert!(output.preflight(true).is_ok());
+        assert!(Output::Dir { path: std::env::temp_dir(), live: true }.preflight(false).is_ok());
+        std::fs::remove_file(&file).unwrap();
+    }
+}
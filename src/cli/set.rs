@@ -0,0 +1,91 @@
+use anyhow::Result;
+use crate::provider::group::{group_name, GROUP_PROVIDER};
+use crate::{Brightness, Config, LightId};
+
+#[derive(clap::Args, Debug)]
+pub struct SetOpts {
+    /// Light id, e.g. lifx:d073d5123456
+    pub light_id: String,
+    /// Brightness as 0.0-1.0 or N%
+    #[arg(value_parser = parse_brightness)]
+    pub brightness: Brightness,
+}
+
+fn parse_brightness(s: &str) -> std::result::Result<Brightness, String> {
+    let s = s.trim();
+    if let Some(percent) = s.strip_suffix('%') {
+        let percent: f32 = percent.trim().parse().map_err(|_| format!("invalid percentage: {}", s))?;
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!("percentage out of range: {}", s));
+        }
+        Ok(Brightness::new(percent / 100.0))
+    } else {
+        let value: f32 = s.parse().map_err(|_| format!("invalid brightness: {}", s))?;
+        if !(0.0..=1.0).contains(&value) {
+            return Err(format!("brightness out of range 0.0-1.0: {}", s));
+        }
+        Ok(Brightness::new(value))
+    }
+}
+
+/// The providers setting `id` needs, comma-separated: the one named by its
+/// prefix, or for a group every member's.
+fn providers_for(config: &Config, id: &LightId, provider_name: &str) -> Result<String> {
+    if provider_name != GROUP_PROVIDER {
+        return Ok(provider_name.to_string());
+    }
+    let group = config
+        .light_groups()
+        .into_iter()
+        .find(|group| Some(group.name.as_str()) == group_name(id))
+        .ok_or_else(|| anyhow::anyhow!("No group named '{}' in the config", id.0))?;
+    let mut names: Vec<&str> = group.members.iter().filter_map(LightId::provider).collect();
+    names.sort();
+    names.dedup();
+    Ok(names.join(","))
+}
+
+pub async fn run(opts: SetOpts, config: Config, dry_run: bool) -> Result<()> {
+    let id = LightId(opts.light_id);
+    let provider_name = id
+        .provider()
+        .ok_or_else(|| anyhow::anyhow!("Light id '{}' has no provider prefix (expected e.g. lifx:...)", id.0))?
+        .to_string();
+
+    let registry = super::registry_for(&config, Some(&providers_for(&config, &id, &provider_name)?))?;
+
+    if dry_run {
+        println!("DRY RUN: Would set {} to {}%", id.0, opts.brightness.as_percent());
+        return Ok(());
+    }
+
+    registry.set_brightness(&provider_name, &id, opts.brightness).await?;
+    println!("Set {} to {}%", id.0, opts.brightness.as_percent());
+
+    let state = registry.get_state(&provider_name, &id).await?;
+    println!("  - {} ({}): brightness={:.2}, power={}",
+        state.label,
+        state.id.0,
+        state.brightness.as_f32(),
+        state.power
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_providers_for() {
+        let mut config = Config::default();
+        config.groups.insert("desk".to_string(), vec!["lifx:a".to_string(), "kasa:b".to_string(), "lifx:c".to_string()]);
+
+        let lamp = LightId("kasa:d073d5123456".to_string());
+        assert_eq!(providers_for(&config, &lamp, "kasa").unwrap(), "kasa");
+        let desk = LightId("group:desk".to_string());
+        assert_eq!(providers_for(&config, &desk, GROUP_PROVIDER).unwrap(), "kasa,lifx");
+        assert!(providers_for(&config, &LightId("group:attic".to_string()), GROUP_PROVIDER).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+/// Listens for SIGINT and SIGTERM.
+///
+/// Handlers are installed on construction, so a signal that arrives while a
+/// sync pass is mid-write is held until the loop next waits on `recv`.
+pub struct Shutdown {
+    interrupt: Signal,
+    terminate: Signal,
+}
+
+impl Shutdown {
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    pub async fn recv(&mut self) {
+        tokio::select! {
+            _ = self.interrupt.recv() => {}
+            _ = self.terminate.recv() => {}
+        }
+    }
+}
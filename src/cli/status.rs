@@ -0,0 +1,152 @@
+use anyhow::Result;
+use crate::{Config, LightFilter, Volume};
+use super::control::{self, ControlOpts};
+use super::sync_to_pipewire::SyncTarget;
+
+/// How far a node's volume may be from the one its light's brightness maps
+/// to and still count as in sync.
+const SYNC_TOLERANCE: f32 = 0.02;
+
+#[derive(clap::Args, Debug)]
+pub struct StatusOpts {
+    /// Comma-separated providers to use instead of those enabled in the config
+    #[arg(long)]
+    pub provider: Option<String>,
+    /// Only use lights matching a label pattern such as `Office*`, or
+    /// `key=pattern` terms like `provider=lifx,group=Upstairs`
+    #[arg(long = "match", value_name = "PATTERN")]
+    pub filter: Option<LightFilter>,
+    #[command(flatten)]
+    pub control: ControlOpts,
+}
+
+/// One light's line in the status table.
+#[derive(Debug)]
+struct Row {
+    light: String,
+    provider: String,
+    brightness: String,
+    power: String,
+    node: String,
+    volume: String,
+    sync: &'static str,
+}
+
+/// Shows each light next to its node, flagging the ones whose node volume
+/// doesn't match the light, along with the running sync's state if any.
+pub async fn run(opts: StatusOpts, config: Config) -> Result<()> {
+    let socket = opts.control.socket.unwrap_or_else(control::default_socket_path);
+    match control::send(&socket, "status").await {
+        Ok(reply) => println!("Sync: {}", reply),
+        Err(_) => println!("Sync: not running"),
+    }
+
+    let registry = super::registry_for(&config, opts.provider.as_deref())?;
+    let lights = registry.discover_filtered(&opts.filter.unwrap_or_default()).await?;
+    if lights.is_empty() {
+        println!("No lights found on the network.");
+        return Ok(());
+    }
+
+    let curves = config.curves.registry()?;
+    let dropins = super::dropins_for(&config, &lights);
+    let mut rows = Vec::new();
+    for (light, dropin) in lights.iter().zip(&dropins) {
+        let state = light.state();
+        let target = SyncTarget::new(&config, &curves, light.as_ref(), dropin)?;
+        let volume = target.controller.get_volume().await;
+        let sync = match &volume {
+            _ if !config.lights.is_enabled(light.id()) => "disabled",
+            Ok(volume) if in_sync(target.volume_for(&config, state.brightness), volume, state.power) => "ok",
+            Ok(_) => "MISMATCH",
+            Err(e) => {
+                tracing::debug!("Failed to read volume of {}: {}", dropin.node_name(), e);
+                "no node"
+            }
+        };
+        rows.push(Row {
+            light: format!("{} ({})", light.label(), light.id().0),
+            provider: light.provider_name().to_string(),
+            brightness: format!("{}%", state.brightness.as_percent()),
+            power: if state.power { "on" } else { "off" }.to_string(),
+            node: dropin.node_name(),
+            volume: match &volume {
+                Ok(volume) if volume.muted => format!("{:.2} (muted)", volume.value),
+                Ok(volume) => format!("{:.2}", volume.value),
+                Err(_) => "-".to_string(),
+            },
+            sync,
+        });
+    }
+
+    print!("{}", render_table(&rows));
+    let mismatched = rows.iter().filter(|row| row.sync == "MISMATCH").count();
+    if mismatched > 0 {
+        println!("\n{} light(s) out of sync with their node", mismatched);
+    }
+    Ok(())
+}
+
+/// Whether a node's volume matches its light: the volume the light's
+/// brightness maps to while it is on, and muted or silent while it is off.
+fn in_sync(expected: f32, volume: &Volume, power: bool) -> bool {
+    if !power {
+        return volume.muted || volume.value <= SYNC_TOLERANCE;
+    }
+    !volume.muted && (expected - volume.value).abs() <= SYNC_TOLERANCE
+}
+
+fn render_table(rows: &[Row]) -> String {
+    let header = ["LIGHT", "PROVIDER", "BRIGHTNESS", "POWER", "NODE", "VOLUME", "SYNC"];
+    let cells: Vec<[&str; 7]> = rows
+        .iter()
+        .map(|row| [&*row.light, &*row.provider, &*row.brightness, &*row.power, &*row.node, &*row.volume, row.sync])
+        .collect();
+    let mut widths = header.map(str::len);
+    for line in &cells {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut out = String::new();
+    for line in std::iter::once(&header).chain(&cells) {
+        let padded: Vec<String> = line.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell)).collect();
+        out.push_str(padded.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_sync() {
+        assert!(in_sync(0.5, &Volume::new(0.51), true));
+        assert!(!in_sync(0.5, &Volume::new(0.6), true));
+        assert!(!in_sync(0.5, &Volume::muted(0.5), true));
+        assert!(in_sync(0.5, &Volume::muted(0.5), false));
+        assert!(in_sync(0.5, &Volume::new(0.0), false));
+        assert!(!in_sync(0.5, &Volume::new(0.5), false));
+    }
+
+    #[test]
+    fn test_render_table() {
+        let row = Row {
+            light: "Desk (sim:1)".to_string(),
+            provider: "sim".to_string(),
+            brightness: "50%".to_string(),
+            power: "on".to_string(),
+            node: "lightwire.sim.desk".to_string(),
+            volume: "0.50".to_string(),
+            sync: "ok",
+        };
+        assert_eq!(
+            render_table(&[row]),
+            "LIGHT         PROVIDER  BRIGHTNESS  POWER  NODE                VOLUME  SYNC\n\
+             Desk (sim:1)  sim       50%         on     lightwire.sim.desk  0.50    ok\n"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use tokio::time::Instant;
+use crate::config::{Config, InitialSync};
+use crate::{LightFilter, LightId, LightState, VolumeMonitor};
+use super::sync_to_light::{schedule_boundary, Debouncer, LightTarget};
+use super::sync_to_pipewire::{push_volume, subscribe_all, SyncTarget};
+
+/// How far an inbound value may differ from the one just written and still
+/// count as its echo. Covers the 8-bit rounding of most bulbs and PipeWire's
+/// cubic volume scale.
+const ECHO_TOLERANCE: f32 = 0.02;
+
+#[derive(clap::Args, Debug)]
+pub struct SyncOpts {
+    /// Comma-separated providers to use instead of those enabled in the config
+    #[arg(long)]
+    pub provider: Option<String>,
+    /// Only use lights matching a label pattern such as `Office*`, or
+    /// `key=pattern` terms like `provider=lifx,group=Upstairs`
+    #[arg(long = "match", value_name = "PATTERN")]
+    pub filter: Option<LightFilter>,
+    /// How often to poll lights that can't push their state, in milliseconds
+    #[arg(long, default_value = "1000")]
+    pub interval: u64,
+    /// How long after a write an equal inbound change is treated as its echo, in milliseconds
+    #[arg(long, default_value = "1500")]
+    pub echo_window: u64,
+    /// Which side to copy to the other on startup; defaults to pipewire.initial_sync
+    #[arg(long, value_enum)]
+    pub initial_sync: Option<InitialSync>,
+    #[command(flatten)]
+    pub control: super::control::ControlOpts,
+}
+
+/// Remembers the last value written to each node and light, so the change
+/// it causes on the other side isn't synced straight back.
+#[derive(Debug)]
+pub struct EchoGuard {
+    window: Duration,
+    tolerance: f32,
+    written: HashMap<String, (f32, Instant)>,
+}
+
+impl EchoGuard {
+    pub fn new(window: Duration, tolerance: f32) -> Self {
+        Self { window, tolerance, written: HashMap::new() }
+    }
+
+    /// Records a value written to `key`, a node name or light id.
+    pub fn record(&mut self, key: &str, value: f32) {
+        self.written.insert(key.to_string(), (value, Instant::now()));
+    }
+
+    /// Whether an inbound `value` for `key` is just the echo of a recent
+    /// write. A write is kept for the whole window, since a light or node
+    /// may report the same change more than once.
+    pub fn is_echo(&mut self, key: &str, value: f32) -> bool {
+        let Some(&(written, at)) = self.written.get(key) else {
+            return false;
+        };
+        if at.elapsed() > self.window {
+            self.written.remove(key);
+            return false;
+        }
+        (value - written).abs() <= self.tolerance
+    }
+}
+
+/// The last brightness each light was synced at, pushed to its node or
+/// written from it, so polling pushes a light only when it has moved away
+/// from that value.
+#[derive(Debug, Default)]
+struct LastBrightness {
+    seen: HashMap<LightId, f32>,
+}
+
+impl LastBrightness {
+    fn record(&mut self, id: &LightId, brightness: f32) {
+        self.seen.insert(id.clone(), brightness);
+    }
+
+    /// Whether a polled `brightness` is more than `ECHO_TOLERANCE` from the
+    /// last synced value, recording it if so. Readings within the tolerance
+    /// leave the stored value alone, so a slow fade still adds up to a
+    /// change. The first reading is only a baseline.
+    fn changed(&mut self, id: &LightId, brightness: f32) -> bool {
+        let Some(last) = self.seen.get(id) else {
+            self.record(id, brightness);
+            return false;
+        };
+        let changed = (brightness - last).abs() > ECHO_TOLERANCE;
+        if changed {
+            self.record(id, brightness);
+        }
+        changed
+    }
+}
+
+/// A light synced in both directions through its node.
+struct Pair {
+    to_light: LightTarget,
+    to_pipewire: SyncTarget,
+}
+
+pub async fn run(opts: SyncOpts, config: Config, dry_run: bool) -> Result<()> {
+    let registry = Arc::new(super::registry_for(&config, opts.provider.as_deref())?);
+    let lights = registry.discover_filtered(&opts.filter.clone().unwrap_or_default()).await?;
+
+    if lights.is_empty() {
+        println!("No lights found on the network.");
+        return Ok(());
+    }
+
+    let lights = config.lights.retain_enabled(lights);
+    let curves = config.curves.registry()?;
+
+    println!("Found {} light(s):", lights.len());
+    let mut pairs: HashMap<String, Pair> = HashMap::new();
+    let dropins = super::dropins_for(&config, &lights);
+    for (light, dropin) in lights.iter().zip(&dropins) {
+        println!("  - {} ({})", light.label(), light.id().0);
+        let pair = Pair {
+            to_light: LightTarget::new(&config, &curves, &registry, light.as_ref())?,
+            to_pipewire: SyncTarget::new(&config, &curves, light.as_ref(), dropin)?,
+        };
+        pairs.insert(dropin.node_name(), pair);
+    }
+
+    println!("\nSyncing both directions...");
+    let mut guard = EchoGuard::new(Duration::from_millis(opts.echo_window), ECHO_TOLERANCE);
+    let mut last = LastBrightness::default();
+
+    // The monitor reports every node's volume when it first reads it. That
+    // first event is the startup sync when PipeWire is authoritative, and is
+    // only a baseline otherwise.
+    let initial_sync = opts.initial_sync.unwrap_or(config.pipewire.initial_sync);
+    let mut baseline: HashSet<String> = HashSet::new();
+    if initial_sync != InitialSync::FromPipewire {
+        baseline.extend(pairs.keys().cloned());
+    }
+    if initial_sync == InitialSync::FromLight {
+        for (node, pair) in &pairs {
+            let target = &pair.to_pipewire;
+            match registry.get_state(&target.provider, &target.id).await {
+                Ok(state) => {
+                    last.record(&target.id, state.brightness.as_f32());
+                    sync_state(&config, &mut guard, node, target, &state, dry_run).await;
+                }
+                Err(e) => tracing::warn!("Failed to read state of {} ({}): {}", target.label, target.id.0, e),
+            }
+        }
+    }
+
+    let (monitor, mut events) = VolumeMonitor::for_managed_nodes(&config.pipewire.node_prefix).await;
+    tokio::spawn(async move {
+        if let Err(e) = monitor.run().await {
+            tracing::error!("Volume monitor stopped: {}", e);
+        }
+    });
+
+    let targets: Vec<&SyncTarget> = pairs.values().map(|pair| &pair.to_pipewire).collect();
+    let (mut pushed, subscribed) = subscribe_all(&registry, &targets).await;
+    let polled: Vec<String> = pairs
+        .iter()
+        .filter(|(_, pair)| !subscribed.contains(&pair.to_pipewire.provider))
+        .map(|(node, _)| node.clone())
+        .collect();
+    let mut interval = tokio::time::interval(Duration::from_millis(opts.interval.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick is immediate; startup is covered by the initial sync.
+    interval.tick().await;
+
+    let mut debouncer = Debouncer::from_config(&config);
+    let mut shutdown = super::Shutdown::new()?;
+    let lights = pairs.values().map(|pair| pair.to_light.id.clone()).collect::<Vec<_>>();
+    let control = super::control::sync_control(opts.control.socket.clone(), lights).await;
+    let mut scale = config.schedule.scale_now();
+    loop {
+        let next_due = debouncer.next_due();
+        tokio::select! {
+            _ = shutdown.recv() => {
+                println!("Shutting down...");
+                break;
+            }
+            event = events.recv() => {
+                let Some(event) = event else {
+                    tracing::info!("Volume monitor closed");
+                    break;
+                };
+                if !pairs.contains_key(&event.node_name) {
+                    tracing::debug!("Ignoring volume event for unknown node {}", event.node_name);
+                    continue;
+                }
+                if baseline.remove(&event.node_name) {
+                    tracing::trace!("Starting {} from volume {:.2}", event.node_name, event.volume);
+                    continue;
+                }
+                if !event.muted && guard.is_echo(&event.node_name, event.volume) {
+                    tracing::trace!("Ignoring echo of volume {:.2} on {}", event.volume, event.node_name);
+                    continue;
+                }
+                debouncer.push(event, Instant::now());
+            }
+            _ = tokio::time::sleep_until(next_due.unwrap_or_else(Instant::now)), if next_due.is_some() => {
+                for event in debouncer.take_due(Instant::now()) {
+                    if control.is_paused() {
+                        tracing::debug!("Paused; not applying volume {:.2} from {}", event.volume, event.node_name);
+                        continue;
+                    }
+                    let Some(pair) = pairs.get_mut(&event.node_name) else {
+                        continue;
+                    };
+                    if control.is_disabled(&pair.to_light.id) {
+                        tracing::debug!("{} is disabled; not applying volume {:.2}", pair.to_pipewire.label, event.volume);
+                        continue;
+                    }
+                    if let Some(brightness) = pair.to_light.handle(&registry, &event, dry_run).await {
+                        guard.record(&pair.to_light.id.0, brightness.as_f32());
+                        last.record(&pair.to_light.id, brightness.as_f32());
+                    }
+                }
+            }
+            _ = interval.tick(), if !polled.is_empty() => {
+                for node in &polled {
+                    let target = &pairs[node].to_pipewire;
+                    // A pending write would revert the slider if the light's
+                    // old brightness were pushed back now.
+                    if control.is_disabled(&target.id) || debouncer.is_pending(node) {
+                        continue;
+                    }
+                    match registry.get_state(&target.provider, &target.id).await {
+                        Ok(state) if last.changed(&target.id, state.brightness.as_f32()) => {
+                            sync_state(&config, &mut guard, node, target, &state, dry_run).await
+                        }
+                        Ok(_) => {}
+                        Err(e) => tracing::warn!("Failed to read state of {} ({}): {}", target.label, target.id.0, e),
+                    }
+                }
+            }
+            _ = schedule_boundary(&config.schedule) => {
+                let now = config.schedule.scale_now();
+                if now == scale {
+                    continue;
+                }
+                scale = now;
+                tracing::info!("Schedule now scales brightness by {:.2}", scale);
+                if control.is_paused() {
+                    continue;
+                }
+                for pair in pairs.values_mut() {
+                    if control.is_disabled(&pair.to_light.id) {
+                        continue;
+                    }
+                    if let Some(brightness) = pair.to_light.reapply(&registry, dry_run).await {
+                        guard.record(&pair.to_light.id.0, brightness.as_f32());
+                        last.record(&pair.to_light.id, brightness.as_f32());
+                    }
+                }
+            }
+            Some(state) = pushed.recv() => {
+                if control.is_disabled(&state.id) {
+                    continue;
+                }
+                for (node, pair) in pairs.iter().filter(|(_, pair)| pair.to_pipewire.id == state.id) {
+                    sync_state(&config, &mut guard, node, &pair.to_pipewire, &state, dry_run).await;
+                }
+            }
+        }
+    }
+
+    let pending = if control.is_paused() { Vec::new() } else { debouncer.drain() };
+    for event in pending {
+        if let Some(pair) = pairs.get_mut(&event.node_name) {
+            if control.is_disabled(&pair.to_light.id) {
+                continue;
+            }
+            pair.to_light.handle(&registry, &event, dry_run).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Pushes a light's state to its node unless it is the echo of our own write.
+async fn sync_state(
+    config: &Config,
+    guard: &mut EchoGuard,
+    node: &str,
+    target: &SyncTarget,
+    state: &LightState,
+    dry_run: bool,
+) {
+    if guard.is_echo(&target.id.0, state.brightness.as_f32()) {
+        tracing::trace!("Ignoring echo of brightness {:.2} on {}", state.brightness.as_f32(), target.label);
+        return;
+    }
+    let volume = push_volume(config, target, state.brightness, dry_run).await;
+    guard.record(node, volume);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_guard_within_tolerance() {
+        let mut guard = EchoGuard::new(Duration::from_secs(60), 0.02);
+        assert!(!guard.is_echo("lightwire.lifx.desk", 0.5));
+
+        guard.record("lightwire.lifx.desk", 0.5);
+        assert!(guard.is_echo("lightwire.lifx.desk", 0.51));
+        assert!(guard.is_echo("lightwire.lifx.desk", 0.5));
+        assert!(!guard.is_echo("lightwire.lifx.desk", 0.6));
+        assert!(!guard.is_echo("lifx:d073d5123456", 0.5));
+    }
+
+    #[test]
+    fn test_last_brightness_changed() {
+        let id = LightId("lifx:d073d5123456".to_string());
+        let mut last = LastBrightness::default();
+        assert!(!last.changed(&id, 0.5));
+        assert!(!last.changed(&id, 0.51));
+        assert!(last.changed(&id, 0.6));
+        assert!(!last.changed(&id, 0.6));
+
+        // A fade in steps below the tolerance still adds up to a change.
+        assert!(!last.changed(&id, 0.61));
+        assert!(!last.changed(&id, 0.615));
+        assert!(last.changed(&id, 0.63));
+
+        last.record(&id, 0.2);
+        assert!(!last.changed(&id, 0.21));
+        assert!(last.changed(&id, 0.6));
+    }
+
+    #[test]
+    fn test_echo_guard_window_expires() {
+        let mut guard = EchoGuard::new(Duration::ZERO, 0.02);
+        guard.record("lifx:d073d5123456", 0.5);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(!guard.is_echo("lifx:d073d5123456", 0.5));
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use crate::config::{Config, LightConfig, MuteAction, ScheduleConfig};
+use crate::{Brightness, Capabilities, Color, ColorCurve, ColorMap, Curve, CurveRegistry, Light, LightFilter, LightId, NodeLightMap, ProviderRegistry, VolumeController, VolumeEvent, VolumeMonitor, VolumeScale};
+
+#[derive(clap::Args, Debug)]
+pub struct SyncToLightOpts {
+    /// Comma-separated providers to use instead of those enabled in the config
+    #[arg(long)]
+    pub provider: Option<String>,
+    /// Only use lights matching a label pattern such as `Office*`, or
+    /// `key=pattern` terms like `provider=lifx,group=Upstairs`
+    #[arg(long = "match", value_name = "PATTERN")]
+    pub filter: Option<LightFilter>,
+    #[arg(long)]
+    pub once: bool,
+    #[arg(long)]
+    pub daemon: bool,
+    #[command(flatten)]
+    pub control: super::control::ControlOpts,
+}
+
+/// Coalesces bursts of volume events per node. The latest event of a burst
+/// is released once the debounce window has passed and the node's minimum
+/// send interval allows, so the final value of a slider drag always goes out.
+pub(super) struct Debouncer {
+    debounce: Duration,
+    min_interval: Duration,
+    pending: HashMap<String, (VolumeEvent, Instant)>,
+    last_sent: HashMap<String, Instant>,
+}
+
+impl Debouncer {
+    pub(super) fn new(debounce: Duration, min_interval: Duration) -> Self {
+        Self { debounce, min_interval, pending: HashMap::new(), last_sent: HashMap::new() }
+    }
+
+    pub(super) fn from_config(config: &Config) -> Self {
+        Self::new(
+            Duration::from_millis(config.pipewire.debounce_ms),
+            Duration::from_millis(config.pipewire.min_send_interval_ms),
+        )
+    }
+
+    /// Queues an event, replacing any pending one for the same node without
+    /// pushing back when it is due.
+    pub(super) fn push(&mut self, event: VolumeEvent, now: Instant) {
+        let due = match self.pending.get(&event.node_name) {
+            Some((_, due)) => *due,
+            None => {
+                let earliest = now + self.debounce;
+                match self.last_sent.get(&event.node_name) {
+                    Some(sent) => earliest.max(*sent + self.min_interval),
+                    None => earliest,
+                }
+            }
+        };
+        self.pending.insert(event.node_name.clone(), (event, due));
+    }
+
+    pub(super) fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|(_, due)| *due).min()
+    }
+
+    /// Removes and returns the events that are due at `now`.
+    pub(super) fn take_due(&mut self, now: Instant) -> Vec<VolumeEvent> {
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, due))| *due <= now)
+            .map(|(node, _)| node.clone())
+            .collect();
+        due.into_iter()
+            .filter_map(|node| {
+                self.last_sent.insert(node.clone(), now);
+                self.pending.remove(&node).map(|(event, _)| event)
+            })
+            .collect()
+    }
+
+    /// Whether an event for `node` is waiting to be sent.
+    pub(super) fn is_pending(&self, node: &str) -> bool {
+        self.pending.contains_key(node)
+    }
+
+    /// Removes every pending event regardless of when it is due.
+    pub(super) fn drain(&mut self) -> Vec<VolumeEvent> {
+        self.pending.drain().map(|(_, (event, _))| event).collect()
+    }
+}
+
+/// A light driven by one PipeWire node, plus what it looked like before a mute.
+pub(super) struct LightTarget {
+    provider: String,
+    pub(super) id: LightId,
+    label: String,
+    curve: Arc<dyn Curve>,
+    color_curve: Option<Box<dyn ColorCurve>>,
+    color_map: Option<ColorMap>,
+    map_brightness: bool,
+    capabilities: Capabilities,
+    transition: Duration,
+    software_transition: bool,
+    skip_tolerance: Option<f32>,
+    volume_scale: VolumeScale,
+    light_config: Option<LightConfig>,
+    schedule: ScheduleConfig,
+    muted: bool,
+    last_brightness: Option<Brightness>,
+    /// The last volume applied, so it can be applied again when the
+    /// schedule's scale changes.
+    last_event: Option<VolumeEvent>,
+    /// A software fade still stepping, aborted by the light's next write.
+    ramp: Option<JoinHandle<()>>,
+}
+
+impl LightTarget {
+    pub(super) fn new(config: &Config, curves: &CurveRegistry, registry: &ProviderRegistry, light: &dyn Light) -> Result<Self> {
+        let capabilities = registry.capabilities(light.provider_name(), light.id())?;
+        let light_config = config.lights.get(light.id()).cloned();
+        let color_curve = config.color_curve_for_light(light.id());
+        if color_curve.is_some() && !capabilities.kelvin {
+            tracing::warn!("{} cannot set color temperature; only its brightness will follow the curve", light.label());
+        }
+        let mut color_map = config.color_map_for_light(light.id())?;
+        if color_map.is_some() && !capabilities.color {
+            tracing::warn!("{} cannot set color; ignoring its color map", light.label());
+            color_map = None;
+        }
+        let map_brightness = light_config
+            .as_ref()
+            .and_then(|light_config| light_config.map_brightness)
+            .unwrap_or(true);
+        if !capabilities.power
+            && light_config.as_ref().and_then(|light_config| light_config.mute_action) == Some(MuteAction::PowerOff)
+        {
+            tracing::warn!("{} cannot be powered off; muting will set brightness to zero instead", light.label());
+        }
+        Ok(Self {
+            provider: light.provider_name().to_string(),
+            id: light.id().clone(),
+            label: light.label().to_string(),
+            curve: curves.resolve(config.curve_name_for_light(light.id()))?,
+            color_curve,
+            color_map,
+            map_brightness,
+            capabilities,
+            transition: Duration::from_millis(config.pipewire.transition_ms),
+            software_transition: config.pipewire.software_transition,
+            skip_tolerance: config.pipewire.skip_tolerance,
+            volume_scale: config.pipewire.volume_scale,
+            light_config,
+            schedule: config.schedule.clone(),
+            muted: false,
+            last_brightness: None,
+            last_event: None,
+            ramp: None,
+        })
+    }
+
+    fn mute_action(&self) -> MuteAction {
+        let action = self
+            .light_config
+            .as_ref()
+            .and_then(|light_config| light_config.mute_action)
+            .unwrap_or_default();
+        if action == MuteAction::PowerOff && !self.capabilities.power {
+            return MuteAction::BrightnessZero;
+        }
+        action
+    }
+
+    fn brightness_for(&self, volume: f32) -> Brightness {
+        let brightness = match &self.color_curve {
+            Some(color_curve) => color_curve.apply(volume).0,
+            None => Brightness::new(self.curve.apply(volume)),
+        };
+        let brightness = match &self.light_config {
+            Some(light_config) => light_config.map_brightness(brightness),
+            None => brightness,
+        };
+        self.schedule.apply(brightness)
+    }
+
+    /// Stops a software fade that is still running, so it can't overwrite
+    /// a newer value.
+    fn cancel_ramp(&mut self) {
+        if let Some(ramp) = self.ramp.take() {
+            ramp.abort();
+        }
+    }
+
+    async fn set_brightness(&mut self, registry: &Arc<ProviderRegistry>, brightness: Brightness, dry_run: bool) {
+        if dry_run {
+            if self.transition.is_zero() {
+                println!("DRY RUN: Would set {} brightness to {:.2}", self.label, brightness.as_f32());
+            } else {
+                println!(
+                    "DRY RUN: Would fade {} brightness to {:.2} over {}ms",
+                    self.label,
+                    brightness.as_f32(),
+                    self.transition.as_millis()
+                );
+            }
+            return;
+        }
+        let result = match self.last_brightness {
+            _ if self.transition.is_zero() => match self.skip_tolerance {
+                Some(tolerance) => registry
+                    .set_brightness_if_changed(&self.provider, &self.id, brightness, tolerance)
+                    .await
+                    .map(drop),
+                None => registry.set_brightness(&self.provider, &self.id, brightness).await,
+            },
+            Some(from) if self.software_transition && !self.capabilities.transition => {
+                // Stepping takes the whole transition, so it runs beside the
+                // sync loop rather than holding it up.
+                let (registry, provider, id, label) =
+                    (registry.clone(), self.provider.clone(), self.id.clone(), self.label.clone());
+                let transition = self.transition;
+                self.ramp = Some(tokio::spawn(async move {
+                    if let Err(e) = registry.ramp_brightness(&provider, &id, from, brightness, transition).await {
+                        tracing::warn!("Failed to set brightness of {} ({}): {}", label, id.0, e);
+                    }
+                }));
+                Ok(())
+            }
+            _ => {
+                registry
+                    .set_brightness_with_transition(&self.provider, &self.id, brightness, self.transition)
+                    .await
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to set brightness of {} ({}): {}", self.label, self.id.0, e);
+        }
+    }
+
+    async fn set_power(&self, registry: &ProviderRegistry, on: bool, dry_run: bool) {
+        if dry_run {
+            println!("DRY RUN: Would turn {} {}", self.label, if on { "on" } else { "off" });
+            return;
+        }
+        if let Err(e) = registry.set_power(&self.provider, &self.id, on).await {
+            tracing::warn!("Failed to set power of {} ({}): {}", self.label, self.id.0, e);
+        }
+    }
+
+    /// Sends the color map's color for a volume. The color's value is the
+    /// curve's brightness unless brightness mapping is turned off, in which
+    /// case the map's own value is used. Returns the brightness sent.
+    async fn set_color_for(&self, registry: &ProviderRegistry, color_map: &ColorMap, volume: f32, dry_run: bool) -> Brightness {
+        let mapped = color_map.apply(volume);
+        let color = if self.map_brightness {
+            Color::new(mapped.hue, mapped.saturation, self.brightness_for(volume).as_percent())
+        } else {
+            mapped
+        };
+        if dry_run {
+            println!(
+                "DRY RUN: Would set {} color to hue {}, saturation {}%, value {}%",
+                self.label, color.hue, color.saturation, color.value
+            );
+        } else if let Err(e) = registry.set_color(&self.provider, &self.id, color).await {
+            tracing::warn!("Failed to set color of {} ({}): {}", self.label, self.id.0, e);
+        }
+        color.brightness()
+    }
+
+    /// Sends the color curve's kelvin, if any and the provider supports it.
+    async fn set_kelvin_for(&self, registry: &ProviderRegistry, volume: f32, dry_run: bool) {
+        let Some(color_curve) = &self.color_curve else {
+            return;
+        };
+        if !self.capabilities.kelvin {
+            return;
+        }
+        let (_, kelvin) = color_curve.apply(volume);
+        if dry_run {
+            println!("DRY RUN: Would set {} color temperature to {}K", self.label, kelvin);
+            return;
+        }
+        if let Err(e) = registry.set_kelvin(&self.provider, &self.id, kelvin).await {
+            tracing::warn!("Failed to set color temperature of {} ({}): {}", self.label, self.id.0, e);
+        }
+    }
+
+    /// Applies a volume event, returning the brightness sent to the light, if any.
+    pub(super) async fn handle(&mut self, registry: &Arc<ProviderRegistry>, event: &VolumeEvent, dry_run: bool) -> Option<Brightness> {
+        let action = self.mute_action();
+        self.last_event = Some(event.clone());
+
+        if event.muted && self.muted {
+            return None;
+        }
+        self.cancel_ramp();
+        if event.muted {
+            self.muted = true;
+            match action {
+                MuteAction::BrightnessZero => {
+                    let off = Brightness::new(0.0);
+                    self.set_brightness(registry, off, dry_run).await;
+                    return Some(off);
+                }
+                MuteAction::PowerOff => self.set_power(registry, false, dry_run).await,
+                MuteAction::Ignore => {}
+            }
+            return None;
+        }
+
+        if self.muted {
+            self.muted = false;
+            if action == MuteAction::PowerOff {
+                self.set_power(registry, true, dry_run).await;
+            }
+            if let Some(previous) = self.last_brightness {
+                if action == MuteAction::BrightnessZero {
+                    self.set_brightness(registry, previous, dry_run).await;
+                    return Some(previous);
+                }
+                return None;
+            }
+        }
+
+        let position = self.volume_scale.position(event.volume);
+        if dry_run {
+            println!("DRY RUN: {} follows volume {:.2} through the {} curve", self.label, event.volume, self.curve.name());
+        }
+        let brightness = match &self.color_map {
+            Some(color_map) => self.set_color_for(registry, color_map, position, dry_run).await,
+            None => {
+                let brightness = self.brightness_for(position);
+                self.set_brightness(registry, brightness, dry_run).await;
+                self.set_kelvin_for(registry, position, dry_run).await;
+                brightness
+            }
+        };
+        self.last_brightness = Some(brightness);
+        Some(brightness)
+    }
+
+    /// Applies the last volume again under the schedule as it is now,
+    /// returning the brightness sent, if any. Muted lights stay as they are.
+    pub(super) async fn reapply(&mut self, registry: &Arc<ProviderRegistry>, dry_run: bool) -> Option<Brightness> {
+        let event = self.last_event.clone().filter(|event| !event.muted)?;
+        self.handle(registry, &event, dry_run).await
+    }
+}
+
+/// Waits until the schedule's next window opens or closes, or forever
+/// without any windows.
+pub(super) async fn schedule_boundary(schedule: &ScheduleConfig) {
+    match schedule.until_next_boundary(jiff::Zoned::now().time()) {
+        Some(wait) => tokio::time::sleep(wait).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The target of the light behind an event's node. Events for nodes that
+/// aren't ours are logged and ignored.
+fn target_for<'a>(
+    nodes: &NodeLightMap,
+    targets: &'a mut HashMap<LightId, LightTarget>,
+    node_name: &str,
+) -> Option<&'a mut LightTarget> {
+    let Some((_, id)) = nodes.light_for_node(node_name) else {
+        tracing::debug!("Ignoring volume event for unknown node {}", node_name);
+        return None;
+    };
+    targets.get_mut(id)
+}
+
+/// Shows what each light would be set to from its node's current volume.
+async fn preview(registry: &Arc<ProviderRegistry>, nodes: &NodeLightMap, targets: &mut HashMap<LightId, LightTarget>) {
+    for node in nodes.node_names() {
+        let Some(target) = target_for(nodes, targets, &node) else {
+            continue;
+        };
+        match VolumeController::new(node.clone()).get_volume().await {
+            Ok(volume) => {
+                let event = VolumeEvent { node_name: node, volume: volume.value, muted: volume.muted };
+                target.handle(registry, &event, true).await;
+            }
+            Err(e) => println!("DRY RUN: Could not read the volume of {}: {}", node, e),
+        }
+    }
+}
+
+pub async fn run(opts: SyncToLightOpts, config: Config, dry_run: bool) -> Result<()> {
+    let registry = Arc::new(super::registry_for(&config, opts.provider.as_deref())?);
+
+    let lights = registry.discover_filtered(&opts.filter.clone().unwrap_or_default()).await?;
+
+    if lights.is_empty() {
+        println!("No lights found on the network.");
+        return Ok(());
+    }
+
+    let lights = config.lights.retain_enabled(lights);
+    let curves = config.curves.registry()?;
+
+    println!("Found {} light(s):", lights.len());
+    let mut targets = HashMap::new();
+    for light in &lights {
+        println!("  - {} ({})", light.label(), light.id().0);
+        targets.insert(light.id().clone(), LightTarget::new(&config, &curves, &registry, light.as_ref())?);
+    }
+    let nodes = NodeLightMap::from_dropins(&super::dropins_for(&config, &lights));
+
+    println!("\nWatching PipeWire for volume changes...");
+
+    if dry_run {
+        preview(&registry, &nodes, &mut targets).await;
+    }
+
+    if !opts.daemon && !opts.once {
+        println!("Running once and exiting...");
+        return Ok(());
+    }
+
+    let (monitor, mut events) = VolumeMonitor::for_managed_nodes(&config.pipewire.node_prefix).await;
+    tokio::spawn(async move {
+        if let Err(e) = monitor.run().await {
+            tracing::error!("Volume monitor stopped: {}", e);
+        }
+    });
+
+    let mut debouncer = Debouncer::from_config(&config);
+    let mut shutdown = super::Shutdown::new()?;
+    let control = super::control::sync_control(opts.control.socket.clone(), targets.keys().cloned()).await;
+    let mut scale = config.schedule.scale_now();
+    loop {
+        let next_due = debouncer.next_due();
+        tokio::select! {
+            _ = shutdown.recv() => {
+                println!("Shutting down...");
+                break;
+            }
+            event = events.recv() => {
+                let Some(event) = event else {
+                    tracing::info!("Volume monitor closed");
+                    break;
+                };
+                if target_for(&nodes, &mut targets, &event.node_name).is_some() {
+                    debouncer.push(event, Instant::now());
+                }
+            }
+            _ = tokio::time::sleep_until(next_due.unwrap_or_else(Instant::now)), if next_due.is_some() => {
+                for event in debouncer.take_due(Instant::now()) {
+                    if control.is_paused() {
+                        tracing::debug!("Paused; not applying volume {:.2} from {}", event.volume, event.node_name);
+                        continue;
+                    }
+                    if let Some(target) = target_for(&nodes, &mut targets, &event.node_name) {
+                        if control.is_disabled(&target.id) {
+                            tracing::debug!("{} is disabled; not applying volume {:.2}", target.label, event.volume);
+                            continue;
+                        }
+                        target.handle(&registry, &event, dry_run).await;
+                    }
+                }
+                if opts.once {
+                    break;
+                }
+            }
+            _ = schedule_boundary(&config.schedule) => {
+                let now = config.schedule.scale_now();
+                if now == scale {
+                    continue;
+                }
+                scale = now;
+                tracing::info!("Schedule now scales brightness by {:.2}", scale);
+                if control.is_paused() {
+                    continue;
+                }
+                for target in targets.values_mut() {
+                    if !control.is_disabled(&target.id) {
+                        target.reapply(&registry, dry_run).await;
+                    }
+                }
+            }
+        }
+    }
+
+    let pending = if control.is_paused() { Vec::new() } else { debouncer.drain() };
+    for event in pending {
+        if let Some(target) = target_for(&nodes, &mut targets, &event.node_name) {
+            if control.is_disabled(&target.id) {
+                continue;
+            }
+            target.handle(&registry, &event, dry_run).await;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(node: &str, volume: f32) -> VolumeEvent {
+        VolumeEvent { node_name: node.to_string(), volume, muted: false }
+    }
+
+    #[test]
+    fn test_debouncer_coalesces_burst() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(50), Duration::ZERO);
+        let start = Instant::now();
+        debouncer.push(event("desk", 0.1), start);
+        debouncer.push(event("desk", 0.2), start + Duration::from_millis(20));
+        debouncer.push(event("desk", 0.3), start + Duration::from_millis(40));
+        assert!(debouncer.is_pending("desk"));
+
+        assert_eq!(debouncer.next_due(), Some(start + Duration::from_millis(50)));
+        assert!(debouncer.take_due(start + Duration::from_millis(49)).is_empty());
+        let sent = debouncer.take_due(start + Duration::from_millis(50));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].volume, 0.3);
+        assert_eq!(debouncer.next_due(), None);
+        assert!(!debouncer.is_pending("desk"));
+    }
+
+    #[test]
+    fn test_debouncer_respects_send_interval() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(10), Duration::from_millis(100));
+        let start = Instant::now();
+        debouncer.push(event("desk", 0.1), start);
+        assert_eq!(debouncer.take_due(start + Duration::from_millis(10)).len(), 1);
+
+        debouncer.push(event("desk", 0.2), start + Duration::from_millis(20));
+        debouncer.push(event("lamp", 0.5), start + Duration::from_millis(20));
+        let sent = debouncer.take_due(start + Duration::from_millis(30));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].node_name, "lamp");
+        assert_eq!(debouncer.next_due(), Some(start + Duration::from_millis(110)));
+        assert_eq!(debouncer.drain()[0].volume, 0.2);
+    }
+}
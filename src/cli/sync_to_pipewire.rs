@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use tokio::sync::mpsc;
+use crate::cache::StateCache;
+use crate::config::Config;
+use crate::{Brightness, Curve, CurveRegistry, DropinConfig, Light, LightFilter, LightId, LightState, ProviderRegistry, VolumeController, VolumeScale};
+
+/// Pushed state changes queued while earlier ones are still being applied.
+const PUSH_BUFFER: usize = 64;
+
+#[derive(clap::Args, Debug)]
+pub struct SyncToPipewireOpts {
+    /// Comma-separated providers to use instead of those enabled in the config
+    #[arg(long)]
+    pub provider: Option<String>,
+    /// Only use lights matching a label pattern such as `Office*`, or
+    /// `key=pattern` terms like `provider=lifx,group=Upstairs`
+    #[arg(long = "match", value_name = "PATTERN")]
+    pub filter: Option<LightFilter>,
+    #[arg(long)]
+    pub once: bool,
+    #[arg(long)]
+    pub watch: bool,
+    #[arg(long, default_value = "1000")]
+    pub interval: u64,
+    /// Always run discovery instead of starting from cached light states
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Seconds after which cached light states are ignored
+    #[arg(long, default_value = "3600")]
+    pub cache_ttl: u64,
+}
+
+/// A discovered light paired with the PipeWire node and curve it syncs through.
+pub(super) struct SyncTarget {
+    pub(super) provider: String,
+    pub(super) id: LightId,
+    pub(super) label: String,
+    curve: Arc<dyn Curve>,
+    volume_scale: VolumeScale,
+    pub(super) controller: VolumeController,
+}
+
+impl SyncTarget {
+    pub(super) fn new(config: &Config, curves: &CurveRegistry, light: &dyn Light, dropin: &DropinConfig) -> Result<Self> {
+        Ok(Self {
+            provider: light.provider_name().to_string(),
+            id: light.id().clone(),
+            label: light.label().to_string(),
+            curve: curves.resolve(config.curve_name_for_light(light.id()))?,
+            volume_scale: config.pipewire.volume_scale,
+            controller: VolumeController::new(dropin.node_name()),
+        })
+    }
+
+    /// Maps the light's brightness back through the schedule, its range,
+    /// curve and volume scale to a linear volume.
+    pub(super) fn volume_for(&self, config: &Config, brightness: Brightness) -> f32 {
+        let brightness = config.schedule.unapply(brightness);
+        let brightness = match config.lights.get(&self.id) {
+            Some(light_config) => light_config.unmap_brightness(brightness),
+            None => brightness,
+        };
+        self.volume_scale.linear(self.curve.inverse(brightness.as_f32()))
+    }
+}
+
+pub async fn run(opts: SyncToPipewireOpts, config: Config, dry_run: bool) -> Result<()> {
+    let registry = Arc::new(super::registry_for(&config, opts.provider.as_deref())?);
+
+    // The cache holds every enabled provider's lights, so it is bypassed for an explicit selection.
+    let use_cache = !opts.no_cache && opts.provider.is_none();
+    let cache_path = StateCache::default_path();
+    let cached = if !use_cache {
+        None
+    } else {
+        match StateCache::load(&cache_path, Duration::from_secs(opts.cache_ttl)) {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!("Ignoring light cache {}: {}", cache_path.display(), e);
+                None
+            }
+        }
+    };
+
+    let from_cache = cached.is_some();
+    let lights = match cached {
+        Some(cache) => {
+            tracing::info!("Starting from {} cached light(s); refreshing in the background", cache.lights.len());
+            let registry = registry.clone();
+            let cache_path = cache_path.clone();
+            tokio::spawn(async move {
+                match registry.discover_all().await {
+                    Ok(lights) => save_cache(&cache_path, &lights, dry_run),
+                    Err(e) => tracing::warn!("Background discovery failed: {}", e),
+                }
+            });
+            cache.into_lights()
+        }
+        None => {
+            let lights = registry.discover_all().await?;
+            if use_cache && !lights.is_empty() {
+                save_cache(&cache_path, &lights, dry_run);
+            }
+            lights
+        }
+    };
+    let lights = match &opts.filter {
+        Some(filter) => filter.retain(lights),
+        None => lights,
+    };
+
+    if lights.is_empty() {
+        println!("No lights found on the network.");
+        return Ok(());
+    }
+
+    let lights = config.lights.retain_enabled(lights);
+    let curves = config.curves.registry()?;
+
+    println!("Found {} light(s):", lights.len());
+    let mut targets = Vec::with_capacity(lights.len());
+    let dropins = super::dropins_for(&config, &lights);
+    for (light, dropin) in lights.iter().zip(&dropins) {
+        let state = light.state();
+        println!("  - {} ({}): brightness={:.2}, power={}",
+            light.label(),
+            light.id().0,
+            state.brightness.as_f32(),
+            state.power
+        );
+        targets.push(SyncTarget::new(&config, &curves, light.as_ref(), dropin)?);
+    }
+
+    if from_cache {
+        for (target, light) in targets.iter().zip(&lights) {
+            push_volume(&config, target, light.state().brightness, dry_run).await;
+        }
+    }
+
+    let watching = opts.watch && !opts.once;
+    if watching {
+        println!("\nWatching for changes every {}ms...", opts.interval);
+    }
+
+    let mut shutdown = super::Shutdown::new()?;
+    let all: Vec<&SyncTarget> = targets.iter().collect();
+    sync_once(&config, &registry, &all, dry_run).await;
+    if !watching {
+        return Ok(());
+    }
+
+    let (mut pushed, subscribed) = subscribe_all(&registry, &all).await;
+    let polled: Vec<&SyncTarget> = targets.iter().filter(|target| !subscribed.contains(&target.provider)).collect();
+    let mut interval = tokio::time::interval(Duration::from_millis(opts.interval.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.recv() => {
+                println!("Shutting down...");
+                break;
+            }
+            _ = interval.tick(), if !polled.is_empty() => {
+                sync_once(&config, &registry, &polled, dry_run).await;
+            }
+            Some(state) = pushed.recv() => {
+                for target in targets.iter().filter(|target| target.id == state.id) {
+                    push_volume(&config, target, state.brightness, dry_run).await;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Subscribes to every provider that can push state changes, merging their
+/// updates into one channel. Returns the names of the providers that no
+/// longer need polling.
+pub(super) async fn subscribe_all(registry: &ProviderRegistry, targets: &[&SyncTarget]) -> (mpsc::Receiver<LightState>, HashSet<String>) {
+    let (tx, rx) = mpsc::channel(PUSH_BUFFER);
+    let providers: HashSet<&str> = targets.iter().map(|target| target.provider.as_str()).collect();
+    let mut subscribed = HashSet::new();
+    for provider in providers {
+        match registry.subscribe(provider).await {
+            Ok(mut updates) => {
+                tracing::info!("Following pushed state changes from {}", provider);
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    while let Some(state) = updates.recv().await {
+                        if tx.send(state).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                subscribed.insert(provider.to_string());
+            }
+            Err(e) => tracing::debug!("Polling {}; it cannot push state changes: {}", provider, e),
+        }
+    }
+    (rx, subscribed)
+}
+
+/// Pushes every light's current brightness to its node, logging failures
+/// so one unreachable light doesn't stop the rest.
+async fn sync_once(config: &Config, registry: &ProviderRegistry, targets: &[&SyncTarget], dry_run: bool) {
+    for target in targets {
+        let state = match registry.get_state(&target.provider, &target.id).await {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::warn!("Failed to read state of {} ({}): {}", target.label, target.id.0, e);
+                continue;
+            }
+        };
+
+        push_volume(config, target, state.brightness, dry_run).await;
+    }
+}
+
+/// Sets the node's volume from a brightness, returning the volume sent.
+pub(super) async fn push_volume(config: &Config, target: &SyncTarget, brightness: Brightness, dry_run: bool) -> f32 {
+    let volume = target.volume_for(config, brightness);
+
+    if dry_run {
+        println!(
+            "DRY RUN: Would set {} volume to {:.2} from {} brightness {:.2} through the {} curve",
+            target.controller.node_name(),
+            volume,
+            target.label,
+            brightness.as_f32(),
+            target.curve.name()
+        );
+        return volume;
+    }
+
+    match target.controller.set_volume(volume).await {
+        Ok(()) => tracing::debug!(
+            "Synced {} brightness {:.2} to {} volume {:.2}",
+            target.label,
+            brightness.as_f32(),
+            target.controller.node_name(),
+            volume
+        ),
+        Err(e) => tracing::warn!("Failed to set volume on {}: {}", target.controller.node_name(), e),
+    }
+    volume
+}
+
+fn save_cache(path: &Path, lights: &[Box<dyn Light>], dry_run: bool) {
+    if dry_run {
+        println!("DRY RUN: Would write light cache to {}", path.display());
+        return;
+    }
+    if let Err(e) = StateCache::from_lights(lights).save(path) {
+        tracing::warn!("Failed to write light cache {}: {}", path.display(), e);
+    }
+}
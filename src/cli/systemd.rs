@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+
+const UNIT_NAME: &str = "lightwire-sync.service";
+
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("action").required(true).args(["install", "print"]))]
+pub struct SystemdOpts {
+    /// Write the unit to ~/.config/systemd/user/
+    #[arg(long)]
+    pub install: bool,
+    /// Print the unit to stdout instead of installing it
+    #[arg(long)]
+    pub print: bool,
+    /// Also enable and start the unit after installing it
+    #[arg(long, requires = "install")]
+    pub enable: bool,
+    /// Which sync command the unit runs
+    #[arg(long, value_enum, default_value = "both")]
+    pub direction: Direction,
+    /// Comma-separated providers to pass on to the sync command
+    #[arg(long)]
+    pub provider: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// `sync-to-light --daemon`
+    ToLight,
+    /// `sync-to-pipewire --watch`
+    ToPipewire,
+    /// `sync`
+    Both,
+}
+
+impl Direction {
+    fn args(self) -> &'static [&'static str] {
+        match self {
+            Direction::ToLight => &["sync-to-light", "--daemon"],
+            Direction::ToPipewire => &["sync-to-pipewire", "--watch"],
+            Direction::Both => &["sync"],
+        }
+    }
+}
+
+pub async fn run(opts: SystemdOpts, config_path: Option<&str>, dry_run: bool) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate the lightwire binary")?;
+    let config_path = config_path
+        .map(|path| std::path::absolute(shellexpand::tilde(path).as_ref()))
+        .transpose()
+        .context("Failed to resolve --config")?;
+    let unit = render_unit(&exe, config_path.as_deref(), opts.direction, opts.provider.as_deref());
+
+    if opts.print {
+        print!("{}", unit);
+        return Ok(());
+    }
+
+    let dir = user_unit_dir()?;
+    let path = dir.join(UNIT_NAME);
+    if dry_run {
+        println!("DRY RUN: Would write {}:\n{}", path.display(), unit);
+    } else {
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        std::fs::write(&path, &unit).with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+    }
+
+    let mut commands: Vec<&[&str]> = vec![&["systemctl", "--user", "daemon-reload"]];
+    if opts.enable {
+        commands.push(&["systemctl", "--user", "enable", "--now", UNIT_NAME]);
+    }
+    for command in commands {
+        systemctl(command, dry_run).await?;
+    }
+    if !opts.enable {
+        println!("To start it now and at every login, run: systemctl --user enable --now {}", UNIT_NAME);
+    }
+    Ok(())
+}
+
+fn user_unit_dir() -> Result<PathBuf> {
+    let dirs = directories::BaseDirs::new().context("Failed to determine the home directory")?;
+    Ok(dirs.config_dir().join("systemd").join("user"))
+}
+
+async fn systemctl(command: &[&str], dry_run: bool) -> Result<()> {
+    let line = command.join(" ");
+    if dry_run {
+        println!("DRY RUN: Would run: {}", line);
+        return Ok(());
+    }
+    let output = tokio::process::Command::new(command[0])
+        .args(&command[1..])
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", line))?;
+    if !output.status.success() {
+        bail!("{} exited with {}: {}", line, output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// A user unit that starts with PipeWire and restarts on failure. It can't
+/// wait for the network: user managers have no `network-online.target`.
+fn render_unit(exe: &Path, config_path: Option<&Path>, direction: Direction, provider: Option<&str>) -> String {
+    let mut exec = vec![quote(&exe.to_string_lossy())];
+    if let Some(path) = config_path {
+        exec.push("--config".to_string());
+        exec.push(quote(&path.to_string_lossy()));
+    }
+    exec.extend(direction.args().iter().map(|arg| arg.to_string()));
+    if let Some(provider) = provider {
+        exec.push("--provider".to_string());
+        exec.push(quote(provider));
+    }
+
+    format!(
+        "\
+[Unit]
+Description=Sync smart-bulb brightness with PipeWire volumes
+Requires=pipewire.service
+After=pipewire.service
+
+[Service]
+ExecStart={}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=default.target
+",
+        exec.join(" ")
+    )
+}
+
+/// Quotes a word for `ExecStart=` if it contains anything systemd would split on.
+fn quote(word: &str) -> String {
+    if word.chars().any(|c| c.is_whitespace() || c == '"' || c == '\\' || c == '\'') {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_unit() {
+        let unit = render_unit(
+            Path::new("/home/me/.cargo/bin/lightwire"),
+            Some(Path::new("/home/me/my config.toml")),
+            Direction::ToLight,
+            Some("lifx,kasa"),
+        );
+        assert!(unit.contains(
+            "ExecStart=/home/me/.cargo/bin/lightwire --config \"/home/me/my config.toml\" sync-to-light --daemon --provider lifx,kasa\n"
+        ));
+        assert!(unit.contains("Requires=pipewire.service\n"));
+        assert!(unit.contains("After=pipewire.service\n"));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+
+        let unit = render_unit(Path::new("/usr/bin/lightwire"), None, Direction::Both, None);
+        assert!(unit.contains("ExecStart=/usr/bin/lightwire sync\n"));
+    }
+}
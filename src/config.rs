@@ -1,12 +1,50 @@
 use directories::ProjectDirs;
 use figment::{
-    providers::{Env, Format, Toml},
+    providers::{Env, Format, Json, Toml, Yaml},
     Figment,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use crate::curves::{self, ColorCurve, Curve, CurveError};
+use crate::pipewire::VolumeScale;
+use crate::provider::{Brightness, Light, LightGroup, LightId};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Config file not found: {0}")]
+    NotFound(PathBuf),
+    #[error("Invalid config: {0}")]
+    Invalid(#[from] Box<figment::Error>),
+    #[error("Invalid http template: {0}")]
+    HttpTemplate(String),
+    #[error("Failed to serialize config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("Failed to write {path}: {source}")]
+    Write { path: PathBuf, source: std::io::Error },
+}
+
+/// A semantic problem found by `Config::validate`, with the key it concerns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    pub fn new(key: impl Into<String>, message: impl std::fmt::Display) -> Self {
+        Self { key: key.into(), message: message.to_string() }
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Config {
     #[serde(default)]
     pub pipewire: PipewireConfig,
@@ -15,26 +53,79 @@ pub struct Config {
     #[serde(default)]
     pub lifx: LifxConfig,
     #[serde(default)]
+    pub kasa: KasaConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub wled: WledConfig,
+    #[serde(default)]
+    pub yeelight: YeelightConfig,
+    #[serde(default)]
+    pub esphome: EsphomeConfig,
+    #[serde(default)]
+    pub govee: GoveeConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub homeassistant: HomeAssistantConfig,
+    #[serde(default)]
+    pub sim: SimConfig,
+    #[serde(default)]
     pub lights: LightsConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    /// Named sets of light ids that share one PipeWire node.
+    #[serde(default)]
+    pub groups: std::collections::HashMap<String, Vec<String>>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            pipewire: PipewireConfig::default(),
-            curves: CurvesConfig::default(),
-            lifx: LifxConfig::default(),
-            lights: LightsConfig::default(),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PipewireConfig {
     #[serde(default = "default_config_dir")]
     pub config_dir: Option<String>,
     #[serde(default = "default_node_prefix")]
     pub node_prefix: String,
+    /// Volume changes on a node within this window are coalesced, and only
+    /// the latest is sent to the light.
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    /// Minimum time between brightness commands to one light, so dragging a
+    /// slider can't flood it.
+    #[serde(default = "default_min_send_interval_ms")]
+    pub min_send_interval_ms: u64,
+    /// How long lights take to fade to a new brightness; 0 switches at once.
+    #[serde(default)]
+    pub transition_ms: u64,
+    /// Fade lights that can't fade natively by stepping their brightness.
+    #[serde(default)]
+    pub software_transition: bool,
+    /// When set, read a light's brightness before each write and skip the
+    /// write if the light is already within this distance (0.0-1.0) of it.
+    #[serde(default)]
+    pub skip_tolerance: Option<f32>,
+    /// The scale volumes are read in before a curve is applied: `linear`,
+    /// `cubic` like PipeWire's sliders, or `db` for perceived loudness.
+    #[serde(default)]
+    pub volume_scale: VolumeScale,
+    /// Which side `sync` copies to the other when it starts.
+    #[serde(default)]
+    pub initial_sync: InitialSync,
+}
+
+impl Default for PipewireConfig {
+    fn default() -> Self {
+        Self {
+            config_dir: default_config_dir(),
+            node_prefix: default_node_prefix(),
+            debounce_ms: default_debounce_ms(),
+            min_send_interval_ms: default_min_send_interval_ms(),
+            transition_ms: 0,
+            software_transition: false,
+            skip_tolerance: None,
+            volume_scale: VolumeScale::default(),
+            initial_sync: InitialSync::default(),
+        }
+    }
 }
 
 fn default_config_dir() -> Option<String> {
@@ -45,12 +136,23 @@ fn default_node_prefix() -> String {
     "lightwire".to_string()
 }
 
+fn default_debounce_ms() -> u64 {
+    50
+}
+
+fn default_min_send_interval_ms() -> u64 {
+    100
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CurvesConfig {
     #[serde(default = "default_curve")]
     pub default: String,
     #[serde(default)]
     pub custom: std::collections::HashMap<String, crate::curves::CurveConfig>,
+    /// Named volume-to-color gradients, chosen per light with `color_map`.
+    #[serde(default)]
+    pub color_maps: std::collections::HashMap<String, Vec<curves::ColorStop>>,
 }
 
 impl Default for CurvesConfig {
@@ -58,6 +160,36 @@ impl Default for CurvesConfig {
         Self {
             default: default_curve(),
             custom: std::collections::HashMap::new(),
+            color_maps: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl CurvesConfig {
+    /// Looks `name` up among the custom curves, then the built-in ones.
+    pub fn resolve(&self, name: &str) -> Result<Box<dyn Curve>, CurveError> {
+        if let Some(custom) = self.custom.get(name) {
+            return custom.clone().into_curve();
+        }
+        curves::builtin(name).ok_or_else(|| CurveError::Unknown(name.to_string()))
+    }
+
+    /// Builds every built-in and custom curve once, for callers that look
+    /// curves up repeatedly.
+    pub fn registry(&self) -> Result<curves::CurveRegistry, CurveError> {
+        curves::CurveRegistry::from_custom(&self.custom)
+    }
+
+    pub fn resolve_color_map(&self, name: &str) -> Result<curves::ColorMap, CurveError> {
+        let stops = self.color_maps.get(name).ok_or_else(|| CurveError::UnknownColorMap(name.to_string()))?;
+        curves::ColorMap::new(stops.clone())
+    }
+
+    /// Like `resolve`, but only yields curves that also drive color temperature.
+    pub fn resolve_color(&self, name: &str) -> Option<Box<dyn ColorCurve>> {
+        match self.custom.get(name) {
+            Some(custom) => custom.clone().into_color_curve(),
+            None => curves::builtin_color(name),
         }
     }
 }
@@ -74,6 +206,27 @@ pub struct LifxConfig {
     pub broadcast_address: String,
     #[serde(default = "default_port")]
     pub port: u16,
+    /// How long to wait for a bulb's reply, or its acknowledgement of a
+    /// command, before reporting a timeout.
+    #[serde(default = "default_lifx_timeout")]
+    pub timeout_ms: u64,
+    /// The network interface (e.g. `wlan0`) or local IPv4 address to send
+    /// from, for machines where a VPN or bridge would otherwise carry the
+    /// broadcast.
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Bulbs to query directly by IP or hostname, with an optional port,
+    /// for networks where broadcasts don't reach them (e.g. an IoT VLAN).
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Whether to also discover by broadcast; turn off to use only `hosts`.
+    #[serde(default = "default_lifx_broadcast")]
+    pub broadcast: bool,
+    /// Also send discovery to the IPv6 all-nodes group (`ff02::1`), for
+    /// networks that drop IPv4 broadcast. `broadcast_address` may itself be
+    /// an IPv6 multicast group.
+    #[serde(default)]
+    pub ipv6_multicast: bool,
 }
 
 impl Default for LifxConfig {
@@ -82,10 +235,23 @@ impl Default for LifxConfig {
             discovery_timeout_ms: default_discovery_timeout(),
             broadcast_address: default_broadcast_address(),
             port: default_port(),
+            timeout_ms: default_lifx_timeout(),
+            interface: None,
+            hosts: Vec::new(),
+            broadcast: default_lifx_broadcast(),
+            ipv6_multicast: false,
         }
     }
 }
 
+fn default_lifx_timeout() -> u64 {
+    1000
+}
+
+fn default_lifx_broadcast() -> bool {
+    true
+}
+
 fn default_discovery_timeout() -> u64 {
     5000
 }
@@ -98,13 +264,429 @@ fn default_port() -> u16 {
     56700
 }
 
+/// TP-Link Kasa bulbs. Off by default so LIFX-only setups don't wait on a
+/// second discovery that will never answer.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KasaConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_discovery_timeout")]
+    pub discovery_timeout_ms: u64,
+    #[serde(default = "default_broadcast_address")]
+    pub broadcast_address: String,
+    #[serde(default = "default_kasa_port")]
+    pub port: u16,
+}
+
+impl Default for KasaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            discovery_timeout_ms: default_discovery_timeout(),
+            broadcast_address: default_broadcast_address(),
+            port: default_kasa_port(),
+        }
+    }
+}
+
+fn default_kasa_port() -> u16 {
+    9999
+}
+
+/// Zigbee2MQTT lights reached through an MQTT broker.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MqttConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_mqtt_host")]
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default = "default_mqtt_base_topic")]
+    pub base_topic: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default = "default_discovery_timeout")]
+    pub discovery_timeout_ms: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: default_mqtt_host(),
+            port: default_mqtt_port(),
+            base_topic: default_mqtt_base_topic(),
+            username: None,
+            password: None,
+            discovery_timeout_ms: default_discovery_timeout(),
+        }
+    }
+}
+
+impl MqttConfig {
+    /// Username and password, when both are set.
+    pub fn credentials(&self) -> Option<(String, String)> {
+        Some((self.username.clone()?, self.password.clone()?))
+    }
+}
+
+fn default_mqtt_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_base_topic() -> String {
+    "zigbee2mqtt".to_string()
+}
+
+/// Yeelight bulbs with LAN Control enabled, found by SSDP multicast.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct YeelightConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_discovery_timeout")]
+    pub discovery_timeout_ms: u64,
+}
+
+impl Default for YeelightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            discovery_timeout_ms: default_discovery_timeout(),
+        }
+    }
+}
+
+/// ESPHome devices reached over the native API.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EsphomeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub devices: Vec<EsphomeDeviceConfig>,
+    #[serde(default = "default_http_timeout")]
+    pub timeout_ms: u64,
+}
+
+impl Default for EsphomeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            devices: Vec::new(),
+            timeout_ms: default_http_timeout(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EsphomeDeviceConfig {
+    pub host: String,
+    #[serde(default = "default_esphome_port")]
+    pub port: u16,
+    /// The device's `api: password:`, if it sets one.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+fn default_esphome_port() -> u16 {
+    6053
+}
+
+/// Govee lights with the LAN API enabled. Only used when lightwire is built
+/// with the `govee` feature.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GoveeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_discovery_timeout")]
+    pub discovery_timeout_ms: u64,
+}
+
+impl Default for GoveeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            discovery_timeout_ms: default_discovery_timeout(),
+        }
+    }
+}
+
+/// WLED controllers, listed by host or IP since WLED has no broadcast discovery.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WledConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    #[serde(default = "default_http_timeout")]
+    pub timeout_ms: u64,
+}
+
+impl Default for WledConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hosts: Vec::new(),
+            timeout_ms: default_http_timeout(),
+        }
+    }
+}
+
+fn default_http_timeout() -> u64 {
+    2000
+}
+
+/// Lights exposed by Home Assistant, reached with a long-lived access token.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HomeAssistantConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_homeassistant_url")]
+    pub url: String,
+    /// Created under Profile > Security > Long-lived access tokens.
+    #[serde(default)]
+    pub token: String,
+    #[serde(default = "default_http_timeout")]
+    pub timeout_ms: u64,
+}
+
+impl Default for HomeAssistantConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: default_homeassistant_url(),
+            token: String::new(),
+            timeout_ms: default_http_timeout(),
+        }
+    }
+}
+
+fn default_homeassistant_url() -> String {
+    "http://homeassistant.local:8123".to_string()
+}
+
+/// Fake in-memory lights. Only used when lightwire is built with the `sim` feature.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SimConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_sim_lights")]
+    pub lights: usize,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lights: default_sim_lights(),
+        }
+    }
+}
+
+fn default_sim_lights() -> usize {
+    3
+}
+
+/// Time-of-day limits on brightness, applied in the sync path after each
+/// light's curve and brightness range.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScheduleConfig {
+    #[serde(default)]
+    pub windows: Vec<ScheduleWindow>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScheduleWindow {
+    /// Local time the window opens, as `HH:MM`.
+    pub start: String,
+    /// Local time the window closes; earlier than `start` for a window that
+    /// runs past midnight.
+    pub end: String,
+    /// Multiplies brightness while the window is open, so full volume
+    /// reaches at most this level.
+    pub max_brightness: f32,
+}
+
+impl ScheduleWindow {
+    fn contains(&self, time: jiff::civil::Time) -> bool {
+        let (Some(start), Some(end)) = (parse_clock(&self.start), parse_clock(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+impl ScheduleConfig {
+    /// The brightness multiplier at `time`: the lowest of the open windows',
+    /// or 1.0 outside them all.
+    pub fn scale_at(&self, time: jiff::civil::Time) -> f32 {
+        self.windows
+            .iter()
+            .filter(|window| window.contains(time))
+            .map(|window| window.max_brightness.clamp(0.0, 1.0))
+            .fold(1.0, f32::min)
+    }
+
+    /// The brightness multiplier now, in local time.
+    pub fn scale_now(&self) -> f32 {
+        if self.windows.is_empty() {
+            return 1.0;
+        }
+        self.scale_at(jiff::Zoned::now().time())
+    }
+
+    /// How long after `time` the next window opens or closes, or `None`
+    /// without any windows. A boundary at `time` itself is a day away.
+    pub fn until_next_boundary(&self, time: jiff::civil::Time) -> Option<Duration> {
+        const DAY_NANOS: i64 = 24 * 60 * 60 * 1_000_000_000;
+        let now = nanos_of_day(time);
+        self.windows
+            .iter()
+            .flat_map(|window| [parse_clock(&window.start), parse_clock(&window.end)])
+            .flatten()
+            .map(|boundary| match (nanos_of_day(boundary) - now).rem_euclid(DAY_NANOS) {
+                0 => DAY_NANOS,
+                nanos => nanos,
+            })
+            .min()
+            .map(|nanos| Duration::from_nanos(nanos as u64))
+    }
+
+    /// Scales a brightness by the windows open now, in local time.
+    pub fn apply(&self, brightness: Brightness) -> Brightness {
+        Brightness::new(brightness.as_f32() * self.scale_now())
+    }
+
+    /// Inverse of `apply`, for reading a light's level back. A light dimmed
+    /// to zero by the schedule reads as zero.
+    pub fn unapply(&self, brightness: Brightness) -> Brightness {
+        let scale = self.scale_now();
+        if scale <= f32::EPSILON {
+            return Brightness::new(0.0);
+        }
+        Brightness::new(brightness.as_f32() / scale)
+    }
+}
+
+/// Parses a `HH:MM` (or `HH:MM:SS`) time of day.
+fn parse_clock(s: &str) -> Option<jiff::civil::Time> {
+    s.trim().parse().ok()
+}
+
+fn nanos_of_day(time: jiff::civil::Time) -> i64 {
+    let seconds = i64::from(time.hour()) * 3600 + i64::from(time.minute()) * 60 + i64::from(time.second());
+    seconds * 1_000_000_000 + i64::from(time.subsec_nanosecond())
+}
+
+/// User-defined HTTP endpoints for devices without a native provider.
+///
+/// URLs and bodies may use `{id}` and, for `set`, `{brightness}`, which is
+/// scaled to `0..=brightness_max`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_http_timeout")]
+    pub timeout_ms: u64,
+    /// Devices to expose when there is no `discover` endpoint.
+    #[serde(default)]
+    pub devices: Vec<HttpDeviceConfig>,
+    /// Returns a JSON array of ids, or of objects with `id` and optional `label`.
+    #[serde(default)]
+    pub discover: Option<HttpRequestConfig>,
+    #[serde(default)]
+    pub get: Option<HttpRequestConfig>,
+    #[serde(default)]
+    pub set: Option<HttpRequestConfig>,
+    /// JSON pointer to the brightness in the `get` response.
+    #[serde(default = "default_brightness_pointer")]
+    pub brightness_pointer: String,
+    #[serde(default = "default_brightness_max")]
+    pub brightness_max: f32,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_http_timeout(),
+            devices: Vec::new(),
+            discover: None,
+            get: None,
+            set: None,
+            brightness_pointer: default_brightness_pointer(),
+            brightness_max: default_brightness_max(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpDeviceConfig {
+    pub id: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpRequestConfig {
+    /// Defaults to POST when a body is given and GET otherwise.
+    #[serde(default)]
+    pub method: Option<String>,
+    pub url: String,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+fn default_brightness_pointer() -> String {
+    "/brightness".to_string()
+}
+
+fn default_brightness_max() -> f32 {
+    100.0
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct LightsConfig {
     #[serde(default)]
     pub lights: std::collections::HashMap<String, LightConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl LightsConfig {
+    pub fn get(&self, id: &LightId) -> Option<&LightConfig> {
+        self.lights.get(&id.0)
+    }
+
+    /// Lights without a config entry are enabled.
+    pub fn is_enabled(&self, id: &LightId) -> bool {
+        self.get(id).is_none_or(LightConfig::is_enabled)
+    }
+
+    /// Drops lights whose config entry sets `enabled = false`.
+    pub fn retain_enabled(&self, lights: Vec<Box<dyn Light>>) -> Vec<Box<dyn Light>> {
+        lights
+            .into_iter()
+            .filter(|light| {
+                let enabled = self.is_enabled(light.id());
+                if !enabled {
+                    tracing::debug!("Skipping disabled light: {} ({})", light.label(), light.id().0);
+                }
+                enabled
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct LightConfig {
     #[serde(default)]
     pub min_brightness: Option<f32>,
@@ -113,35 +695,316 @@ pub struct LightConfig {
     #[serde(default)]
     pub curve: Option<String>,
     #[serde(default)]
-    pub mute_action: Option<String>,
+    pub mute_action: Option<MuteAction>,
     #[serde(default)]
     pub enabled: Option<bool>,
+    /// The light's canonical name, used for its node description and
+    /// written to the bulb by `populate --push-labels`.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// A `curves.color_maps` entry whose color follows the volume.
+    #[serde(default)]
+    pub color_map: Option<String>,
+    /// With a color map, whether the curve still sets the brightness; when
+    /// false the map's value does. Defaults to true.
+    #[serde(default)]
+    pub map_brightness: Option<bool>,
 }
 
-impl Config {
-    pub fn load() -> Result<Self, figment::Error> {
-        let dirs = ProjectDirs::from("com", "lightwire", "lightwire")
-            .expect("Failed to determine project directories");
+/// Which side is authoritative when `sync` starts. Its state is written to
+/// the other side once, so the first slider move doesn't jump the light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum InitialSync {
+    /// Set each node's volume from its light's brightness
+    #[default]
+    FromLight,
+    /// Set each light's brightness from its node's volume
+    FromPipewire,
+    /// Leave both sides alone until one of them changes
+    None,
+}
+
+/// What to do with a light when its PipeWire node is muted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MuteAction {
+    #[default]
+    BrightnessZero,
+    PowerOff,
+    Ignore,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown mute action: {0} (expected brightness_zero, power_off or ignore)")]
+pub struct UnknownMuteAction(pub String);
+
+impl FromStr for MuteAction {
+    type Err = UnknownMuteAction;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "brightness_zero" => Ok(MuteAction::BrightnessZero),
+            "power_off" => Ok(MuteAction::PowerOff),
+            "ignore" => Ok(MuteAction::Ignore),
+            other => Err(UnknownMuteAction(other.to_string())),
+        }
+    }
+}
 
-        let config_path = dirs.config_dir().join("config.toml");
+impl LightConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Linearly remaps a 0..1 brightness into `[min_brightness, max_brightness]`.
+    ///
+    /// A missing bound defaults to the matching endpoint, and bounds given in
+    /// the wrong order are swapped.
+    pub fn map_brightness(&self, raw: Brightness) -> Brightness {
+        let (min, max) = self.brightness_range();
+        Brightness::new(min + raw.as_f32() * (max - min))
+    }
+
+    /// Inverse of `map_brightness`, for reading a light's level back into 0..1.
+    pub fn unmap_brightness(&self, mapped: Brightness) -> Brightness {
+        let (min, max) = self.brightness_range();
+        if max - min <= f32::EPSILON {
+            return Brightness::new(if mapped.as_f32() >= max { 1.0 } else { 0.0 });
+        }
+        Brightness::new((mapped.as_f32() - min) / (max - min))
+    }
+
+    fn brightness_range(&self) -> (f32, f32) {
+        let min = self.min_brightness.unwrap_or(0.0).clamp(0.0, 1.0);
+        let max = self.max_brightness.unwrap_or(1.0).clamp(0.0, 1.0);
+        if min > max { (max, min) } else { (min, max) }
+    }
+}
+
+impl Config {
+    /// Loads the user's config merged with `LIGHTWIRE_` environment variables.
+    ///
+    /// `config.toml`, `config.yaml` and `config.json` are read from the config
+    /// directory in that order, each overriding the one before, and environment
+    /// variables override all of them. Missing files are skipped; an unreadable
+    /// or invalid one is an error.
+    pub fn load() -> Result<Self, ConfigError> {
+        let config_dir = Self::user_config_dir();
 
         let figment = Figment::new()
-            .merge(Toml::file(config_path))
+            .merge(Toml::file(config_dir.join("config.toml")))
+            .merge(Yaml::file(config_dir.join("config.yaml")))
+            .merge(Json::file(config_dir.join("config.json")))
             .merge(Env::prefixed("LIGHTWIRE_").split("_"));
 
-        let config: Config = figment.extract()?;
+        let config: Config = figment.extract().map_err(Box::new)?;
+        #[cfg(feature = "http")]
+        crate::provider::http::validate_config(&config.http).map_err(ConfigError::HttpTemplate)?;
 
         Ok(config)
     }
 
-    pub fn load_from_path(path: PathBuf) -> Result<Self, figment::Error> {
-        let figment = Figment::new().merge(Toml::file(path));
+    /// The directory `load` reads from, e.g. `~/.config/lightwire`.
+    pub fn user_config_dir() -> PathBuf {
+        let dirs = ProjectDirs::from("com", "lightwire", "lightwire")
+            .expect("Failed to determine project directories");
+        dirs.config_dir().to_path_buf()
+    }
+
+    /// Loads a single config file, choosing the format from its extension
+    /// (`.yaml`/`.yml`, `.json`, anything else as TOML).
+    pub fn load_from_path(path: PathBuf) -> Result<Self, ConfigError> {
+        if !path.exists() {
+            return Err(ConfigError::NotFound(path));
+        }
+
+        let figment = Figment::new();
+        let figment = match extension(&path).as_deref() {
+            Some("yaml" | "yml") => figment.merge(Yaml::file(path)),
+            Some("json") => figment.merge(Json::file(path)),
+            _ => figment.merge(Toml::file(path)),
+        };
 
-        let config: Config = figment.extract()?;
+        let config: Config = figment.extract().map_err(Box::new)?;
+        #[cfg(feature = "http")]
+        crate::provider::http::validate_config(&config.http).map_err(ConfigError::HttpTemplate)?;
 
         Ok(config)
     }
 
+    /// Writes the config to `config.toml` in `user_config_dir`, creating the
+    /// directory if needed.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        self.save_to_path(&Self::user_config_dir().join("config.toml"))
+    }
+
+    /// Writes the config to `path` as TOML, creating its parent directory.
+    pub fn save_to_path(&self, path: &Path) -> Result<(), ConfigError> {
+        let contents = toml::to_string_pretty(self)?;
+        let write_error = |source| ConfigError::Write { path: path.to_path_buf(), source };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        std::fs::write(path, contents).map_err(write_error)
+    }
+
+    /// Checks invariants serde can't express, returning one issue per problem.
+    ///
+    /// `mute_action` needs no check here: unknown values already fail to load.
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        if let Err(e) = self.curves.resolve(&self.curves.default) {
+            issues.push(ConfigIssue::new("curves.default", e));
+        }
+        for (name, custom) in &self.curves.custom {
+            if let Err(e) = custom.clone().into_curve() {
+                issues.push(ConfigIssue::new(format!("curves.custom.{}", name), e));
+            }
+        }
+        for name in self.curves.color_maps.keys() {
+            if let Err(e) = self.curves.resolve_color_map(name) {
+                issues.push(ConfigIssue::new(format!("curves.color_maps.{}", name), e));
+            }
+        }
+
+        #[cfg(feature = "http")]
+        if let Err(e) = crate::provider::http::validate_config(&self.http) {
+            issues.push(ConfigIssue::new("http", e));
+        }
+        if let Some(tolerance) = self.pipewire.skip_tolerance {
+            if !(0.0..=1.0).contains(&tolerance) {
+                issues.push(ConfigIssue::new("pipewire.skip_tolerance", format!("{} is outside 0.0-1.0", tolerance)));
+            }
+        }
+        for (index, window) in self.schedule.windows.iter().enumerate() {
+            let key = format!("schedule.windows[{}]", index);
+            for (field, value) in [("start", &window.start), ("end", &window.end)] {
+                if parse_clock(value).is_none() {
+                    issues.push(ConfigIssue::new(format!("{}.{}", key, field), format!("{} is not a HH:MM time", value)));
+                }
+            }
+            if parse_clock(&window.start).is_some() && parse_clock(&window.start) == parse_clock(&window.end) {
+                issues.push(ConfigIssue::new(format!("{}.end", key), "window starts and ends at the same time"));
+            }
+            if !(0.0..=1.0).contains(&window.max_brightness) {
+                issues.push(ConfigIssue::new(
+                    format!("{}.max_brightness", key),
+                    format!("{} is outside 0.0-1.0", window.max_brightness),
+                ));
+            }
+        }
+        if let Some(interface) = &self.lifx.interface {
+            let is_addr = interface.parse::<std::net::IpAddr>().is_ok();
+            if !is_addr && !Path::new("/sys/class/net").join(interface).exists() {
+                issues.push(ConfigIssue::new("lifx.interface", format!("no network interface named {}", interface)));
+            }
+        }
+        if self.lifx.broadcast_address.parse::<std::net::IpAddr>().is_err() {
+            issues.push(ConfigIssue::new(
+                "lifx.broadcast_address",
+                format!("{} is not an IPv4 or IPv6 address", self.lifx.broadcast_address),
+            ));
+        }
+        if !self.lifx.broadcast && self.lifx.hosts.is_empty() {
+            issues.push(ConfigIssue::new("lifx.hosts", "broadcast is off, so at least one host is required"));
+        }
+        if self.homeassistant.enabled && self.homeassistant.token.is_empty() {
+            issues.push(ConfigIssue::new("homeassistant.token", "a long-lived access token is required"));
+        }
+
+        let mut seen = std::collections::HashMap::new();
+        for group in self.light_groups() {
+            let key = format!("groups.{}", group.name);
+            if group.members.is_empty() {
+                issues.push(ConfigIssue::new(key.clone(), "group has no members"));
+            }
+            for member in &group.members {
+                match member.provider() {
+                    None => issues.push(ConfigIssue::new(key.clone(), format!("{} has no provider prefix", member.0))),
+                    Some(crate::provider::group::GROUP_PROVIDER) => {
+                        issues.push(ConfigIssue::new(key.clone(), format!("{} is a group; groups cannot be nested", member.0)))
+                    }
+                    Some(_) => {}
+                }
+                if let Some(other) = seen.insert(member.clone(), group.name.clone()) {
+                    issues.push(ConfigIssue::new(key.clone(), format!("{} is also in group {}", member.0, other)));
+                }
+            }
+        }
+
+        let mut ids: Vec<&String> = self.lights.lights.keys().collect();
+        ids.sort();
+        for id in ids {
+            let light = &self.lights.lights[id];
+            let key = format!("lights.lights.\"{}\"", id);
+            if let Some(curve) = &light.curve {
+                if let Err(e) = self.curves.resolve(curve) {
+                    issues.push(ConfigIssue::new(format!("{}.curve", key), e));
+                }
+            }
+            if let Some(name) = &light.color_map {
+                if !self.curves.color_maps.contains_key(name) {
+                    issues.push(ConfigIssue::new(format!("{}.color_map", key), CurveError::UnknownColorMap(name.clone())));
+                }
+            }
+            for (field, value) in [("min_brightness", light.min_brightness), ("max_brightness", light.max_brightness)] {
+                if let Some(value) = value {
+                    if !(0.0..=1.0).contains(&value) {
+                        issues.push(ConfigIssue::new(format!("{}.{}", key, field), format!("{} is outside [0, 1]", value)));
+                    }
+                }
+            }
+            if let (Some(min), Some(max)) = (light.min_brightness, light.max_brightness) {
+                if min > max {
+                    issues.push(ConfigIssue::new(
+                        format!("{}.min_brightness", key),
+                        format!("{} is greater than max_brightness {}", min, max),
+                    ));
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// The `[groups]` section as `LightGroup`s, sorted by name.
+    pub fn light_groups(&self) -> Vec<LightGroup> {
+        let mut groups: Vec<LightGroup> = self
+            .groups
+            .iter()
+            .map(|(name, members)| LightGroup::new(name.clone(), members.iter().cloned().map(LightId).collect()))
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        groups
+    }
+
+    /// Resolves the light's `curve` override, falling back to `curves.default`.
+    pub fn curve_for_light(&self, id: &LightId) -> Result<Box<dyn Curve>, CurveError> {
+        self.curves.resolve(self.curve_name_for_light(id))
+    }
+
+    /// The color curve for the light, if its resolved curve drives kelvin.
+    pub fn color_curve_for_light(&self, id: &LightId) -> Option<Box<dyn ColorCurve>> {
+        self.curves.resolve_color(self.curve_name_for_light(id))
+    }
+
+    /// The light's color map, if it has one.
+    pub fn color_map_for_light(&self, id: &LightId) -> Result<Option<curves::ColorMap>, CurveError> {
+        match self.lights.get(id).and_then(|light| light.color_map.as_deref()) {
+            Some(name) => self.curves.resolve_color_map(name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn curve_name_for_light(&self, id: &LightId) -> &str {
+        self.lights
+            .get(id)
+            .and_then(|light| light.curve.as_deref())
+            .unwrap_or(&self.curves.default)
+    }
+
     pub fn pipewire_config_dir(&self) -> PathBuf {
         if let Some(ref dir) = self.pipewire.config_dir {
             PathBuf::from(shellexpand::tilde(dir).into_owned())
@@ -152,3 +1015,391 @@ impl Config {
         }
     }
 }
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light_config(min: Option<f32>, max: Option<f32>) -> LightConfig {
+        LightConfig {
+            min_brightness: min,
+            max_brightness: max,
+            curve: None,
+            mute_action: None,
+            enabled: None,
+            label: None,
+            color_map: None,
+            map_brightness: None,
+        }
+    }
+
+    fn write_temp_config(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lightwire-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_from_path_missing() {
+        let path = std::env::temp_dir().join("lightwire-does-not-exist.toml");
+        assert!(matches!(Config::load_from_path(path), Err(ConfigError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_load_from_path_invalid() {
+        let path = write_temp_config("invalid.toml", "[curves]\ndefault = 3\n");
+        let result = Config::load_from_path(path.clone());
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_load_from_path() {
+        let path = write_temp_config("valid.toml", "[curves]\ndefault = \"gamma\"\n");
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(config.curves.default, "gamma");
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut config = Config::default();
+        config.curves.default = "gamma".to_string();
+        config.curves.custom.insert("soft".to_string(), crate::curves::CurveConfig::Gamma { gamma: Some(1.5), lut: true });
+        config.groups.insert("desk".to_string(), vec!["lifx:a".to_string(), "lifx:b".to_string()]);
+        let mut light = light_config(Some(0.1), None);
+        light.mute_action = Some(MuteAction::PowerOff);
+        config.lights.lights.insert("lifx:d073d5123456".to_string(), light);
+
+        let dir = std::env::temp_dir().join(format!("lightwire-{}-save", std::process::id()));
+        let path = dir.join("nested").join("config.toml");
+        config.save_to_path(&path).unwrap();
+        let loaded = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(toml::to_string_pretty(&loaded).unwrap(), toml::to_string_pretty(&config).unwrap());
+        assert_eq!(loaded.curves.default, "gamma");
+        assert_eq!(loaded.lights.get(&LightId("lifx:d073d5123456".to_string())).unwrap().min_brightness, Some(0.1));
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn test_load_from_path_rejects_bad_http_template() {
+        let path = write_temp_config("http.toml", "[http.set]\nurl = \"http://host/{id}/{level}\"\n");
+        let result = Config::load_from_path(path.clone());
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(ConfigError::HttpTemplate(_))));
+    }
+
+    #[test]
+    fn test_load_bezier_curve() {
+        let path = write_temp_config(
+            "bezier.toml",
+            "[curves]\ndefault = \"ease\"\n[curves.custom.ease]\ntype = \"bezier\"\np1 = [0.25, 0.1]\np2 = [0.25, 1.0]\n",
+        );
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(config.validate().is_empty());
+        assert_eq!(config.curve_for_light(&LightId("lifx:any".to_string())).unwrap().name(), "bezier");
+    }
+
+    #[test]
+    fn test_load_composite_curve() {
+        let path = write_temp_config(
+            "composite.toml",
+            "[curves.custom.stacked]\ntype = \"composite\"\nstages = [{ type = \"perceptual\" }, { type = \"gamma\", gamma = 1.2 }]\n",
+        );
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let curve = config.curves.custom["stacked"].clone().into_curve().unwrap();
+        assert_eq!(curve.name(), "composite");
+    }
+
+    #[test]
+    fn test_load_floor_ceil_curve() {
+        let path = write_temp_config(
+            "floor_ceil.toml",
+            "[curves.custom.floored]\ntype = \"floor_ceil\"\nfloor_in = 0.05\nfloor_out = 0.02\ncurve = { type = \"gamma\" }\n",
+        );
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let curve = config.curves.custom["floored"].clone().into_curve().unwrap();
+        assert_eq!(curve.apply(0.01), 0.02);
+        assert_eq!(curve.apply(0.99), 1.0);
+    }
+
+    #[test]
+    fn test_load_color_map() {
+        let path = write_temp_config(
+            "color_map.toml",
+            "[curves.color_maps]\ntraffic = [\n  { volume = 0.0, hue = 120, saturation = 100, value = 100 },\n  { volume = 1.0, hue = 0, saturation = 100, value = 100 },\n]\n\n[lights.lights.\"lifx:desk\"]\ncolor_map = \"traffic\"\nmap_brightness = false\n",
+        );
+        let mut config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(config.validate().is_empty());
+        let id = LightId("lifx:desk".to_string());
+        let map = config.color_map_for_light(&id).unwrap().unwrap();
+        assert_eq!(map.apply(0.5).hue, 60);
+        assert_eq!(config.lights.get(&id).unwrap().map_brightness, Some(false));
+        assert!(config.color_map_for_light(&LightId("lifx:other".to_string())).unwrap().is_none());
+
+        config.lights.lights.get_mut("lifx:desk").unwrap().color_map = Some("missing".to_string());
+        let keys: Vec<String> = config.validate().into_iter().map(|issue| issue.key).collect();
+        assert_eq!(keys, vec!["lights.lights.\"lifx:desk\".color_map"]);
+    }
+
+    #[test]
+    fn test_load_initial_sync() {
+        assert_eq!(Config::default().pipewire.initial_sync, InitialSync::FromLight);
+        let path = write_temp_config("initial_sync.toml", "[pipewire]\ninitial_sync = \"from-pipewire\"\n");
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(config.pipewire.initial_sync, InitialSync::FromPipewire);
+    }
+
+    #[test]
+    fn test_load_from_path_yaml_and_json() {
+        let path = write_temp_config("valid.yaml", "curves:\n  default: gamma\n");
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(config.curves.default, "gamma");
+
+        let path = write_temp_config("valid.json", r#"{"curves": {"default": "linear"}}"#);
+        let config = Config::load_from_path(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(config.curves.default, "linear");
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = Config::default();
+        assert!(config.validate().is_empty());
+
+        let mut light = light_config(Some(0.8), Some(1.5));
+        light.curve = Some("wobbly".to_string());
+        config.lights.lights.insert("lifx:bad".to_string(), light);
+        config.lights.lights.insert("lifx:inverted".to_string(), light_config(Some(0.6), Some(0.4)));
+
+        let keys: Vec<String> = config.validate().into_iter().map(|issue| issue.key).collect();
+        assert_eq!(keys, vec![
+            "lights.lights.\"lifx:bad\".curve",
+            "lights.lights.\"lifx:bad\".max_brightness",
+            "lights.lights.\"lifx:inverted\".min_brightness",
+        ]);
+    }
+
+    #[test]
+    fn test_validate_lifx_interface() {
+        let mut config = Config::default();
+        config.lifx.interface = Some("192.168.1.20".to_string());
+        assert!(config.validate().is_empty());
+
+        config.lifx.interface = Some("lightwire-missing0".to_string());
+        let keys: Vec<String> = config.validate().into_iter().map(|issue| issue.key).collect();
+        assert_eq!(keys, vec!["lifx.interface"]);
+    }
+
+    fn window(start: &str, end: &str, max_brightness: f32) -> ScheduleWindow {
+        ScheduleWindow { start: start.to_string(), end: end.to_string(), max_brightness }
+    }
+
+    #[test]
+    fn test_schedule_scale() {
+        let schedule = ScheduleConfig { windows: vec![window("22:00", "07:00", 0.6), window("23:30", "23:45", 0.2)] };
+        let at = |h, m| schedule.scale_at(jiff::civil::time(h, m, 0, 0));
+        assert_eq!(at(21, 59), 1.0);
+        assert_eq!(at(22, 0), 0.6);
+        assert_eq!(at(23, 40), 0.2);
+        assert_eq!(at(3, 0), 0.6);
+        assert_eq!(at(7, 0), 1.0);
+
+        let daytime = ScheduleConfig { windows: vec![window("09:00", "17:00", 0.5)] };
+        assert_eq!(daytime.scale_at(jiff::civil::time(12, 0, 0, 0)), 0.5);
+        assert_eq!(daytime.scale_at(jiff::civil::time(17, 0, 0, 0)), 1.0);
+        assert_eq!(ScheduleConfig::default().apply(Brightness::new(0.8)).as_f32(), 0.8);
+    }
+
+    #[test]
+    fn test_schedule_next_boundary() {
+        let schedule = ScheduleConfig { windows: vec![window("22:00", "07:00", 0.6), window("23:30", "23:45", 0.2)] };
+        let until = |h, m| schedule.until_next_boundary(jiff::civil::time(h, m, 0, 0));
+        assert_eq!(until(21, 0), Some(Duration::from_secs(3600)));
+        assert_eq!(until(23, 40), Some(Duration::from_secs(5 * 60)));
+        assert_eq!(until(23, 45), Some(Duration::from_secs(7 * 3600 + 15 * 60)));
+        assert_eq!(until(7, 0), Some(Duration::from_secs(15 * 3600)));
+        assert_eq!(ScheduleConfig::default().until_next_boundary(jiff::civil::time(12, 0, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_validate_schedule() {
+        let mut config = Config::default();
+        config.schedule.windows = vec![window("22:00", "07:00", 0.6)];
+        assert!(config.validate().is_empty());
+
+        config.schedule.windows = vec![window("late", "07:00", 0.6), window("08:00", "08:00", 1.5)];
+        let keys: Vec<String> = config.validate().into_iter().map(|issue| issue.key).collect();
+        assert_eq!(keys, vec![
+            "schedule.windows[0].start",
+            "schedule.windows[1].end",
+            "schedule.windows[1].max_brightness",
+        ]);
+    }
+
+    #[test]
+    fn test_validate_lifx_broadcast_address() {
+        let mut config = Config::default();
+        config.lifx.broadcast_address = "ff02::1".to_string();
+        assert!(config.validate().is_empty());
+
+        config.lifx.broadcast_address = "lan".to_string();
+        let keys: Vec<String> = config.validate().into_iter().map(|issue| issue.key).collect();
+        assert_eq!(keys, vec!["lifx.broadcast_address"]);
+    }
+
+    #[test]
+    fn test_validate_groups() {
+        let mut config = Config::default();
+        config.groups.insert("desk".to_string(), vec!["lifx:a".to_string(), "lifx:b".to_string()]);
+        assert!(config.validate().is_empty());
+
+        config.groups.insert("shelf".to_string(), vec!["lifx:b".to_string(), "bare".to_string()]);
+        let messages: Vec<String> = config.validate().into_iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(messages, vec![
+            "groups.shelf: lifx:b is also in group desk",
+            "groups.shelf: bare has no provider prefix",
+        ]);
+    }
+
+    #[test]
+    fn test_lights_is_enabled() {
+        let mut lights = LightsConfig::default();
+        let mut disabled = light_config(None, None);
+        disabled.enabled = Some(false);
+        lights.lights.insert("lifx:disabled".to_string(), disabled);
+        lights.lights.insert("lifx:default".to_string(), light_config(None, None));
+
+        assert!(!lights.is_enabled(&LightId("lifx:disabled".to_string())));
+        assert!(lights.is_enabled(&LightId("lifx:default".to_string())));
+        assert!(lights.is_enabled(&LightId("lifx:unknown".to_string())));
+    }
+
+    #[test]
+    fn test_curve_for_light() {
+        let mut config = Config::default();
+        config.curves.custom.insert(
+            "soft".to_string(),
+            crate::curves::CurveConfig::Gamma { gamma: Some(1.5), lut: false },
+        );
+        let mut light = light_config(None, None);
+        light.curve = Some("soft".to_string());
+        config.lights.lights.insert("lifx:soft".to_string(), light);
+        let mut light = light_config(None, None);
+        light.curve = Some("linear".to_string());
+        config.lights.lights.insert("lifx:linear".to_string(), light);
+
+        let curve = config.curve_for_light(&LightId("lifx:soft".to_string())).unwrap();
+        assert_eq!(curve.name(), "gamma");
+        let curve = config.curve_for_light(&LightId("lifx:linear".to_string())).unwrap();
+        assert_eq!(curve.name(), "linear");
+        let curve = config.curve_for_light(&LightId("lifx:other".to_string())).unwrap();
+        assert_eq!(curve.name(), "perceptual");
+    }
+
+    #[test]
+    fn test_color_curve_for_light() {
+        let mut config = Config::default();
+        config.curves.custom.insert(
+            "evening".to_string(),
+            crate::curves::CurveConfig::DimToWarm { warm_k: Some(2000), cool_k: None },
+        );
+        let mut light = light_config(None, None);
+        light.curve = Some("evening".to_string());
+        config.lights.lights.insert("lifx:evening".to_string(), light);
+
+        let id = LightId("lifx:evening".to_string());
+        assert_eq!(config.curve_for_light(&id).unwrap().name(), "dim_to_warm");
+        let (_, kelvin) = config.color_curve_for_light(&id).unwrap().apply(0.0);
+        assert_eq!(kelvin, 2000);
+        assert!(config.color_curve_for_light(&LightId("lifx:other".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_curve_for_light_unknown() {
+        let mut config = Config::default();
+        let mut light = light_config(None, None);
+        light.curve = Some("wobbly".to_string());
+        config.lights.lights.insert("lifx:wobbly".to_string(), light);
+
+        let result = config.curve_for_light(&LightId("lifx:wobbly".to_string()));
+        assert!(matches!(result, Err(CurveError::Unknown(name)) if name == "wobbly"));
+    }
+
+    #[test]
+    fn test_mute_action_from_str() {
+        assert_eq!("brightness_zero".parse::<MuteAction>().unwrap(), MuteAction::BrightnessZero);
+        assert_eq!("power_off".parse::<MuteAction>().unwrap(), MuteAction::PowerOff);
+        assert_eq!("ignore".parse::<MuteAction>().unwrap(), MuteAction::Ignore);
+        assert!("explode".parse::<MuteAction>().is_err());
+    }
+
+    #[test]
+    fn test_mute_action_deserialize() {
+        let config: LightsConfig = toml::from_str(
+            "[lights.\"lifx:desk\"]\nmute_action = \"power_off\"\n",
+        ).unwrap();
+        let light = config.get(&LightId("lifx:desk".to_string())).unwrap();
+        assert_eq!(light.mute_action, Some(MuteAction::PowerOff));
+
+        let result: Result<LightsConfig, _> = toml::from_str(
+            "[lights.\"lifx:desk\"]\nmute_action = \"explode\"\n",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_map_brightness_identity() {
+        let config = light_config(None, None);
+        assert_eq!(config.map_brightness(Brightness::new(0.3)).as_f32(), 0.3);
+    }
+
+    #[test]
+    fn test_map_brightness_range() {
+        let config = light_config(Some(0.1), Some(0.9));
+        assert_eq!(config.map_brightness(Brightness::new(0.0)).as_f32(), 0.1);
+        assert_eq!(config.map_brightness(Brightness::new(1.0)).as_f32(), 0.9);
+        assert!((config.map_brightness(Brightness::new(0.3)).as_f32() - 0.34).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_map_brightness_single_bound() {
+        let config = light_config(Some(0.2), None);
+        assert_eq!(config.map_brightness(Brightness::new(0.0)).as_f32(), 0.2);
+        assert_eq!(config.map_brightness(Brightness::new(1.0)).as_f32(), 1.0);
+
+        let config = light_config(None, Some(0.5));
+        assert_eq!(config.map_brightness(Brightness::new(0.0)).as_f32(), 0.0);
+        assert_eq!(config.map_brightness(Brightness::new(1.0)).as_f32(), 0.5);
+    }
+
+    #[test]
+    fn test_unmap_brightness() {
+        let config = light_config(Some(0.1), Some(0.9));
+        for raw in [0.0, 0.25, 0.5, 1.0] {
+            let mapped = config.map_brightness(Brightness::new(raw));
+            assert!((config.unmap_brightness(mapped).as_f32() - raw).abs() < 1e-6);
+        }
+        assert_eq!(config.unmap_brightness(Brightness::new(0.05)).as_f32(), 0.0);
+        assert_eq!(config.unmap_brightness(Brightness::new(0.95)).as_f32(), 1.0);
+    }
+
+    #[test]
+    fn test_map_brightness_swapped_bounds() {
+        let config = light_config(Some(0.9), Some(0.1));
+        assert_eq!(config.map_brightness(Brightness::new(0.0)).as_f32(), 0.1);
+        assert_eq!(config.map_brightness(Brightness::new(1.0)).as_f32(), 0.9);
+    }
+}
@@ -0,0 +1,135 @@
+use super::{Curve, CurveError};
+
+const NEWTON_ITERATIONS: usize = 8;
+const BISECTION_ITERATIONS: usize = 32;
+const EPSILON: f32 = 1e-6;
+
+/// Cubic bezier from (0, 0) to (1, 1) shaped by two control handles, like
+/// CSS `cubic-bezier(x1, y1, x2, y2)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BezierCurve {
+    p1: (f32, f32),
+    p2: (f32, f32),
+}
+
+impl BezierCurve {
+    /// Both handles must lie within [0, 1] on each axis, which keeps the
+    /// curve monotonic so it can be inverted.
+    pub fn new(p1: (f32, f32), p2: (f32, f32)) -> Result<Self, CurveError> {
+        if [p1.0, p1.1, p2.0, p2.1].iter().any(|v| !(0.0..=1.0).contains(v)) {
+            return Err(CurveError::InvalidBezier("control points must lie within [0, 1]".to_string()));
+        }
+        Ok(Self { p1, p2 })
+    }
+
+    pub fn control_points(&self) -> ((f32, f32), (f32, f32)) {
+        (self.p1, self.p2)
+    }
+}
+
+/// One axis of the curve at `t`, with the end points fixed at 0 and 1.
+fn evaluate(c1: f32, c2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * t * c1 + 3.0 * u * t * t * c2 + t * t * t
+}
+
+fn derivative(c1: f32, c2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * c1 + 6.0 * u * t * (c2 - c1) + 3.0 * t * t * (1.0 - c2)
+}
+
+/// Finds `t` where one axis reaches `target`, using Newton's method and
+/// falling back to bisection where the slope is too flat.
+fn solve(c1: f32, c2: f32, target: f32) -> f32 {
+    let mut t = target;
+    for _ in 0..NEWTON_ITERATIONS {
+        let error = evaluate(c1, c2, t) - target;
+        if error.abs() < EPSILON {
+            return t;
+        }
+        let slope = derivative(c1, c2, t);
+        if slope.abs() < EPSILON {
+            break;
+        }
+        t -= error / slope;
+        if !(0.0..=1.0).contains(&t) {
+            break;
+        }
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    t = target;
+    for _ in 0..BISECTION_ITERATIONS {
+        let value = evaluate(c1, c2, t);
+        if (value - target).abs() < EPSILON {
+            break;
+        }
+        if value < target {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+    t
+}
+
+impl Curve for BezierCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        let t = solve(self.p1.0, self.p2.0, volume.clamp(0.0, 1.0));
+        evaluate(self.p1.1, self.p2.1, t).clamp(0.0, 1.0)
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        let t = solve(self.p1.1, self.p2.1, brightness.clamp(0.0, 1.0));
+        evaluate(self.p1.0, self.p2.0, t).clamp(0.0, 1.0)
+    }
+
+    fn name(&self) -> &'static str {
+        "bezier"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ease() -> BezierCurve {
+        BezierCurve::new((0.25, 0.1), (0.25, 1.0)).unwrap()
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let curve = ease();
+        assert!(curve.apply(0.0).abs() < 1e-4);
+        assert!((curve.apply(1.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_linear_handles() {
+        let curve = BezierCurve::new((0.25, 0.25), (0.75, 0.75)).unwrap();
+        for volume in [0.1, 0.33, 0.5, 0.9] {
+            assert!((curve.apply(volume) - volume).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_matches_css_ease() {
+        // Browsers report cubic-bezier(0.25, 0.1, 0.25, 1) at 50% as ~0.8024.
+        assert!((ease().apply(0.5) - 0.8024).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_inverse_round_trip() {
+        let curve = BezierCurve::new((0.7, 0.0), (1.0, 0.3)).unwrap();
+        for volume in [0.05, 0.2, 0.5, 0.8, 0.95] {
+            assert!((curve.inverse(curve.apply(volume)) - volume).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_rejects_handles_outside_unit_square() {
+        assert!(BezierCurve::new((1.2, 0.0), (0.5, 0.5)).is_err());
+        assert!(BezierCurve::new((0.5, 0.5), (0.5, -0.1)).is_err());
+    }
+}
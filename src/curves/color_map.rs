@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use crate::provider::Color;
+use super::CurveError;
+
+/// The color a `ColorMap` shows at `volume`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct ColorStop {
+    pub volume: f32,
+    #[serde(flatten)]
+    pub color: Color,
+}
+
+/// Maps volume to a color gradient through a list of stops, e.g.
+/// green -> yellow -> red as volume rises. Between stops the hue turns the
+/// shorter way round the wheel, and saturation and value move linearly.
+#[derive(Debug, Clone)]
+pub struct ColorMap {
+    stops: Vec<ColorStop>,
+}
+
+impl ColorMap {
+    /// Stops must be sorted by strictly increasing volume within `[0, 1]`.
+    /// Volumes before the first stop or after the last take its color.
+    pub fn new(stops: Vec<ColorStop>) -> Result<Self, CurveError> {
+        if stops.is_empty() {
+            return Err(CurveError::InvalidColorMap("at least one stop is required".to_string()));
+        }
+        if stops.iter().any(|stop| !(0.0..=1.0).contains(&stop.volume)) {
+            return Err(CurveError::InvalidColorMap("volumes must lie within [0, 1]".to_string()));
+        }
+        if stops.windows(2).any(|w| w[1].volume <= w[0].volume) {
+            return Err(CurveError::InvalidColorMap("volumes must be strictly increasing".to_string()));
+        }
+        if stops.iter().any(|stop| stop.color != Color::new(stop.color.hue, stop.color.saturation, stop.color.value)) {
+            return Err(CurveError::InvalidColorMap(
+                "hue must be below 360, saturation and value at most 100".to_string(),
+            ));
+        }
+        Ok(Self { stops })
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    pub fn apply(&self, volume: f32) -> Color {
+        let volume = volume.clamp(0.0, 1.0);
+        let Some(upper) = self.stops.iter().position(|stop| volume <= stop.volume) else {
+            return self.stops[self.stops.len() - 1].color;
+        };
+        if upper == 0 {
+            return self.stops[0].color;
+        }
+        let (from, to) = (self.stops[upper - 1], self.stops[upper]);
+        let t = (volume - from.volume) / (to.volume - from.volume);
+        interpolate(from.color, to.color, t)
+    }
+}
+
+fn interpolate(from: Color, to: Color, t: f32) -> Color {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    let mut delta = to.hue as f32 - from.hue as f32;
+    if delta > 180.0 {
+        delta -= 360.0;
+    } else if delta < -180.0 {
+        delta += 360.0;
+    }
+    let hue = (from.hue as f32 + delta * t).rem_euclid(360.0).round() as u16;
+    Color::new(hue, lerp(from.saturation, to.saturation), lerp(from.value, to.value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(volume: f32, hue: u16) -> ColorStop {
+        ColorStop { volume, color: Color::new(hue, 100, 100) }
+    }
+
+    #[test]
+    fn test_color_map_interpolates_in_hsv() {
+        let map = ColorMap::new(vec![stop(0.0, 120), stop(0.5, 60), stop(1.0, 0)]).unwrap();
+        assert_eq!(map.apply(0.0), Color::new(120, 100, 100));
+        assert_eq!(map.apply(0.25), Color::new(90, 100, 100));
+        assert_eq!(map.apply(0.5), Color::new(60, 100, 100));
+        assert_eq!(map.apply(1.0), Color::new(0, 100, 100));
+        assert_eq!(map.apply(2.0), Color::new(0, 100, 100));
+
+        let faded = ColorMap::new(vec![
+            ColorStop { volume: 0.2, color: Color::new(0, 0, 0) },
+            ColorStop { volume: 0.6, color: Color::new(0, 100, 100) },
+        ])
+        .unwrap();
+        assert_eq!(faded.apply(0.0), Color::new(0, 0, 0));
+        assert_eq!(faded.apply(0.4), Color::new(0, 50, 50));
+    }
+
+    #[test]
+    fn test_color_map_hue_takes_short_way() {
+        let map = ColorMap::new(vec![stop(0.0, 350), stop(1.0, 10)]).unwrap();
+        assert_eq!(map.apply(0.5).hue, 0);
+        assert_eq!(map.apply(0.25).hue, 355);
+    }
+
+    #[test]
+    fn test_color_map_rejects_bad_stops() {
+        assert!(ColorMap::new(vec![]).is_err());
+        assert!(ColorMap::new(vec![stop(0.5, 0), stop(0.5, 10)]).is_err());
+        assert!(ColorMap::new(vec![stop(1.5, 0)]).is_err());
+        let bad = ColorStop { volume: 0.0, color: Color { hue: 400, saturation: 100, value: 100 } };
+        assert!(ColorMap::new(vec![bad]).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use super::Curve;
+
+/// Chains curves: `apply` runs each stage in order, `inverse` undoes them in reverse.
+pub struct CompositeCurve(pub Vec<Box<dyn Curve>>);
+
+impl CompositeCurve {
+    pub fn new(stages: Vec<Box<dyn Curve>>) -> Self {
+        Self(stages)
+    }
+
+    pub fn stages(&self) -> &[Box<dyn Curve>] {
+        &self.0
+    }
+}
+
+impl Curve for CompositeCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        self.0.iter().fold(volume, |value, stage| stage.apply(value))
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        self.0.iter().rev().fold(brightness, |value, stage| stage.inverse(value))
+    }
+
+    fn name(&self) -> &'static str {
+        "composite"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::{GammaCurve, LinearCurve, LookupTableCurve};
+
+    fn table() -> Box<dyn Curve> {
+        Box::new(LookupTableCurve::new(vec![(0.0, 0.0), (0.5, 0.2), (1.0, 1.0)]).unwrap())
+    }
+
+    #[test]
+    fn test_linear_is_identity() {
+        let plain = table();
+        let before = CompositeCurve::new(vec![Box::new(LinearCurve), table()]);
+        let after = CompositeCurve::new(vec![table(), Box::new(LinearCurve)]);
+        for x in [0.0, 0.1, 0.25, 0.5, 0.8, 1.0] {
+            assert!((before.apply(x) - plain.apply(x)).abs() < 1e-6);
+            assert!((after.apply(x) - plain.apply(x)).abs() < 1e-6);
+            assert!((before.inverse(x) - plain.inverse(x)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_two_stage_round_trip() {
+        let curve = CompositeCurve::new(vec![table(), Box::new(GammaCurve::default())]);
+        for x in [0.05, 0.2, 0.5, 0.75, 1.0] {
+            assert!((curve.inverse(curve.apply(x)) - x).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_empty_is_identity() {
+        let curve = CompositeCurve::new(Vec::new());
+        assert_eq!(curve.apply(0.3), 0.3);
+        assert_eq!(curve.inverse(0.3), 0.3);
+    }
+}
@@ -0,0 +1,71 @@
+use super::{ColorCurve, Curve};
+use crate::provider::Brightness;
+
+/// Dims linearly while sliding color temperature from `cool_k` at full
+/// volume down to `warm_k` at silence, the way an incandescent filament does.
+pub struct DimToWarmCurve {
+    pub warm_k: u16,
+    pub cool_k: u16,
+}
+
+impl Default for DimToWarmCurve {
+    fn default() -> Self {
+        Self { warm_k: 2500, cool_k: 4000 }
+    }
+}
+
+impl DimToWarmCurve {
+    pub fn kelvin(&self, volume: f32) -> u16 {
+        let warm = self.warm_k as f32;
+        let cool = self.cool_k as f32;
+        (warm + (cool - warm) * volume.clamp(0.0, 1.0)).round() as u16
+    }
+}
+
+impl Curve for DimToWarmCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        volume.clamp(0.0, 1.0)
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        brightness.clamp(0.0, 1.0)
+    }
+
+    fn name(&self) -> &'static str {
+        "dim_to_warm"
+    }
+}
+
+impl ColorCurve for DimToWarmCurve {
+    fn apply(&self, volume: f32) -> (Brightness, u16) {
+        (Brightness::new(Curve::apply(self, volume)), self.kelvin(volume))
+    }
+
+    fn name(&self) -> &'static str {
+        "dim_to_warm"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dim_to_warm_endpoints() {
+        let curve = DimToWarmCurve { warm_k: 2000, cool_k: 4000 };
+        let (brightness, kelvin) = ColorCurve::apply(&curve, 1.0);
+        assert_eq!(brightness.as_f32(), 1.0);
+        assert_eq!(kelvin, 4000);
+
+        let (brightness, kelvin) = ColorCurve::apply(&curve, 0.0);
+        assert_eq!(brightness.as_f32(), 0.0);
+        assert_eq!(kelvin, 2000);
+    }
+
+    #[test]
+    fn test_dim_to_warm_interpolates() {
+        let curve = DimToWarmCurve { warm_k: 2000, cool_k: 4000 };
+        assert_eq!(curve.kelvin(0.5), 3000);
+        assert_eq!(curve.kelvin(0.25), 2500);
+    }
+}
@@ -0,0 +1,17 @@
+#[derive(Debug, thiserror::Error)]
+pub enum CurveError {
+    #[error("Unknown curve: {0}")]
+    Unknown(String),
+    #[error("Invalid lookup table: {0}")]
+    InvalidTable(String),
+    #[error("Invalid bezier curve: {0}")]
+    InvalidBezier(String),
+    #[error("Invalid floor/ceiling bounds: {0}")]
+    InvalidBounds(String),
+    #[error("Unknown color map: {0}")]
+    UnknownColorMap(String),
+    #[error("Invalid color map: {0}")]
+    InvalidColorMap(String),
+    #[error("Invalid custom curve {name}: {source}")]
+    InvalidCustom { name: String, source: Box<CurveError> },
+}
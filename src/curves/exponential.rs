@@ -0,0 +1,43 @@
+use super::Curve;
+
+/// Grows slowly then rapidly: brightness is `(base^volume - 1) / (base - 1)`,
+/// inverted by `ln(1 + brightness * (base - 1)) / ln(base)`. This is the
+/// mirror of `logarithmic`, giving fine control at the low end of the volume
+/// range like the log-taper faders of audio gear.
+pub struct ExponentialCurve {
+    pub base: f32,
+}
+
+impl Default for ExponentialCurve {
+    fn default() -> Self {
+        Self { base: 10.0 }
+    }
+}
+
+impl ExponentialCurve {
+    fn is_linear(&self) -> bool {
+        self.base <= 0.0 || (self.base - 1.0).abs() < 1e-6
+    }
+}
+
+impl Curve for ExponentialCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        let volume = volume.clamp(0.0, 1.0);
+        if self.is_linear() {
+            return volume;
+        }
+        ((self.base.powf(volume) - 1.0) / (self.base - 1.0)).clamp(0.0, 1.0)
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        let brightness = brightness.clamp(0.0, 1.0);
+        if self.is_linear() {
+            return brightness;
+        }
+        ((1.0 + brightness * (self.base - 1.0)).ln() / self.base.ln()).clamp(0.0, 1.0)
+    }
+
+    fn name(&self) -> &'static str {
+        "exponential"
+    }
+}
@@ -0,0 +1,109 @@
+use super::{Curve, CurveError, LinearCurve};
+
+/// Wraps a curve so the bottom of the volume range holds the light at a
+/// minimum on-state brightness and the top reaches full brightness before the
+/// slider does. Between `floor_in` and `ceil_in` the inner curve is stretched
+/// over the remaining range.
+pub struct FloorCeilCurve {
+    floor_in: f32,
+    floor_out: f32,
+    ceil_in: f32,
+    inner: Box<dyn Curve>,
+}
+
+impl FloorCeilCurve {
+    pub const DEFAULT_FLOOR_IN: f32 = 0.05;
+    pub const DEFAULT_FLOOR_OUT: f32 = 0.01;
+    pub const DEFAULT_CEIL_IN: f32 = 0.95;
+
+    pub fn new(inner: Box<dyn Curve>, floor_in: f32, floor_out: f32, ceil_in: f32) -> Result<Self, CurveError> {
+        if [floor_in, floor_out, ceil_in].iter().any(|v| !(0.0..=1.0).contains(v)) {
+            return Err(CurveError::InvalidBounds("values must lie within [0, 1]".to_string()));
+        }
+        if floor_in >= ceil_in {
+            return Err(CurveError::InvalidBounds("floor_in must be below ceil_in".to_string()));
+        }
+        if floor_out >= 1.0 {
+            return Err(CurveError::InvalidBounds("floor_out must be below 1.0".to_string()));
+        }
+        Ok(Self { floor_in, floor_out, ceil_in, inner })
+    }
+}
+
+impl Default for FloorCeilCurve {
+    fn default() -> Self {
+        Self {
+            floor_in: Self::DEFAULT_FLOOR_IN,
+            floor_out: Self::DEFAULT_FLOOR_OUT,
+            ceil_in: Self::DEFAULT_CEIL_IN,
+            inner: Box::new(LinearCurve),
+        }
+    }
+}
+
+impl Curve for FloorCeilCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        if volume < self.floor_in {
+            return self.floor_out;
+        }
+        if volume > self.ceil_in {
+            return 1.0;
+        }
+        let t = (volume - self.floor_in) / (self.ceil_in - self.floor_in);
+        (self.floor_out + self.inner.apply(t) * (1.0 - self.floor_out)).clamp(0.0, 1.0)
+    }
+
+    /// Brightness at or below the floor reads back as zero volume, so a light
+    /// that was snapped to its floor does not drag the slider up.
+    fn inverse(&self, brightness: f32) -> f32 {
+        if brightness <= self.floor_out {
+            return 0.0;
+        }
+        if brightness >= 1.0 {
+            return 1.0;
+        }
+        let t = self.inner.inverse((brightness - self.floor_out) / (1.0 - self.floor_out));
+        (self.floor_in + t * (self.ceil_in - self.floor_in)).clamp(0.0, 1.0)
+    }
+
+    fn name(&self) -> &'static str {
+        "floor_ceil"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::GammaCurve;
+
+    fn curve() -> FloorCeilCurve {
+        FloorCeilCurve::new(Box::new(LinearCurve), 0.05, 0.02, 0.9).unwrap()
+    }
+
+    #[test]
+    fn test_floor_and_ceiling() {
+        let curve = curve();
+        assert_eq!(curve.apply(0.0), 0.02);
+        assert_eq!(curve.apply(0.049), 0.02);
+        assert!((curve.apply(0.05) - 0.02).abs() < 1e-6);
+        assert_eq!(curve.apply(0.95), 1.0);
+        assert!((curve.apply(0.9) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_inverse_inside_range() {
+        let curve = FloorCeilCurve::new(Box::new(GammaCurve::default()), 0.05, 0.02, 0.9).unwrap();
+        for volume in [0.1, 0.3, 0.5, 0.85] {
+            assert!((curve.inverse(curve.apply(volume)) - volume).abs() < 1e-4);
+        }
+        assert_eq!(curve.inverse(0.02), 0.0);
+        assert_eq!(curve.inverse(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_rejects_invalid_bounds() {
+        assert!(FloorCeilCurve::new(Box::new(LinearCurve), 0.5, 0.0, 0.4).is_err());
+        assert!(FloorCeilCurve::new(Box::new(LinearCurve), 0.0, 1.0, 0.9).is_err());
+        assert!(FloorCeilCurve::new(Box::new(LinearCurve), -0.1, 0.0, 0.9).is_err());
+    }
+}
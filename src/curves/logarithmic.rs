@@ -1,5 +1,9 @@
 use super::Curve;
 
+/// Logarithmic curve: brightness is `ln(1 + volume * (base - 1)) / ln(base)`,
+/// rising quickly at low volumes and flattening towards the top. Larger
+/// bases bend harder; a base of 1 (or below 0) is linear. `exponential` is
+/// its mirror image.
 pub struct LogarithmicCurve {
     pub base: f32,
 }
@@ -10,16 +14,27 @@ impl Default for LogarithmicCurve {
     }
 }
 
+impl LogarithmicCurve {
+    fn is_linear(&self) -> bool {
+        self.base <= 0.0 || (self.base - 1.0).abs() < 1e-6
+    }
+}
+
 impl Curve for LogarithmicCurve {
     fn apply(&self, volume: f32) -> f32 {
-        if volume <= 0.0 {
-            return 0.0;
+        let volume = volume.clamp(0.0, 1.0);
+        if self.is_linear() {
+            return volume;
         }
-        (volume.powf(1.0 / self.base.log10())).clamp(0.0, 1.0)
+        ((1.0 + volume * (self.base - 1.0)).ln() / self.base.ln()).clamp(0.0, 1.0)
     }
 
     fn inverse(&self, brightness: f32) -> f32 {
-        brightness.powf(self.base.log10()).clamp(0.0, 1.0)
+        let brightness = brightness.clamp(0.0, 1.0);
+        if self.is_linear() {
+            return brightness;
+        }
+        ((self.base.powf(brightness) - 1.0) / (self.base - 1.0)).clamp(0.0, 1.0)
     }
 
     fn name(&self) -> &'static str {
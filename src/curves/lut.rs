@@ -0,0 +1,43 @@
+use super::Curve;
+
+/// Entries in a `LutCurve`'s table, spanning volume 0 to 1 inclusive.
+pub const LUT_SIZE: usize = 1024;
+
+/// Another curve's `apply` sampled into a table and linearly interpolated,
+/// trading a little accuracy for skipping `powf` on every volume event.
+/// `inverse` still calls the wrapped curve: it's off the sync-to-light hot
+/// path, and the power laws' inverses are too steep near black to sample
+/// evenly.
+pub struct LutCurve {
+    curve: Box<dyn Curve>,
+    table: Box<[f32]>,
+}
+
+impl LutCurve {
+    pub fn new(curve: Box<dyn Curve>) -> Self {
+        let table = (0..LUT_SIZE)
+            .map(|i| curve.apply(i as f32 / (LUT_SIZE - 1) as f32))
+            .collect();
+        Self { curve, table }
+    }
+}
+
+impl Curve for LutCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        let position = volume.clamp(0.0, 1.0) * (LUT_SIZE - 1) as f32;
+        let index = position as usize;
+        if index >= LUT_SIZE - 1 {
+            return self.table[LUT_SIZE - 1];
+        }
+        let (low, high) = (self.table[index], self.table[index + 1]);
+        low + (high - low) * (position - index as f32)
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        self.curve.inverse(brightness)
+    }
+
+    fn name(&self) -> &'static str {
+        self.curve.name()
+    }
+}
@@ -1,7 +1,19 @@
+pub mod bezier;
+pub mod color_map;
+pub mod composite;
+pub mod dim_to_warm;
+pub mod error;
+pub mod exponential;
+pub mod floor_ceil;
 pub mod gamma;
 pub mod linear;
 pub mod logarithmic;
+pub mod lut;
 pub mod perceptual;
+pub mod registry;
+pub mod srgb;
+pub mod stevens;
+pub mod table;
 
 pub trait Curve: Send + Sync {
     fn apply(&self, volume: f32) -> f32;
@@ -9,31 +21,312 @@ pub trait Curve: Send + Sync {
     fn name(&self) -> &'static str;
 }
 
+/// A curve that drives color temperature alongside brightness.
+pub trait ColorCurve: Send + Sync {
+    fn apply(&self, volume: f32) -> (crate::provider::Brightness, u16);
+    fn name(&self) -> &'static str;
+}
+
+pub use bezier::BezierCurve;
+pub use color_map::{ColorMap, ColorStop};
+pub use composite::CompositeCurve;
+pub use dim_to_warm::DimToWarmCurve;
+pub use error::CurveError;
+pub use exponential::ExponentialCurve;
+pub use floor_ceil::FloorCeilCurve;
 pub use gamma::GammaCurve;
 pub use linear::LinearCurve;
 pub use logarithmic::LogarithmicCurve;
+pub use lut::{LutCurve, LUT_SIZE};
 pub use perceptual::PerceptualCurve;
+pub use registry::CurveRegistry;
+pub use srgb::SrgbCurve;
+pub use stevens::StevensCurve;
+pub use table::LookupTableCurve;
+
+/// Names accepted by `builtin`, without any custom parameters.
+pub const BUILTIN_CURVES: &[&str] = &["linear", "logarithmic", "exponential", "gamma", "perceptual", "srgb", "stevens", "dim_to_warm"];
+
+/// Constructs a built-in curve with its default parameters.
+pub fn builtin(name: &str) -> Option<Box<dyn Curve>> {
+    match name {
+        "linear" => Some(Box::new(LinearCurve)),
+        "logarithmic" => Some(Box::new(LogarithmicCurve::default())),
+        "exponential" => Some(Box::new(ExponentialCurve::default())),
+        "gamma" => Some(Box::new(GammaCurve::default())),
+        "perceptual" => Some(Box::new(PerceptualCurve)),
+        "srgb" => Some(Box::new(SrgbCurve)),
+        "stevens" => Some(Box::new(StevensCurve::default())),
+        "dim_to_warm" => Some(Box::new(DimToWarmCurve::default())),
+        _ => None,
+    }
+}
+
+/// Constructs a built-in color curve with its default parameters.
+pub fn builtin_color(name: &str) -> Option<Box<dyn ColorCurve>> {
+    match name {
+        "dim_to_warm" => Some(Box::new(DimToWarmCurve::default())),
+        _ => None,
+    }
+}
 
+/// `lut = true` on the curves built on `powf` samples them into a `LutCurve`
+/// for speed, at an error below 1e-5.
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum CurveConfig {
     Linear,
-    Logarithmic { base: Option<f32> },
-    Gamma { gamma: Option<f32> },
-    Perceptual,
+    Logarithmic {
+        base: Option<f32>,
+        #[serde(default)]
+        lut: bool,
+    },
+    Exponential {
+        base: Option<f32>,
+        #[serde(default)]
+        lut: bool,
+    },
+    Gamma {
+        gamma: Option<f32>,
+        #[serde(default)]
+        lut: bool,
+    },
+    Perceptual {
+        #[serde(default)]
+        lut: bool,
+    },
+    Srgb {
+        #[serde(default)]
+        lut: bool,
+    },
+    Stevens {
+        exponent: Option<f32>,
+        #[serde(default)]
+        lut: bool,
+    },
+    Table { points: Vec<[f32; 2]> },
+    Bezier { p1: [f32; 2], p2: [f32; 2] },
+    Composite { stages: Vec<CurveConfig> },
+    /// Holds `floor_out` below `floor_in` and full brightness above `ceil_in`,
+    /// stretching `curve` (linear if unset) in between.
+    #[serde(rename = "floor_ceil")]
+    FloorCeil {
+        floor_in: Option<f32>,
+        floor_out: Option<f32>,
+        ceil_in: Option<f32>,
+        curve: Option<Box<CurveConfig>>,
+    },
+    #[serde(rename = "dim_to_warm")]
+    DimToWarm { warm_k: Option<u16>, cool_k: Option<u16> },
 }
 
 impl CurveConfig {
-    pub fn into_curve(self) -> Box<dyn Curve> {
-        match self {
+    pub fn into_curve(self) -> Result<Box<dyn Curve>, CurveError> {
+        Ok(match self {
             CurveConfig::Linear => Box::new(LinearCurve),
-            CurveConfig::Logarithmic { base } => Box::new(LogarithmicCurve {
+            CurveConfig::Logarithmic { base, lut } => sampled(Box::new(LogarithmicCurve {
                 base: base.unwrap_or(10.0),
-            }),
-            CurveConfig::Gamma { gamma } => Box::new(GammaCurve {
+            }), lut),
+            CurveConfig::Exponential { base, lut } => sampled(Box::new(ExponentialCurve {
+                base: base.unwrap_or(ExponentialCurve::default().base),
+            }), lut),
+            CurveConfig::Gamma { gamma, lut } => sampled(Box::new(GammaCurve {
                 gamma: gamma.unwrap_or(2.2),
-            }),
-            CurveConfig::Perceptual => Box::new(PerceptualCurve),
+            }), lut),
+            CurveConfig::Perceptual { lut } => sampled(Box::new(PerceptualCurve), lut),
+            CurveConfig::Srgb { lut } => sampled(Box::new(SrgbCurve), lut),
+            CurveConfig::Stevens { exponent, lut } => sampled(Box::new(StevensCurve {
+                exponent: exponent.unwrap_or(StevensCurve::default().exponent),
+            }), lut),
+            CurveConfig::Table { points } => Box::new(LookupTableCurve::new(
+                points.into_iter().map(|[x, y]| (x, y)).collect(),
+            )?),
+            CurveConfig::Bezier { p1: [x1, y1], p2: [x2, y2] } => Box::new(BezierCurve::new((x1, y1), (x2, y2))?),
+            CurveConfig::Composite { stages } => Box::new(CompositeCurve::new(
+                stages.into_iter().map(CurveConfig::into_curve).collect::<Result<_, _>>()?,
+            )),
+            CurveConfig::FloorCeil { floor_in, floor_out, ceil_in, curve } => Box::new(FloorCeilCurve::new(
+                match curve {
+                    Some(curve) => curve.into_curve()?,
+                    None => Box::new(LinearCurve),
+                },
+                floor_in.unwrap_or(FloorCeilCurve::DEFAULT_FLOOR_IN),
+                floor_out.unwrap_or(FloorCeilCurve::DEFAULT_FLOOR_OUT),
+                ceil_in.unwrap_or(FloorCeilCurve::DEFAULT_CEIL_IN),
+            )?),
+            CurveConfig::DimToWarm { warm_k, cool_k } => Box::new(dim_to_warm(warm_k, cool_k)),
+        })
+    }
+
+    /// Returns the color side of curves that also drive kelvin.
+    pub fn into_color_curve(self) -> Option<Box<dyn ColorCurve>> {
+        match self {
+            CurveConfig::DimToWarm { warm_k, cool_k } => Some(Box::new(dim_to_warm(warm_k, cool_k))),
+            _ => None,
+        }
+    }
+}
+
+fn sampled(curve: Box<dyn Curve>, lut: bool) -> Box<dyn Curve> {
+    if lut {
+        Box::new(LutCurve::new(curve))
+    } else {
+        curve
+    }
+}
+
+fn dim_to_warm(warm_k: Option<u16>, cool_k: Option<u16>) -> DimToWarmCurve {
+    let defaults = DimToWarmCurve::default();
+    DimToWarmCurve {
+        warm_k: warm_k.unwrap_or(defaults.warm_k),
+        cool_k: cool_k.unwrap_or(defaults.cool_k),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn assert_round_trip(curve: &dyn Curve, x: f32) {
+        let y = curve.apply(x);
+        let back = curve.inverse(y);
+        assert!((back - x).abs() < 1e-3, "{}: inverse(apply({})) = {}", curve.name(), x, back);
+    }
+
+    proptest! {
+        #[test]
+        fn test_linear_round_trip(x in 0.0f32..=1.0) {
+            assert_round_trip(&LinearCurve, x);
+        }
+
+        #[test]
+        fn test_logarithmic_round_trip(x in 0.0f32..=1.0, base in 1.5f32..100.0) {
+            assert_round_trip(&LogarithmicCurve { base }, x);
+        }
+
+        #[test]
+        fn test_exponential_round_trip(x in 0.0f32..=1.0, base in 1.5f32..100.0) {
+            assert_round_trip(&ExponentialCurve { base }, x);
+        }
+
+        #[test]
+        fn test_gamma_round_trip(x in 0.0f32..=1.0, gamma in 0.5f32..3.0) {
+            assert_round_trip(&GammaCurve { gamma }, x);
+        }
+
+        #[test]
+        fn test_stevens_round_trip(x in 0.0f32..=1.0, exponent in 0.2f32..1.0) {
+            assert_round_trip(&StevensCurve { exponent }, x);
+        }
+
+        #[test]
+        fn test_perceptual_round_trip(x in 0.0f32..=1.0) {
+            assert_round_trip(&PerceptualCurve, x);
+        }
+
+        #[test]
+        fn test_srgb_round_trip(x in 0.0f32..=1.0) {
+            assert_round_trip(&SrgbCurve, x);
+        }
+    }
+
+    #[test]
+    fn test_logarithmic_shape() {
+        let curve = LogarithmicCurve::default();
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert!((curve.apply(1.0) - 1.0).abs() < 1e-6);
+        assert!((curve.apply(0.5) - 5.5f32.log10()).abs() < 1e-6);
+        assert!(curve.apply(0.5) > 0.5);
+        assert_eq!(LogarithmicCurve { base: 1.0 }.apply(0.4), 0.4);
+    }
+
+    #[test]
+    fn test_exponential_endpoints_exact() {
+        for base in [2.0, ExponentialCurve::default().base, 50.0] {
+            let curve = ExponentialCurve { base };
+            assert_eq!(curve.apply(0.0), 0.0);
+            assert_eq!(curve.apply(1.0), 1.0);
+            assert_eq!(curve.inverse(0.0), 0.0);
+            assert_eq!(curve.inverse(1.0), 1.0);
+            assert!(curve.apply(0.5) < 0.5);
+        }
+        assert!(builtin("exponential").is_some());
+    }
+
+    #[test]
+    fn test_exponential_mirrors_logarithmic() {
+        let (logarithmic, exponential) = (builtin("logarithmic").unwrap(), builtin("exponential").unwrap());
+        assert!(logarithmic.apply(0.5) > 0.5);
+        assert!(exponential.apply(0.5) < 0.5);
+        for x in [0.1, 0.25, 0.5, 0.9] {
+            assert!((exponential.apply(x) - logarithmic.inverse(x)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_lut_matches_analytic_curves() {
+        for name in ["logarithmic", "exponential", "gamma", "perceptual", "srgb", "stevens"] {
+            let exact = builtin(name).unwrap();
+            let lut = LutCurve::new(builtin(name).unwrap());
+            assert_eq!(lut.name(), exact.name());
+            assert_eq!(lut.apply(0.0), exact.apply(0.0));
+            assert_eq!(lut.apply(1.0), exact.apply(1.0));
+            for i in 0..=10_000 {
+                let x = i as f32 / 10_000.0;
+                let error = (lut.apply(x) - exact.apply(x)).abs();
+                assert!(error < 1e-5, "{}: apply({}) off by {}", name, x, error);
+                assert_eq!(lut.inverse(x), exact.inverse(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_lut_flag() {
+        let config: CurveConfig = toml::from_str("type = \"perceptual\"\nlut = true\n").unwrap();
+        assert!(matches!(config, CurveConfig::Perceptual { lut: true }));
+        let config: CurveConfig = toml::from_str("type = \"gamma\"\n").unwrap();
+        assert!(matches!(config, CurveConfig::Gamma { gamma: None, lut: false }));
+    }
+
+    #[test]
+    fn test_stevens_shape() {
+        let curve = StevensCurve { exponent: 0.5 };
+        assert!((curve.apply(0.5) - 0.25).abs() < 1e-6);
+        assert!((curve.inverse(0.25) - 0.5).abs() < 1e-6);
+        assert!(builtin("stevens").is_some());
+    }
+
+    #[test]
+    fn test_perceptual_is_continuous_at_knee() {
+        let below = PerceptualCurve.apply(0.08);
+        let above = PerceptualCurve.apply(0.080_01);
+        assert!((below - 0.008856).abs() < 1e-5);
+        assert!((above - below).abs() < 1e-4);
+        assert!((PerceptualCurve.inverse(0.008856) - 0.08).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_srgb_is_continuous_at_breakpoint() {
+        let below = SrgbCurve.inverse(0.003_130_8);
+        let above = SrgbCurve.inverse(0.003_130_9);
+        assert!((below - 0.040_45).abs() < 1e-5);
+        assert!((above - below).abs() < 1e-5);
+
+        let below = SrgbCurve.apply(0.040_45);
+        let above = SrgbCurve.apply(0.040_46);
+        assert!((below - 0.003_130_8).abs() < 1e-6);
+        assert!((above - below).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_srgb_round_trip_near_black() {
+        for i in 0..=100 {
+            let x = i as f32 / 1000.0;
+            let back = SrgbCurve.inverse(SrgbCurve.apply(x));
+            assert!((back - x).abs() < 1e-5, "inverse(apply({})) = {}", x, back);
         }
+        assert_eq!(SrgbCurve.apply(1.0), 1.0);
+        assert!((SrgbCurve.apply(0.5) - 0.214_04).abs() < 1e-4);
     }
 }
@@ -1,11 +1,21 @@
 use super::Curve;
 
+/// CIE 1976 lightness: volume is treated as L* / 100 and brightness as
+/// relative luminance Y. Both branches meet at L* = 8, which is Y = 0.008856,
+/// so `apply` and `inverse` switch at the same point.
 pub struct PerceptualCurve;
 
+/// L* / 100 at the linear/cubic breakpoint.
+const LIGHTNESS_KNEE: f32 = 0.08;
+/// Y at the same breakpoint, `((0.08 + 0.16) / 1.16)^3`.
+const LUMINANCE_KNEE: f32 = 0.008856;
+/// Slope of the linear segment, `903.3 / 100`.
+const LINEAR_SLOPE: f32 = 9.033;
+
 impl Curve for PerceptualCurve {
     fn apply(&self, volume: f32) -> f32 {
-        if volume <= 0.08 {
-            volume / 9.033
+        if volume <= LIGHTNESS_KNEE {
+            volume / LINEAR_SLOPE
         } else {
             ((volume + 0.16) / 1.16).powf(3.0)
         }
@@ -13,8 +23,8 @@ impl Curve for PerceptualCurve {
     }
 
     fn inverse(&self, brightness: f32) -> f32 {
-        if brightness <= 0.008856 {
-            brightness * 9.033
+        if brightness <= LUMINANCE_KNEE {
+            brightness * LINEAR_SLOPE
         } else {
             1.16 * brightness.powf(1.0 / 3.0) - 0.16
         }
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use super::{builtin, Curve, CurveConfig, CurveError, BUILTIN_CURVES};
+
+/// Constructed curves by name, shared between every light that uses them.
+#[derive(Default)]
+pub struct CurveRegistry {
+    curves: HashMap<String, Arc<dyn Curve>>,
+}
+
+impl CurveRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every built-in curve with its default parameters.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        for name in BUILTIN_CURVES {
+            if let Some(curve) = builtin(name) {
+                registry.register(name.to_string(), curve);
+            }
+        }
+        registry
+    }
+
+    /// The built-in curves plus `custom`, which shadow built-ins of the same name.
+    pub fn from_custom(custom: &HashMap<String, CurveConfig>) -> Result<Self, CurveError> {
+        let mut registry = Self::with_builtins();
+        for (name, config) in custom {
+            let curve = config.clone().into_curve().map_err(|e| CurveError::InvalidCustom {
+                name: name.clone(),
+                source: Box::new(e),
+            })?;
+            registry.register(name.clone(), curve);
+        }
+        Ok(registry)
+    }
+
+    /// Adds a curve, replacing any existing one with the same name.
+    pub fn register(&mut self, name: String, curve: Box<dyn Curve>) {
+        self.curves.insert(name, Arc::from(curve));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Curve> {
+        self.curves.get(name).map(|curve| curve.as_ref())
+    }
+
+    /// A shared handle to the named curve, or `CurveError::Unknown`.
+    pub fn resolve(&self, name: &str) -> Result<Arc<dyn Curve>, CurveError> {
+        self.curves.get(name).cloned().ok_or_else(|| CurveError::Unknown(name.to_string()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.curves.contains_key(name)
+    }
+
+    /// Registered names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.curves.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_builtins() {
+        let registry = CurveRegistry::with_builtins();
+        for name in BUILTIN_CURVES {
+            assert_eq!(registry.get(name).unwrap().name(), *name);
+        }
+        assert!(registry.get("wobbly").is_none());
+        assert!(matches!(registry.resolve("wobbly"), Err(CurveError::Unknown(name)) if name == "wobbly"));
+    }
+
+    #[test]
+    fn test_custom_shadows_builtin() {
+        let mut custom = HashMap::new();
+        custom.insert("linear".to_string(), CurveConfig::Gamma { gamma: Some(2.0), lut: false });
+        custom.insert("soft".to_string(), CurveConfig::Perceptual { lut: false });
+        let registry = CurveRegistry::from_custom(&custom).unwrap();
+        assert_eq!(registry.get("linear").unwrap().name(), "gamma");
+        assert_eq!(registry.get("soft").unwrap().name(), "perceptual");
+        assert!(Arc::ptr_eq(&registry.resolve("soft").unwrap(), &registry.resolve("soft").unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_custom_names_the_curve() {
+        let mut custom = HashMap::new();
+        custom.insert("broken".to_string(), CurveConfig::Table { points: vec![[0.0, 0.0]] });
+        let result = CurveRegistry::from_custom(&custom);
+        assert!(matches!(result, Err(CurveError::InvalidCustom { name, .. }) if name == "broken"));
+    }
+}
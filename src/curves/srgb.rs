@@ -0,0 +1,43 @@
+use super::Curve;
+
+/// The exact sRGB transfer functions: volume is treated as an encoded sRGB
+/// value and brightness as linear light. `apply` is the EOTF,
+/// `((v + 0.055) / 1.055)^2.4` above 0.04045 and `v / 12.92` below; `inverse`
+/// is the OETF, `1.055 * b^(1/2.4) - 0.055` above 0.0031308 and `12.92 * b`
+/// below. Unlike `gamma`, it keeps the linear toe, so it doesn't crush the
+/// range near black.
+pub struct SrgbCurve;
+
+/// Encoded value at the breakpoint, `12.92 * LINEAR_KNEE` rounded as the
+/// standard gives it.
+const ENCODED_KNEE: f32 = 0.04045;
+/// Linear value at the breakpoint.
+const LINEAR_KNEE: f32 = 0.003_130_8;
+/// Slope of the linear toe.
+const TOE_SLOPE: f32 = 12.92;
+
+impl Curve for SrgbCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        let volume = volume.clamp(0.0, 1.0);
+        if volume <= ENCODED_KNEE {
+            volume / TOE_SLOPE
+        } else {
+            ((volume + 0.055) / 1.055).powf(2.4)
+        }
+        .clamp(0.0, 1.0)
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        let brightness = brightness.clamp(0.0, 1.0);
+        if brightness <= LINEAR_KNEE {
+            brightness * TOE_SLOPE
+        } else {
+            1.055 * brightness.powf(1.0 / 2.4) - 0.055
+        }
+        .clamp(0.0, 1.0)
+    }
+
+    fn name(&self) -> &'static str {
+        "srgb"
+    }
+}
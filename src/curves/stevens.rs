@@ -0,0 +1,28 @@
+use super::Curve;
+
+/// Stevens' power law for perceived brightness, `perceived = intensity^exponent`.
+/// Volume is taken as the perceived level, so brightness is `volume^(1/exponent)`.
+pub struct StevensCurve {
+    pub exponent: f32,
+}
+
+impl Default for StevensCurve {
+    /// 0.33 is Stevens' exponent for brightness of an extended source.
+    fn default() -> Self {
+        Self { exponent: 0.33 }
+    }
+}
+
+impl Curve for StevensCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        volume.clamp(0.0, 1.0).powf(1.0 / self.exponent).clamp(0.0, 1.0)
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        brightness.clamp(0.0, 1.0).powf(self.exponent).clamp(0.0, 1.0)
+    }
+
+    fn name(&self) -> &'static str {
+        "stevens"
+    }
+}
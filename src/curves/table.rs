@@ -0,0 +1,102 @@
+use super::{Curve, CurveError};
+
+/// Piecewise-linear curve through measured (volume, brightness) points.
+pub struct LookupTableCurve {
+    points: Vec<(f32, f32)>,
+}
+
+impl LookupTableCurve {
+    /// Points must be sorted by volume, span 0.0 to 1.0, and have
+    /// non-decreasing brightness so the curve can be inverted.
+    pub fn new(points: Vec<(f32, f32)>) -> Result<Self, CurveError> {
+        if points.len() < 2 {
+            return Err(CurveError::InvalidTable("at least two points are required".to_string()));
+        }
+        if points.iter().any(|&(x, y)| !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y)) {
+            return Err(CurveError::InvalidTable("points must lie within [0, 1]".to_string()));
+        }
+        if points.windows(2).any(|w| w[1].0 <= w[0].0) {
+            return Err(CurveError::InvalidTable("volumes must be strictly increasing".to_string()));
+        }
+        if points.windows(2).any(|w| w[1].1 < w[0].1) {
+            return Err(CurveError::InvalidTable("brightness must not decrease".to_string()));
+        }
+        if points[0].0 != 0.0 || points[points.len() - 1].0 != 1.0 {
+            return Err(CurveError::InvalidTable("volumes must start at 0.0 and end at 1.0".to_string()));
+        }
+        Ok(Self { points })
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+}
+
+fn interpolate(points: impl Iterator<Item = (f32, f32)>, input: f32) -> f32 {
+    let mut prev: Option<(f32, f32)> = None;
+    for (x, y) in points {
+        if input <= x {
+            return match prev {
+                Some((x0, y0)) if x > x0 => y0 + (input - x0) / (x - x0) * (y - y0),
+                _ => y,
+            };
+        }
+        prev = Some((x, y));
+    }
+    prev.map(|(_, y)| y).unwrap_or(input)
+}
+
+impl Curve for LookupTableCurve {
+    fn apply(&self, volume: f32) -> f32 {
+        interpolate(self.points.iter().copied(), volume.clamp(0.0, 1.0))
+    }
+
+    fn inverse(&self, brightness: f32) -> f32 {
+        interpolate(self.points.iter().map(|&(x, y)| (y, x)), brightness.clamp(0.0, 1.0))
+    }
+
+    fn name(&self) -> &'static str {
+        "table"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> LookupTableCurve {
+        LookupTableCurve::new(vec![(0.0, 0.0), (0.5, 0.2), (1.0, 1.0)]).unwrap()
+    }
+
+    #[test]
+    fn test_apply_at_control_points() {
+        let curve = curve();
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(0.5), 0.2);
+        assert_eq!(curve.apply(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_apply_between_control_points() {
+        let curve = curve();
+        assert!((curve.apply(0.25) - 0.1).abs() < 1e-6);
+        assert!((curve.apply(0.75) - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let curve = curve();
+        assert_eq!(curve.inverse(0.2), 0.5);
+        assert!((curve.inverse(0.1) - 0.25).abs() < 1e-6);
+        assert!((curve.inverse(0.6) - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rejects_invalid_points() {
+        assert!(LookupTableCurve::new(vec![(0.0, 0.0)]).is_err());
+        assert!(LookupTableCurve::new(vec![(0.0, 0.0), (0.6, 0.5), (0.4, 0.6), (1.0, 1.0)]).is_err());
+        assert!(LookupTableCurve::new(vec![(0.1, 0.0), (1.0, 1.0)]).is_err());
+        assert!(LookupTableCurve::new(vec![(0.0, 0.0), (0.9, 1.0)]).is_err());
+        assert!(LookupTableCurve::new(vec![(0.0, 0.5), (0.5, 0.2), (1.0, 1.0)]).is_err());
+    }
+}
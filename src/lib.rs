@@ -2,8 +2,11 @@ pub mod provider;
 pub mod curves;
 pub mod pipewire;
 pub mod config;
+pub mod cache;
+pub mod cli;
+pub mod metrics;
 
-pub use provider::{LightId, Brightness, LightState, Light, Provider, ProviderRegistry, ProviderError};
-pub use curves::{Curve, CurveConfig, LinearCurve, LogarithmicCurve, GammaCurve, PerceptualCurve};
-pub use pipewire::{DropinConfig, Volume, VolumeController, VolumeMonitor, VolumeEvent};
-pub use config::{Config, PipewireConfig, CurvesConfig, LifxConfig, LightsConfig, LightConfig};
+pub use provider::{LightId, Brightness, Capabilities, Color, LightState, Light, LightFilter, Provider, ProviderRegistry, ProviderError, DiscoveryProgress};
+pub use curves::{Curve, ColorCurve, CurveConfig, CurveError, CurveRegistry, BezierCurve, ColorMap, ColorStop, CompositeCurve, DimToWarmCurve, ExponentialCurve, FloorCeilCurve, LinearCurve, LogarithmicCurve, LutCurve, GammaCurve, PerceptualCurve, SrgbCurve, StevensCurve, LookupTableCurve};
+pub use pipewire::{managed_nodes, DropinConfig, ManagedNode, NodeLightMap, Volume, VolumeController, VolumeMonitor, VolumeEvent, VolumeScale};
+pub use config::{Config, ConfigError, ConfigIssue, PipewireConfig, CurvesConfig, LifxConfig, KasaConfig, MqttConfig, WledConfig, YeelightConfig, EsphomeConfig, GoveeConfig, HttpConfig, HomeAssistantConfig, SimConfig, LightsConfig, LightConfig, MuteAction, InitialSync, ScheduleConfig, ScheduleWindow};
@@ -0,0 +1,73 @@
+//! Counters and gauges for long-running syncs, served over HTTP in the
+//! Prometheus text format when built with the `metrics` feature. Without it
+//! recording is a no-op.
+
+use crate::provider::{Brightness, LightId};
+
+/// Serves `/metrics` on `addr` and starts recording.
+#[cfg(feature = "metrics")]
+pub fn install(addr: std::net::SocketAddr) -> anyhow::Result<()> {
+    metrics_exporter_prometheus::PrometheusBuilder::new().with_http_listener(addr).install()?;
+    tracing::info!("Serving metrics on http://{}/metrics", addr);
+    Ok(())
+}
+
+/// Counts a command sent to a light, as `lightwire_<command>_total` labelled
+/// with the provider and whether it succeeded.
+pub fn command<T, E>(provider: &str, command: &str, result: &Result<T, E>) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(
+        format!("lightwire_{}_total", command),
+        "provider" => provider.to_string(),
+        "result" => if result.is_ok() { "ok" } else { "error" },
+    )
+    .increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (provider, command, result);
+}
+
+/// Records a light's last known brightness, from a command or a read.
+pub fn brightness(provider: &str, id: &LightId, brightness: Brightness) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!(
+        "lightwire_light_brightness",
+        "provider" => provider.to_string(),
+        "light" => id.0.clone(),
+    )
+    .set(brightness.as_f32() as f64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (provider, id, brightness);
+}
+
+/// Counts a volume change seen on a node.
+pub fn volume_event(node_name: &str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("lightwire_volume_events_total", "node" => node_name.to_string()).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = node_name;
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    #[test]
+    fn test_records_commands_and_brightness() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            let id = LightId("lifx:d073d5123456".to_string());
+            command("lifx", "set_brightness", &Ok::<(), ()>(()));
+            command("lifx", "set_brightness", &Err::<(), ()>(()));
+            brightness("lifx", &id, Brightness::new(0.25));
+            volume_event("lightwire.lifx.desk");
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains(r#"lightwire_set_brightness_total{provider="lifx",result="ok"} 1"#), "{}", rendered);
+        assert!(rendered.contains(r#"lightwire_set_brightness_total{provider="lifx",result="error"} 1"#));
+        assert!(rendered.contains(r#"lightwire_light_brightness{provider="lifx",light="lifx:d073d5123456"} 0.25"#));
+        assert!(rendered.contains(r#"lightwire_volume_events_total{node="lightwire.lifx.desk"} 1"#));
+    }
+}
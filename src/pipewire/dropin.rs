@@ -1,13 +1,28 @@
 use crate::provider::LightId;
+use std::collections::HashSet;
 use std::io::Result;
 use std::path::Path;
 
-#[derive(Clone, Debug)]
+#[derive(Debug, thiserror::Error)]
+pub enum DropinParseError {
+    #[error("Missing {0}")]
+    Missing(&'static str),
+    #[error("Malformed node.name: {0}")]
+    NodeName(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct DropinConfig {
     pub provider_name: String,
     pub light_label: String,
     pub light_id: LightId,
     pub node_prefix: String,
+    /// Name of the brightness curve, recorded as the node's `lightwire.curve` property.
+    pub curve: String,
+    /// Overrides the default `"<label> (lightwire)"` node description.
+    pub description: Option<String>,
+    /// Filename- and node-safe form of the label; see `dedupe_slugs`.
+    pub slug: String,
 }
 
 impl DropinConfig {
@@ -16,31 +31,51 @@ impl DropinConfig {
         light_label: String,
         light_id: LightId,
         node_prefix: String,
+        curve: String,
     ) -> Self {
         Self {
+            slug: sanitize_label(&light_label),
             provider_name,
             light_label,
             light_id,
             node_prefix,
+            curve,
+            description: None,
         }
     }
 
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// The name shown by `wpctl status` and pavucontrol.
+    pub fn description(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| format!("{} (lightwire)", self.light_label))
+    }
+
     pub fn filename(&self) -> String {
         format!(
             "{}-{}-{}.conf",
             self.node_prefix,
             self.provider_name.to_lowercase(),
-            sanitize_label(&self.light_label)
+            self.slug
         )
     }
 
-    pub fn generate(&self) -> String {
-        let node_name = format!(
+    pub fn node_name(&self) -> String {
+        format!(
             "{}.{}.{}",
             self.node_prefix,
             self.provider_name.to_lowercase(),
-            sanitize_label(&self.light_label)
-        );
+            self.slug
+        )
+    }
+
+    pub fn generate(&self) -> String {
+        let node_name = self.node_name();
 
         format!(
             r#"# Generated by lightwire - do not edit manually
@@ -53,24 +88,67 @@ context.objects = [
     args = {{
       factory.name = support.null-audio-sink
       node.name = "{}"
-      node.description = "{}: {}"
+      node.description = "{}"
+      node.nick = "{}"
       media.class = Audio/Sink
       object.linger = true
       audio.position = [ FL FR ]
       monitor.channel-volumes = true
+      lightwire.light-id = "{}"
+      lightwire.curve = "{}"
     }}
   }}
-]]
+]
 "#,
-            self.light_label,
-            self.light_id.0,
-            self.provider_name,
+            comment(&self.light_label),
+            comment(&self.light_id.0),
+            comment(&self.provider_name),
             node_name,
-            capitalize_first(&self.provider_name),
-            self.light_label
+            escape(&self.description()),
+            escape(&self.light_label),
+            escape(&self.light_id.0),
+            escape(&self.curve)
         )
     }
 
+    /// Reads back a drop-in written by `generate`.
+    pub fn parse(contents: &str) -> std::result::Result<Self, DropinParseError> {
+        let light_id = property(contents, "lightwire.light-id").ok_or(DropinParseError::Missing("lightwire.light-id"))?;
+        let node_name = property(contents, "node.name").ok_or(DropinParseError::Missing("node.name"))?;
+        let provider_name = header(contents, "Provider").ok_or(DropinParseError::Missing("provider header"))?;
+
+        // Slugs and provider names never contain dots, so anything before them is the prefix.
+        let mut parts = node_name.rsplitn(3, '.');
+        let (Some(slug), Some(provider), Some(node_prefix)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(DropinParseError::NodeName(node_name));
+        };
+        if provider != provider_name.to_lowercase() {
+            return Err(DropinParseError::NodeName(node_name));
+        }
+
+        let light_label = match property(contents, "node.nick") {
+            Some(nick) => nick,
+            None => header(contents, "Light")
+                .and_then(|light| light.strip_suffix(&format!(" ({})", light_id)).map(str::to_string))
+                .ok_or(DropinParseError::Missing("node.nick"))?,
+        };
+
+        let mut dropin = Self::new(
+            provider_name,
+            light_label,
+            LightId(light_id),
+            node_prefix.to_string(),
+            property(contents, "lightwire.curve").ok_or(DropinParseError::Missing("lightwire.curve"))?,
+        );
+        dropin.slug = slug.to_string();
+        if let Some(description) = property(contents, "node.description") {
+            if description != dropin.description() {
+                dropin.description = Some(description);
+            }
+        }
+        Ok(dropin)
+    }
+
     pub fn write_to(&self, config_dir: &Path) -> Result<()> {
         let file_path = config_dir.join(self.filename());
         std::fs::write(file_path, self.generate())?;
@@ -78,22 +156,200 @@ context.objects = [
     }
 }
 
+/// Appends `-2`, `-3`, ... to slugs that would give two lights the same node
+/// name. Lights are numbered in id order so the result doesn't depend on
+/// discovery order.
+pub fn dedupe_slugs(dropins: &mut [DropinConfig]) {
+    let mut order: Vec<usize> = (0..dropins.len()).collect();
+    order.sort_by(|&a, &b| dropins[a].light_id.0.cmp(&dropins[b].light_id.0));
+
+    let mut taken = HashSet::new();
+    for i in order {
+        let base = dropins[i].slug.clone();
+        let mut suffix = 1;
+        while !taken.insert(dropins[i].node_name()) {
+            suffix += 1;
+            dropins[i].slug = format!("{}-{}", base, suffix);
+        }
+    }
+}
+
+/// Lowercase ASCII letters and digits joined by single dashes. Apostrophes are
+/// dropped rather than split on, so "Kid's Room" becomes `kids-room`.
 fn sanitize_label(label: &str) -> String {
-    label
+    let slug = label
         .to_lowercase()
         .chars()
-        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .filter(|c| *c != '\'' && *c != '’')
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
         .collect::<String>()
         .split('-')
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>()
-        .join("-")
+        .join("-");
+    if slug.is_empty() {
+        "light".to_string()
+    } else {
+        slug
+    }
+}
+
+/// The value of a `key = value` line, unquoted and unescaped.
+fn property(contents: &str, key: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(key)?.trim_start().strip_prefix('=')?.trim();
+        Some(match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => unescape(quoted),
+            None => value.to_string(),
+        })
+    })
+}
+
+/// The value of a `# Name: value` comment line.
+fn header(contents: &str, name: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        line.strip_prefix("# ")?.strip_prefix(name)?.strip_prefix(": ").map(str::to_string)
+    })
 }
 
-fn capitalize_first(s: &str) -> String {
-    let mut chars = s.chars();
-    match chars.next() {
-        None => String::new(),
-        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                out.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+            }
+            escaped => out.extend(escaped),
+        }
+    }
+    out
+}
+
+/// Escapes a value for a double-quoted SPA-JSON string. Control characters
+/// are escaped too, so a name from the network can't end the string's line.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A value for a `# Name: value` header, with control characters replaced
+/// so it stays on its comment line.
+fn comment(value: &str) -> String {
+    value.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_includes_curve() {
+        let dropin = DropinConfig::new(
+            "lifx".to_string(),
+            "Desk Lamp".to_string(),
+            LightId("lifx:d073d5123456".to_string()),
+            "lightwire".to_string(),
+            "gamma".to_string(),
+        );
+        let generated = dropin.generate();
+        assert!(generated.contains(r#"node.name = "lightwire.lifx.desk-lamp""#));
+        assert!(generated.contains(r#"lightwire.light-id = "lifx:d073d5123456""#));
+        assert!(generated.contains(r#"lightwire.curve = "gamma""#));
+    }
+
+    #[test]
+    fn test_generate_description_and_nick() {
+        let dropin = DropinConfig::new(
+            "lifx".to_string(),
+            "Desk \"Lamp\"".to_string(),
+            LightId("lifx:d073d5123456".to_string()),
+            "lightwire".to_string(),
+            "gamma".to_string(),
+        );
+        let generated = dropin.generate();
+        assert!(generated.contains(r#"node.description = "Desk \"Lamp\" (lightwire)""#));
+        assert!(generated.contains(r#"node.nick = "Desk \"Lamp\"""#));
+        assert!(generated.contains("media.class = Audio/Sink"));
+        assert!(generated.trim_end().ends_with("\n]"));
+
+        let generated = dropin.with_description("Office ceiling".to_string()).generate();
+        assert!(generated.contains(r#"node.description = "Office ceiling""#));
+    }
+
+    fn dropin(id: &str, label: &str) -> DropinConfig {
+        DropinConfig::new(
+            "lifx".to_string(),
+            label.to_string(),
+            LightId(id.to_string()),
+            "lightwire".to_string(),
+            "perceptual".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_sanitize_label() {
+        assert_eq!(sanitize_label("Kid's Room / Lamp #2"), "kids-room-lamp-2");
+        assert_eq!(sanitize_label("  Café--Bar  "), "caf-bar");
+        assert_eq!(sanitize_label("???"), "light");
+
+        let dropin = dropin("lifx:abc", "Kid's Room / Lamp #2");
+        assert_eq!(dropin.filename(), "lightwire-lifx-kids-room-lamp-2.conf");
+        assert_eq!(dropin.node_name(), "lightwire.lifx.kids-room-lamp-2");
+        assert!(dropin.generate().contains(r#"node.description = "Kid's Room / Lamp #2 (lightwire)""#));
+    }
+
+    #[test]
+    fn test_dedupe_slugs() {
+        let mut dropins = vec![dropin("lifx:b", "Lamp"), dropin("lifx:a", "lamp!"), dropin("lifx:c", "Desk")];
+        dedupe_slugs(&mut dropins);
+        assert_eq!(dropins[0].node_name(), "lightwire.lifx.lamp-2");
+        assert_eq!(dropins[1].node_name(), "lightwire.lifx.lamp");
+        assert_eq!(dropins[2].node_name(), "lightwire.lifx.desk");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let mut dropin = dropin("lifx:d073d5123456", "Kid's \"Lamp\"");
+        dropin.slug = "kids-lamp-2".to_string();
+        assert_eq!(DropinConfig::parse(&dropin.generate()).unwrap(), dropin);
+
+        let dropin = dropin.with_description("Nursery".to_string());
+        assert_eq!(DropinConfig::parse(&dropin.generate()).unwrap(), dropin);
+    }
+
+    #[test]
+    fn test_generate_escapes_control_characters() {
+        let dropin = dropin("lifx:d073d5123456", "Lamp\ncontext.exec = [ { path = \"/bin/sh\" } ]\r\t\u{7}");
+        let generated = dropin.generate();
+        assert!(generated.contains("# Light: Lamp context.exec"));
+        assert!(generated.contains(r#"node.nick = "Lamp\ncontext.exec = [ { path = \"/bin/sh\" } ]\r\t\u0007""#));
+        assert!(!generated.lines().any(|line| line.trim_start().starts_with("context.exec")));
+        assert_eq!(DropinConfig::parse(&generated).unwrap(), dropin);
+    }
+
+    #[test]
+    fn test_parse_rejects_foreign_config() {
+        let result = DropinConfig::parse("context.objects = []\n");
+        assert!(matches!(result, Err(DropinParseError::Missing("lightwire.light-id"))));
     }
 }
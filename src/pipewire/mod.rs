@@ -1,7 +1,11 @@
 pub mod dropin;
 pub mod volume;
 pub mod monitor;
+pub mod nodes;
+pub mod node_map;
 
-pub use dropin::DropinConfig;
-pub use volume::{Volume, VolumeController};
+pub use dropin::{dedupe_slugs, DropinConfig, DropinParseError};
+pub use volume::{Volume, VolumeController, VolumeScale, DB_FLOOR};
 pub use monitor::{VolumeMonitor, VolumeEvent};
+pub use nodes::{managed_nodes, ManagedNode};
+pub use node_map::NodeLightMap;
@@ -1,5 +1,13 @@
+use std::collections::HashMap;
+use std::time::Duration;
 use anyhow::Result;
 use tokio::sync::mpsc;
+use super::volume::{Volume, VolumeController};
+
+/// How often `run` reads each node's volume.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// The longest `run` waits between attempts to reach PipeWire again.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Debug)]
 pub struct VolumeEvent {
@@ -8,9 +16,11 @@ pub struct VolumeEvent {
     pub muted: bool,
 }
 
-#[allow(dead_code)]
 pub struct VolumeMonitor {
     node_names: Vec<String>,
+    /// Set when watching managed nodes, so the names can be looked up
+    /// again after PipeWire restarts.
+    node_prefix: Option<String>,
     event_tx: mpsc::UnboundedSender<VolumeEvent>,
 }
 
@@ -18,12 +28,166 @@ impl VolumeMonitor {
     pub fn new(node_names: Vec<String>) -> (Self, mpsc::UnboundedReceiver<VolumeEvent>) {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         (
-            Self { node_names, event_tx },
+            Self { node_names, node_prefix: None, event_tx },
             event_rx,
         )
     }
 
-    pub async fn run(self) -> Result<()> {
+    /// Watches every running node named under `node_prefix`, i.e. every
+    /// light lightwire has created a node for. If the nodes can't be listed
+    /// yet, `run` keeps looking them up until PipeWire answers.
+    pub async fn for_managed_nodes(node_prefix: &str) -> (Self, mpsc::UnboundedReceiver<VolumeEvent>) {
+        let (mut monitor, events) = Self::new(Vec::new());
+        monitor.node_prefix = Some(node_prefix.to_string());
+        if let Err(e) = monitor.refresh_node_names().await {
+            tracing::warn!("Failed to list PipeWire nodes under {}: {}", node_prefix, e);
+        }
+        (monitor, events)
+    }
+
+    /// Polls every node's volume, sending an event for each node when it is
+    /// first read and whenever its volume or mute changes. A node that can't
+    /// be read is retried on the next poll. Returns once the receiver is dropped.
+    ///
+    /// When no node can be read, PipeWire is taken to have gone away (e.g.
+    /// restarted by `populate --restart`) and polling backs off exponentially,
+    /// up to `MAX_RECONNECT_DELAY`, until a read succeeds again. Managed nodes
+    /// are looked up again before each attempt, which also picks up nodes
+    /// added or renamed while PipeWire was down; having none counts as
+    /// PipeWire being away.
+    pub async fn run(mut self) -> Result<()> {
+        let mut controllers = controllers_for(&self.node_names);
+        let mut last: HashMap<String, (f32, bool)> = HashMap::new();
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut backoff = Backoff::new(POLL_INTERVAL, MAX_RECONNECT_DELAY);
+        let mut attempts = 0;
+
+        loop {
+            if attempts == 0 {
+                interval.tick().await;
+            } else {
+                let delay = backoff.next_delay();
+                tracing::info!("Reconnecting to PipeWire in {:?} (attempt {})", delay, attempts);
+                tokio::time::sleep(delay).await;
+                if let Err(e) = self.refresh_node_names().await {
+                    tracing::debug!("Failed to list managed nodes: {}", e);
+                } else {
+                    controllers = controllers_for(&self.node_names);
+                }
+            }
+            if self.event_tx.is_closed() {
+                return Ok(());
+            }
+
+            let mut read_any = false;
+            for controller in &controllers {
+                let volume = match controller.get_volume().await {
+                    Ok(volume) => volume,
+                    Err(e) => {
+                        tracing::debug!("Failed to read volume of {}: {}", controller.node_name(), e);
+                        continue;
+                    }
+                };
+                read_any = true;
+                let Some(event) = changed(&mut last, controller.node_name(), &volume) else {
+                    continue;
+                };
+                crate::metrics::volume_event(&event.node_name);
+                if self.event_tx.send(event).is_err() {
+                    return Ok(());
+                }
+            }
+
+            let waiting = !read_any && (!controllers.is_empty() || self.node_prefix.is_some());
+            if !waiting {
+                if attempts > 0 {
+                    tracing::info!("Reconnected to PipeWire after {} attempt(s)", attempts);
+                    attempts = 0;
+                    backoff.reset();
+                    interval.reset();
+                }
+            } else {
+                if attempts == 0 && controllers.is_empty() {
+                    tracing::warn!("No nodes to watch yet; waiting for PipeWire");
+                } else if attempts == 0 {
+                    tracing::warn!("None of {} node(s) could be read; waiting for PipeWire", controllers.len());
+                }
+                attempts += 1;
+            }
+        }
+    }
+
+    /// Looks up the managed nodes again; fixed node names are kept as they are.
+    async fn refresh_node_names(&mut self) -> Result<()> {
+        if let Some(prefix) = &self.node_prefix {
+            let nodes = super::managed_nodes(prefix).await?;
+            self.node_names = nodes.into_iter().map(|node| node.name).collect();
+        }
         Ok(())
     }
 }
+
+fn controllers_for(node_names: &[String]) -> Vec<VolumeController> {
+    node_names.iter().cloned().map(VolumeController::new).collect()
+}
+
+/// Delays that double from `base` on each call, capped at `max`.
+struct Backoff {
+    base: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    fn new(base: Duration, max: Duration) -> Self {
+        Self { base, max, next: base }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.next = self.base;
+    }
+}
+
+/// Records the node's volume, returning an event if it differs from the last one seen.
+fn changed(last: &mut HashMap<String, (f32, bool)>, node_name: &str, volume: &Volume) -> Option<VolumeEvent> {
+    let current = (volume.value, volume.muted);
+    if last.insert(node_name.to_string(), current) == Some(current) {
+        return None;
+    }
+    Some(VolumeEvent { node_name: node_name.to_string(), volume: volume.value, muted: volume.muted })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_reports_first_read_and_changes() {
+        let mut last = HashMap::new();
+        let event = changed(&mut last, "lightwire.lifx.desk", &Volume::new(0.4)).unwrap();
+        assert_eq!((event.volume, event.muted), (0.4, false));
+        assert!(changed(&mut last, "lightwire.lifx.desk", &Volume::new(0.4)).is_none());
+
+        let muted = Volume { muted: true, ..Volume::new(0.4) };
+        assert!(changed(&mut last, "lightwire.lifx.desk", &muted).unwrap().muted);
+        assert!(changed(&mut last, "lightwire.lifx.lamp", &Volume::new(0.4)).is_some());
+        assert_eq!(changed(&mut last, "lightwire.lifx.desk", &Volume::new(0.5)).unwrap().volume, 0.5);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_millis(250), Duration::from_secs(1));
+        let delays: Vec<u128> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, [250, 500, 1000, 1000, 1000]);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::provider::LightId;
+use std::collections::HashMap;
+use super::DropinConfig;
+
+/// Routes PipeWire node names back to the lights whose drop-ins created them.
+#[derive(Debug, Clone, Default)]
+pub struct NodeLightMap {
+    lights: HashMap<String, (String, LightId)>,
+}
+
+impl NodeLightMap {
+    pub fn from_dropins(dropins: &[DropinConfig]) -> Self {
+        Self {
+            lights: dropins
+                .iter()
+                .map(|dropin| (dropin.node_name(), (dropin.provider_name.clone(), dropin.light_id.clone())))
+                .collect(),
+        }
+    }
+
+    /// The provider and id of the light behind `node_name`, if it is one of ours.
+    pub fn light_for_node(&self, node_name: &str) -> Option<(&str, &LightId)> {
+        self.lights.get(node_name).map(|(provider, id)| (provider.as_str(), id))
+    }
+
+    /// Every mapped node name, sorted.
+    pub fn node_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.lights.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_for_node() {
+        let dropin = |provider: &str, label: &str, id: &str| {
+            DropinConfig::new(
+                provider.to_string(),
+                label.to_string(),
+                LightId(id.to_string()),
+                "lightwire".to_string(),
+                "perceptual".to_string(),
+            )
+        };
+        let dropins = vec![dropin("lifx", "Desk", "lifx:d073d5000001"), dropin("kasa", "Lamp", "kasa:10.0.0.2")];
+        let map = NodeLightMap::from_dropins(&dropins);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.node_names(), vec!["lightwire.kasa.lamp", "lightwire.lifx.desk"]);
+        let (provider, id) = map.light_for_node("lightwire.lifx.desk").unwrap();
+        assert_eq!((provider, id.0.as_str()), ("lifx", "lifx:d073d5000001"));
+        assert!(map.light_for_node("alsa_output.pci-0000_00_1f.3").is_none());
+    }
+}
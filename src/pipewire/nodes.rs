@@ -0,0 +1,59 @@
+use crate::provider::ProviderError;
+use serde_json::Value;
+use super::volume::run;
+
+/// A PipeWire node created from one of lightwire's drop-ins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManagedNode {
+    pub id: u32,
+    pub name: String,
+}
+
+/// Lists the running nodes named under `node_prefix`, as drop-ins name
+/// them (`<prefix>.<provider>.<slug>`), sorted by name.
+pub async fn managed_nodes(node_prefix: &str) -> Result<Vec<ManagedNode>, ProviderError> {
+    let dump = run("pw-dump", &[]).await?;
+    parse_managed_nodes(&dump, node_prefix)
+}
+
+fn parse_managed_nodes(dump: &str, node_prefix: &str) -> Result<Vec<ManagedNode>, ProviderError> {
+    let objects: Vec<Value> =
+        serde_json::from_str(dump).map_err(|e| ProviderError::Protocol(format!("unreadable pw-dump output: {}", e)))?;
+    let prefix = format!("{}.", node_prefix);
+    let mut nodes: Vec<ManagedNode> = objects
+        .iter()
+        .filter(|object| object["type"] == "PipeWire:Interface:Node")
+        .filter_map(|object| {
+            let name = object["info"]["props"]["node.name"].as_str()?;
+            let id = object["id"].as_u64().and_then(|id| u32::try_from(id).ok())?;
+            name.starts_with(&prefix).then(|| ManagedNode { id, name: name.to_string() })
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PW_DUMP: &str = r#"[
+  { "id": 0, "type": "PipeWire:Interface:Core", "info": { "props": { "core.name": "pipewire-0" } } },
+  { "id": 48, "type": "PipeWire:Interface:Node", "info": { "props": { "node.name": "lightwire.lifx.office-lamp" } } },
+  { "id": 31, "type": "PipeWire:Interface:Node", "info": { "props": { "node.name": "alsa_output.pci-0000_00_1f.3" } } },
+  { "id": 47, "type": "PipeWire:Interface:Node", "info": { "props": { "node.name": "lightwire.kasa.desk" } } },
+  { "id": 50, "type": "PipeWire:Interface:Node", "info": { "props": { "node.name": "lightwirex.kasa.desk" } } },
+  { "id": 51, "type": "PipeWire:Interface:Metadata", "info": { "props": { "node.name": "lightwire.fake" } } }
+]"#;
+
+    #[test]
+    fn test_parse_managed_nodes() {
+        let nodes = parse_managed_nodes(PW_DUMP, "lightwire").unwrap();
+        assert_eq!(nodes, vec![
+            ManagedNode { id: 47, name: "lightwire.kasa.desk".to_string() },
+            ManagedNode { id: 48, name: "lightwire.lifx.office-lamp".to_string() },
+        ]);
+        assert!(parse_managed_nodes(PW_DUMP, "other").unwrap().is_empty());
+        assert!(parse_managed_nodes("not json", "lightwire").is_err());
+    }
+}
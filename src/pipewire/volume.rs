@@ -1,18 +1,42 @@
-use anyhow::Result;
+use crate::provider::ProviderError;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// The quietest level the `db` scale tells apart; anything below reads as 0.
+pub const DB_FLOOR: f32 = -60.0;
 
 #[derive(Clone, Debug)]
 pub struct Volume {
+    /// The loudest channel, which is what a single light follows.
     pub value: f32,
     pub muted: bool,
+    /// Per-channel volumes, when the node reported them; empty otherwise.
+    pub channels: Vec<f32>,
 }
 
 impl Volume {
     pub fn new(value: f32) -> Self {
-        Self { value: value.clamp(0.0, 1.0), muted: false }
+        Self { value: value.clamp(0.0, 1.0), muted: false, channels: Vec::new() }
     }
 
     pub fn muted(value: f32) -> Self {
-        Self { value: value.clamp(0.0, 1.0), muted: true }
+        Self { value: value.clamp(0.0, 1.0), muted: true, channels: Vec::new() }
+    }
+
+    /// A volume from per-channel levels, with `value` the loudest of them
+    /// so an unbalanced node doesn't read quieter than it sounds.
+    pub fn from_channels(channels: Vec<f32>, muted: bool) -> Self {
+        let channels: Vec<f32> = channels.into_iter().map(|c| c.clamp(0.0, 1.0)).collect();
+        let value = channels.iter().copied().fold(0.0, f32::max);
+        Self { value, muted, channels }
+    }
+
+    /// The average channel level, or `value` when there are no channels.
+    pub fn mean(&self) -> f32 {
+        if self.channels.is_empty() {
+            return self.value;
+        }
+        self.channels.iter().sum::<f32>() / self.channels.len() as f32
     }
 
     pub fn is_muted(&self) -> bool {
@@ -22,9 +46,76 @@ impl Volume {
     pub fn as_f32(&self) -> f32 {
         self.value
     }
+
+    /// A linear amplitude from decibels: `10^(db / 20)`, so -6dB is about
+    /// 0.501, -20dB is 0.1 and 0dB is unity. Gains above 0dB clamp to 1.
+    pub fn from_db(db: f32) -> Self {
+        Self::new(10f32.powf(db / 20.0))
+    }
+
+    /// The inverse of `from_db`: `20 * log10(value)`, or negative infinity
+    /// for silence.
+    pub fn to_db(&self) -> f32 {
+        20.0 * self.value.log10()
+    }
+
+    /// A linear volume from the cubic scale of PipeWire's UI sliders and
+    /// `wpctl`: `cubic^3`.
+    pub fn from_cubic(cubic: f32) -> Self {
+        Self::new(cubic.clamp(0.0, 1.0).powi(3))
+    }
+
+    /// The inverse of `from_cubic`: `cbrt(value)`.
+    pub fn to_cubic(&self) -> f32 {
+        self.value.cbrt()
+    }
+}
+
+/// The scale a node's volume is read in before a curve maps it to
+/// brightness, and written back in after the curve's inverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeScale {
+    /// The channel volume PipeWire stores.
+    #[default]
+    Linear,
+    /// The position of PipeWire's volume sliders, `cbrt(linear)`.
+    Cubic,
+    /// Loudness, with `DB_FLOOR`..0dB spread evenly over 0..1:
+    /// `(to_db(linear) - DB_FLOOR) / -DB_FLOOR`.
+    Db,
+}
+
+impl VolumeScale {
+    /// Maps a linear volume to a 0..1 position on this scale.
+    pub fn position(self, linear: f32) -> f32 {
+        let volume = Volume::new(linear);
+        match self {
+            VolumeScale::Linear => volume.as_f32(),
+            VolumeScale::Cubic => volume.to_cubic(),
+            VolumeScale::Db => ((volume.to_db() - DB_FLOOR) / -DB_FLOOR).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Maps a 0..1 position on this scale back to a linear volume.
+    pub fn linear(self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        match self {
+            VolumeScale::Linear => position,
+            VolumeScale::Cubic => Volume::from_cubic(position).as_f32(),
+            VolumeScale::Db if position == 0.0 => 0.0,
+            VolumeScale::Db => Volume::from_db(DB_FLOOR + position * -DB_FLOOR).as_f32(),
+        }
+    }
 }
 
-#[allow(dead_code)]
+/// Reads and writes a node's volume by shelling out to `pw-cli` and `wpctl`.
+///
+/// Channel volumes are read and written through the node's `Props` param so
+/// every channel is seen. Nodes without `channelVolumes` fall back to `wpctl`,
+/// which speaks in the cubic scale PipeWire uses for its UI sliders, while
+/// `Volume` holds the linear channel volume, so values are converted at the
+/// boundary.
 pub struct VolumeController {
     node_name: String,
 }
@@ -34,15 +125,260 @@ impl VolumeController {
         Self { node_name }
     }
 
-    pub async fn get_volume(&self) -> Result<Volume> {
-        Ok(Volume::new(1.0))
+    pub fn node_name(&self) -> &str {
+        &self.node_name
+    }
+
+    pub async fn get_volume(&self) -> Result<Volume, ProviderError> {
+        let id = self.resolve_node_id().await?.to_string();
+        self.get_volume_of(&id).await
+    }
+
+    async fn get_volume_of(&self, id: &str) -> Result<Volume, ProviderError> {
+        let props = run("pw-cli", &["enum-params", id, "Props"]).await?;
+        if let Some(volume) = parse_props_volume(&props) {
+            return Ok(volume);
+        }
+        let output = run("wpctl", &["get-volume", id]).await?;
+        parse_wpctl_volume(&output).ok_or_else(|| {
+            ProviderError::PipeWireConnection(format!("unexpected wpctl output: {}", output.trim()))
+        })
+    }
+
+    /// Sets the loudest channel to `volume`, scaling the others to keep
+    /// the node's balance.
+    pub async fn set_volume(&self, volume: f32) -> Result<(), ProviderError> {
+        let id = self.resolve_node_id().await?.to_string();
+        let current = self.get_volume_of(&id).await?;
+        if current.channels.is_empty() {
+            let cubic = format!("{:.4}", Volume::new(volume).to_cubic());
+            run("wpctl", &["set-volume", &id, &cubic]).await?;
+            return Ok(());
+        }
+        self.set_channels_of(&id, &rebalance(&current, volume)).await
+    }
+
+    /// Sets each channel's linear volume.
+    pub async fn set_channels(&self, channels: &[f32]) -> Result<(), ProviderError> {
+        let id = self.resolve_node_id().await?.to_string();
+        self.set_channels_of(&id, channels).await
     }
 
-    pub async fn set_volume(&self, _volume: f32) -> Result<()> {
+    async fn set_channels_of(&self, id: &str, channels: &[f32]) -> Result<(), ProviderError> {
+        run("pw-cli", &["set-param", id, "Props", &channel_volumes_pod(channels)]).await?;
         Ok(())
     }
 
-    pub async fn set_muted(&self, _muted: bool) -> Result<()> {
+    pub async fn set_muted(&self, muted: bool) -> Result<(), ProviderError> {
+        let id = self.resolve_node_id().await?.to_string();
+        run("wpctl", &["set-mute", &id, if muted { "1" } else { "0" }]).await?;
         Ok(())
     }
+
+    /// Node ids change whenever PipeWire restarts, so look it up on every call.
+    async fn resolve_node_id(&self) -> Result<u32, ProviderError> {
+        let listing = run("pw-cli", &["ls", "Node"]).await?;
+        parse_node_id(&listing, &self.node_name)
+            .ok_or_else(|| ProviderError::NodeNotFound(self.node_name.clone()))
+    }
+}
+
+pub(super) async fn run(program: &str, args: &[&str]) -> Result<String, ProviderError> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| ProviderError::PipeWireConnection(format!("failed to run {}: {}", program, e)))?;
+
+    if !output.status.success() {
+        return Err(ProviderError::PipeWireConnection(format!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Finds the id of the node called `node_name` in `pw-cli ls Node` output.
+fn parse_node_id(listing: &str, node_name: &str) -> Option<u32> {
+    let mut current_id = None;
+    for line in listing.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("id ") {
+            current_id = rest.split(',').next().and_then(|id| id.trim().parse().ok());
+        } else if let Some(value) = line.strip_prefix("node.name = ") {
+            if value.trim_matches('"') == node_name {
+                return current_id;
+            }
+        }
+    }
+    None
+}
+
+/// The channels of `current` scaled so the loudest becomes `volume`. A
+/// silent node has no balance to keep, so every channel gets `volume`.
+fn rebalance(current: &Volume, volume: f32) -> Vec<f32> {
+    let volume = volume.clamp(0.0, 1.0);
+    if current.value <= 0.0 {
+        return vec![volume; current.channels.len()];
+    }
+    current.channels.iter().map(|channel| channel / current.value * volume).collect()
+}
+
+/// The `Props` pod `pw-cli set-param` takes to set every channel.
+fn channel_volumes_pod(channels: &[f32]) -> String {
+    let volumes: Vec<String> = channels.iter().map(|c| format!("{:.6}", c.clamp(0.0, 1.0))).collect();
+    format!("{{ channelVolumes: [ {} ] }}", volumes.join(", "))
+}
+
+/// Parses the `channelVolumes` and `mute` props out of
+/// `pw-cli enum-params <id> Props` output. `None` if the node has no
+/// channel volumes.
+fn parse_props_volume(output: &str) -> Option<Volume> {
+    let mut lines = output.lines().map(str::trim).peekable();
+    let mut channels = None;
+    let mut muted = None;
+    while let Some(line) = lines.next() {
+        if !line.starts_with("Prop: key ") {
+            continue;
+        }
+        if line.contains(":Props:channelVolumes ") && channels.is_none() {
+            lines.next_if(|l| l.starts_with("Array:"));
+            let mut values = Vec::new();
+            while let Some(value) = lines.next_if(|l| l.starts_with("Float ")) {
+                values.push(value.strip_prefix("Float ")?.parse::<f32>().ok()?);
+            }
+            channels = Some(values);
+        } else if line.contains(":Props:mute ") && muted.is_none() {
+            muted = lines.next().and_then(|l| l.strip_prefix("Bool ")).map(|b| b == "true");
+        }
+    }
+    channels
+        .filter(|channels| !channels.is_empty())
+        .map(|channels| Volume::from_channels(channels, muted.unwrap_or(false)))
+}
+
+/// Parses `wpctl get-volume` output such as `Volume: 0.40 [MUTED]`.
+fn parse_wpctl_volume(output: &str) -> Option<Volume> {
+    let rest = output.trim().strip_prefix("Volume:")?;
+    let mut parts = rest.split_whitespace();
+    let cubic: f32 = parts.next()?.parse().ok()?;
+    let volume = Volume::from_cubic(cubic);
+    if parts.any(|p| p == "[MUTED]") {
+        Some(Volume::muted(volume.value))
+    } else {
+        Some(volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PW_CLI_LS: &str = r#"	id 31, type PipeWire:Interface:Node/3
+ 		object.serial = "31"
+ 		factory.id = "19"
+ 		node.name = "alsa_output.pci-0000_00_1f.3.analog-stereo"
+ 		media.class = "Audio/Sink"
+	id 58, type PipeWire:Interface:Node/3
+ 		object.serial = "112"
+ 		node.name = "lightwire.lifx.desk"
+ 		media.class = "Audio/Sink"
+"#;
+
+    #[test]
+    fn test_parse_node_id() {
+        assert_eq!(parse_node_id(PW_CLI_LS, "lightwire.lifx.desk"), Some(58));
+        assert_eq!(parse_node_id(PW_CLI_LS, "alsa_output.pci-0000_00_1f.3.analog-stereo"), Some(31));
+        assert_eq!(parse_node_id(PW_CLI_LS, "lightwire.lifx.missing"), None);
+    }
+
+    #[test]
+    fn test_parse_wpctl_volume() {
+        let volume = parse_wpctl_volume("Volume: 0.50\n").unwrap();
+        assert!((volume.as_f32() - 0.125).abs() < 1e-6);
+        assert!(!volume.is_muted());
+
+        let volume = parse_wpctl_volume("Volume: 1.00 [MUTED]\n").unwrap();
+        assert_eq!(volume.as_f32(), 1.0);
+        assert!(volume.is_muted());
+
+        assert!(parse_wpctl_volume("garbage").is_none());
+    }
+
+    #[test]
+    fn test_volume_db() {
+        assert!((Volume::from_db(-6.0).as_f32() - 0.501_187).abs() < 1e-5);
+        assert!((Volume::from_db(-20.0).as_f32() - 0.1).abs() < 1e-6);
+        assert_eq!(Volume::from_db(0.0).as_f32(), 1.0);
+        assert_eq!(Volume::from_db(6.0).as_f32(), 1.0);
+
+        assert!((Volume::new(0.5).to_db() - -6.0206).abs() < 1e-3);
+        assert!((Volume::new(0.1).to_db() - -20.0).abs() < 1e-4);
+        assert_eq!(Volume::new(1.0).to_db(), 0.0);
+        assert_eq!(Volume::new(0.0).to_db(), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_volume_cubic() {
+        assert_eq!(Volume::from_cubic(0.5).as_f32(), 0.125);
+        assert!((Volume::new(0.125).to_cubic() - 0.5).abs() < 1e-6);
+        assert_eq!(Volume::from_cubic(1.0).as_f32(), 1.0);
+        assert_eq!(Volume::new(1.0).to_cubic(), 1.0);
+    }
+
+    #[test]
+    fn test_volume_scale_position() {
+        assert_eq!(VolumeScale::Linear.position(0.25), 0.25);
+        assert!((VolumeScale::Cubic.position(0.125) - 0.5).abs() < 1e-6);
+        assert!((VolumeScale::Db.position(0.1) - 2.0 / 3.0).abs() < 1e-5);
+        assert!((VolumeScale::Db.position(Volume::from_db(-6.0).as_f32()) - 0.9).abs() < 1e-5);
+        assert_eq!(VolumeScale::Db.position(1.0), 1.0);
+        assert_eq!(VolumeScale::Db.position(0.0), 0.0);
+        assert_eq!(VolumeScale::Db.position(0.0001), 0.0);
+
+        for scale in [VolumeScale::Linear, VolumeScale::Cubic, VolumeScale::Db] {
+            assert_eq!(scale.linear(0.0), 0.0);
+            assert!((scale.linear(1.0) - 1.0).abs() < 1e-6);
+            for linear in [0.01, 0.1, 0.5, 0.9] {
+                assert!((scale.linear(scale.position(linear)) - linear).abs() < 1e-5, "{:?} {}", scale, linear);
+            }
+        }
+    }
+
+    const PW_CLI_PROPS: &str = r#"  Object: size 1392, type Spa:Pod:Object:Param:Props (262146), id Spa:Enum:ParamId:Props (2)
+    Prop: key Spa:Pod:Object:Param:Props:volume (65539), flags 00000000
+      Float 1.000000
+    Prop: key Spa:Pod:Object:Param:Props:mute (65540), flags 00000000
+      Bool true
+    Prop: key Spa:Pod:Object:Param:Props:channelVolumes (65544), flags 00000000
+      Array: child.size 4, child.type Spa:Float
+        Float 0.125000
+        Float 0.500000
+    Prop: key Spa:Pod:Object:Param:Props:monitorVolumes (65547), flags 00000000
+      Array: child.size 4, child.type Spa:Float
+        Float 1.000000
+        Float 1.000000
+"#;
+
+    #[test]
+    fn test_parse_props_volume() {
+        let volume = parse_props_volume(PW_CLI_PROPS).unwrap();
+        assert_eq!(volume.channels, vec![0.125, 0.5]);
+        assert_eq!(volume.as_f32(), 0.5);
+        assert!((volume.mean() - 0.3125).abs() < 1e-6);
+        assert!(volume.is_muted());
+
+        assert!(parse_props_volume("  Object: size 0, type Spa:Pod:Object:Param:Props (262146)\n").is_none());
+    }
+
+    #[test]
+    fn test_rebalance_keeps_balance() {
+        let volume = Volume::from_channels(vec![0.25, 0.5], false);
+        assert_eq!(rebalance(&volume, 0.8), vec![0.4, 0.8]);
+        assert_eq!(rebalance(&Volume::from_channels(vec![0.0, 0.0], false), 0.3), vec![0.3, 0.3]);
+        assert_eq!(channel_volumes_pod(&[0.4, 0.8]), "{ channelVolumes: [ 0.400000, 0.800000 ] }");
+    }
 }
@@ -12,6 +12,8 @@ pub enum ProviderError {
     Timeout(String),
     #[error("Provider not configured: {0}")]
     NotConfigured(String),
+    #[error("Provider already registered: {0}")]
+    AlreadyRegistered(String),
     #[error("Discovery failed: {0}")]
     DiscoveryFailed(String),
     #[error("Set brightness failed: {0}")]
@@ -21,3 +23,15 @@ pub enum ProviderError {
     #[error("PipeWire node not found: {0}")]
     NodeNotFound(String),
 }
+
+/// Maps a failed HTTP request onto the closest `ProviderError`.
+#[cfg(any(feature = "wled", feature = "homeassistant"))]
+pub(super) fn http_error(e: reqwest::Error) -> ProviderError {
+    if e.is_timeout() {
+        ProviderError::Timeout(e.to_string())
+    } else if e.is_connect() || e.is_request() {
+        ProviderError::Network(std::io::Error::other(e.to_string()))
+    } else {
+        ProviderError::Protocol(e.to_string())
+    }
+}
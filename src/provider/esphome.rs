@@ -0,0 +1,719 @@
+use super::types::{Capabilities, Color, Light, LightState, LightId, Brightness, Provider};
+use super::error::ProviderError;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Native API version this client speaks.
+const API_VERSION: (u32, u32) = (1, 9);
+
+const HELLO_REQUEST: u32 = 1;
+const HELLO_RESPONSE: u32 = 2;
+const CONNECT_REQUEST: u32 = 3;
+const CONNECT_RESPONSE: u32 = 4;
+const DISCONNECT_REQUEST: u32 = 5;
+const DISCONNECT_RESPONSE: u32 = 6;
+const PING_REQUEST: u32 = 7;
+const PING_RESPONSE: u32 = 8;
+const LIST_ENTITIES_REQUEST: u32 = 11;
+const LIST_ENTITIES_LIGHT_RESPONSE: u32 = 15;
+const LIST_ENTITIES_DONE_RESPONSE: u32 = 19;
+const SUBSCRIBE_STATES_REQUEST: u32 = 20;
+const LIGHT_STATE_RESPONSE: u32 = 24;
+const LIGHT_COMMAND_REQUEST: u32 = 32;
+
+#[derive(Debug)]
+pub struct EsphomeLight {
+    state: LightState,
+}
+
+impl EsphomeLight {
+    pub fn new(id: LightId, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            state: LightState::new(id, label, brightness, power),
+        }
+    }
+}
+
+impl Light for EsphomeLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "esphome"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+/// Ids combine the ESPHome node name with the light's object id.
+pub fn light_id_for_entity(node: &str, object_id: &str) -> LightId {
+    LightId(format!("esphome:{}.{}", node, object_id))
+}
+
+/// Just enough protobuf to build and read the native API's messages.
+#[derive(Debug, Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn tag(&mut self, field: u32, wire_type: u8) {
+        put_varint(&mut self.0, u64::from(field << 3 | u32::from(wire_type)));
+    }
+
+    fn string(mut self, field: u32, value: &str) -> Self {
+        self.tag(field, 2);
+        put_varint(&mut self.0, value.len() as u64);
+        self.0.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    fn uint32(mut self, field: u32, value: u32) -> Self {
+        self.tag(field, 0);
+        put_varint(&mut self.0, u64::from(value));
+        self
+    }
+
+    fn bool(self, field: u32, value: bool) -> Self {
+        self.uint32(field, u32::from(value))
+    }
+
+    fn fixed32(mut self, field: u32, value: u32) -> Self {
+        self.tag(field, 5);
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn float(self, field: u32, value: f32) -> Self {
+        self.fixed32(field, value.to_bits())
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn take_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field<'a> {
+    Varint(u64),
+    Fixed32(u32),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+}
+
+impl Field<'_> {
+    fn as_u32(&self) -> u32 {
+        match *self {
+            Field::Varint(v) => v as u32,
+            Field::Fixed32(v) => v,
+            _ => 0,
+        }
+    }
+
+    fn as_f32(&self) -> f32 {
+        match *self {
+            Field::Fixed32(v) => f32::from_bits(v),
+            _ => 0.0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Field::Bytes(bytes) => std::str::from_utf8(bytes).unwrap_or(""),
+            _ => "",
+        }
+    }
+}
+
+/// Decodes a message into `(field number, value)` pairs.
+fn decode(mut buf: &[u8]) -> Result<Vec<(u32, Field<'_>)>, ProviderError> {
+    let malformed = || ProviderError::Protocol("malformed ESPHome message".to_string());
+    let mut fields = Vec::new();
+    while !buf.is_empty() {
+        let tag = take_varint(&mut buf).ok_or_else(malformed)?;
+        let field = match tag & 7 {
+            0 => Field::Varint(take_varint(&mut buf).ok_or_else(malformed)?),
+            1 => {
+                let (bytes, rest) = buf.split_first_chunk::<8>().ok_or_else(malformed)?;
+                buf = rest;
+                Field::Fixed64(u64::from_le_bytes(*bytes))
+            }
+            2 => {
+                let len = take_varint(&mut buf).ok_or_else(malformed)? as usize;
+                if len > buf.len() {
+                    return Err(malformed());
+                }
+                let (bytes, rest) = buf.split_at(len);
+                buf = rest;
+                Field::Bytes(bytes)
+            }
+            5 => {
+                let (bytes, rest) = buf.split_first_chunk::<4>().ok_or_else(malformed)?;
+                buf = rest;
+                Field::Fixed32(u32::from_le_bytes(*bytes))
+            }
+            _ => return Err(malformed()),
+        };
+        fields.push(((tag >> 3) as u32, field));
+    }
+    Ok(fields)
+}
+
+/// Frames a message as `0x00, varint length, varint type, payload`.
+fn frame(message_type: u32, message: Message) -> Vec<u8> {
+    let mut buf = vec![0];
+    put_varint(&mut buf, message.0.len() as u64);
+    put_varint(&mut buf, u64::from(message_type));
+    buf.extend_from_slice(&message.0);
+    buf
+}
+
+async fn read_varint(reader: &mut (impl AsyncRead + Unpin)) -> Result<u64, ProviderError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = reader.read_u8().await?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(ProviderError::Protocol("oversized varint from ESPHome device".to_string()))
+}
+
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> Result<(u32, Vec<u8>), ProviderError> {
+    match reader.read_u8().await? {
+        0 => {}
+        1 => {
+            return Err(ProviderError::NotConfigured(
+                "device requires API encryption, which is not supported".to_string(),
+            ))
+        }
+        other => return Err(ProviderError::Protocol(format!("unexpected ESPHome preamble {:#04x}", other))),
+    }
+    let len = read_varint(reader).await? as usize;
+    let message_type = read_varint(reader).await? as u32;
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).await?;
+    Ok((message_type, payload))
+}
+
+#[derive(Debug, Clone)]
+struct Entity {
+    key: u32,
+    object_id: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EntityState {
+    on: bool,
+    brightness: f32,
+}
+
+fn parse_entity(payload: &[u8]) -> Result<Entity, ProviderError> {
+    let mut entity = Entity { key: 0, object_id: String::new(), name: String::new() };
+    for (field, value) in decode(payload)? {
+        match field {
+            1 => entity.object_id = value.as_str().to_string(),
+            2 => entity.key = value.as_u32(),
+            3 => entity.name = value.as_str().to_string(),
+            _ => {}
+        }
+    }
+    Ok(entity)
+}
+
+fn parse_light_state(payload: &[u8]) -> Result<(u32, EntityState), ProviderError> {
+    let mut key = 0;
+    let mut state = EntityState { on: false, brightness: 0.0 };
+    for (field, value) in decode(payload)? {
+        match field {
+            1 => key = value.as_u32(),
+            2 => state.on = value.as_u32() != 0,
+            3 => state.brightness = value.as_f32(),
+            _ => {}
+        }
+    }
+    Ok((key, state))
+}
+
+/// Light states pushed by the device after `SubscribeStatesRequest`.
+#[derive(Debug, Default)]
+struct SharedState {
+    states: std::sync::Mutex<HashMap<u32, EntityState>>,
+    changed: Notify,
+    alive: AtomicBool,
+}
+
+/// A connected, subscribed device. The reader task answers pings and records
+/// state updates until the connection drops.
+#[derive(Debug)]
+struct Session {
+    node: String,
+    entities: Vec<Entity>,
+    writer: Arc<Mutex<OwnedWriteHalf>>,
+    shared: Arc<SharedState>,
+    reader: JoinHandle<()>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl Session {
+    async fn connect(host: &str, port: u16, password: &str, timeout: Duration) -> Result<Self, ProviderError> {
+        let addr = format!("{}:{}", host, port);
+        tokio::time::timeout(timeout, Self::handshake(&addr, password))
+            .await
+            .map_err(|_| ProviderError::Timeout(format!("ESPHome device {} did not finish connecting", addr)))?
+    }
+
+    async fn handshake(addr: &str, password: &str) -> Result<Self, ProviderError> {
+        let (reader, mut writer) = TcpStream::connect(addr).await?.into_split();
+        let mut reader = BufReader::new(reader);
+
+        let hello = Message::default()
+            .string(1, "lightwire")
+            .uint32(2, API_VERSION.0)
+            .uint32(3, API_VERSION.1);
+        writer.write_all(&frame(HELLO_REQUEST, hello)).await?;
+        let payload = expect(&mut reader, &mut writer, HELLO_RESPONSE).await?;
+        let node = decode(&payload)?
+            .into_iter()
+            .find(|(field, _)| *field == 4)
+            .map(|(_, value)| value.as_str().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| addr.split(':').next().unwrap_or(addr).to_string());
+
+        writer.write_all(&frame(CONNECT_REQUEST, Message::default().string(1, password))).await?;
+        let payload = expect(&mut reader, &mut writer, CONNECT_RESPONSE).await?;
+        if decode(&payload)?.iter().any(|(field, value)| *field == 1 && value.as_u32() != 0) {
+            return Err(ProviderError::NotConfigured(format!("ESPHome device {} rejected the password", node)));
+        }
+
+        writer.write_all(&frame(LIST_ENTITIES_REQUEST, Message::default())).await?;
+        let mut entities = Vec::new();
+        loop {
+            match read_frame(&mut reader).await? {
+                (LIST_ENTITIES_LIGHT_RESPONSE, payload) => entities.push(parse_entity(&payload)?),
+                (LIST_ENTITIES_DONE_RESPONSE, _) => break,
+                (PING_REQUEST, _) => writer.write_all(&frame(PING_RESPONSE, Message::default())).await?,
+                _ => {}
+            }
+        }
+
+        writer.write_all(&frame(SUBSCRIBE_STATES_REQUEST, Message::default())).await?;
+
+        let writer = Arc::new(Mutex::new(writer));
+        let shared = Arc::new(SharedState::default());
+        shared.alive.store(true, Ordering::SeqCst);
+        let reader = tokio::spawn(Self::read_loop(reader, writer.clone(), shared.clone(), node.clone()));
+        tracing::debug!("Connected to ESPHome device {} with {} light(s)", node, entities.len());
+        Ok(Self { node, entities, writer, shared, reader })
+    }
+
+    async fn read_loop(
+        mut reader: BufReader<OwnedReadHalf>,
+        writer: Arc<Mutex<OwnedWriteHalf>>,
+        shared: Arc<SharedState>,
+        node: String,
+    ) {
+        let result: Result<(), ProviderError> = async {
+            loop {
+                match read_frame(&mut reader).await? {
+                    (LIGHT_STATE_RESPONSE, payload) => {
+                        let (key, state) = parse_light_state(&payload)?;
+                        shared.states.lock().expect("ESPHome state table poisoned").insert(key, state);
+                        shared.changed.notify_waiters();
+                    }
+                    (PING_REQUEST, _) => writer.lock().await.write_all(&frame(PING_RESPONSE, Message::default())).await?,
+                    (DISCONNECT_REQUEST, _) => {
+                        let _ = writer.lock().await.write_all(&frame(DISCONNECT_RESPONSE, Message::default())).await;
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::debug!("ESPHome device {} disconnected: {}", node, e);
+        }
+        shared.alive.store(false, Ordering::SeqCst);
+        shared.changed.notify_waiters();
+    }
+
+    fn is_alive(&self) -> bool {
+        self.shared.alive.load(Ordering::SeqCst)
+    }
+
+    fn entity(&self, key: u32) -> Option<&Entity> {
+        self.entities.iter().find(|entity| entity.key == key)
+    }
+
+    /// The entity's latest state, waiting for the first one after subscribing.
+    async fn state(&self, key: u32, timeout: Duration) -> Result<EntityState, ProviderError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let changed = self.shared.changed.notified();
+            if let Some(state) = self.shared.states.lock().expect("ESPHome state table poisoned").get(&key) {
+                return Ok(*state);
+            }
+            if !self.is_alive() {
+                return Err(ProviderError::Network(std::io::ErrorKind::ConnectionReset.into()));
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                return Err(ProviderError::Timeout(format!("ESPHome device {} sent no state", self.node)));
+            }
+        }
+    }
+
+    async fn send(&self, message_type: u32, message: Message) -> Result<(), ProviderError> {
+        self.writer.lock().await.write_all(&frame(message_type, message)).await?;
+        Ok(())
+    }
+}
+
+async fn expect(
+    reader: &mut BufReader<OwnedReadHalf>,
+    writer: &mut OwnedWriteHalf,
+    message_type: u32,
+) -> Result<Vec<u8>, ProviderError> {
+    loop {
+        match read_frame(reader).await? {
+            (received, payload) if received == message_type => return Ok(payload),
+            (PING_REQUEST, _) => writer.write_all(&frame(PING_RESPONSE, Message::default())).await?,
+            (DISCONNECT_REQUEST, _) => {
+                return Err(ProviderError::Protocol("ESPHome device closed the connection".to_string()))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A configured device and its current session, if connected.
+#[derive(Debug)]
+pub struct EsphomeDevice {
+    pub host: String,
+    pub port: u16,
+    pub password: String,
+    session: Mutex<Option<Arc<Session>>>,
+}
+
+impl EsphomeDevice {
+    pub fn new(host: String, port: u16, password: Option<String>) -> Self {
+        Self { host, port, password: password.unwrap_or_default(), session: Mutex::new(None) }
+    }
+
+    /// The live session, reconnecting if the last one dropped.
+    async fn session(&self, timeout: Duration) -> Result<Arc<Session>, ProviderError> {
+        let mut session = self.session.lock().await;
+        if let Some(existing) = session.as_ref().filter(|s| s.is_alive()) {
+            return Ok(existing.clone());
+        }
+        let connected = Arc::new(Session::connect(&self.host, self.port, &self.password, timeout).await?);
+        *session = Some(connected.clone());
+        Ok(connected)
+    }
+
+    async fn reset(&self) {
+        *self.session.lock().await = None;
+    }
+}
+
+/// ESPHome devices over the plaintext native API (port 6053). Devices that
+/// require an API encryption key are reported as unsupported.
+#[derive(Debug)]
+pub struct EsphomeProvider {
+    devices: Vec<EsphomeDevice>,
+    timeout: Duration,
+    /// Which device and entity key each discovered light lives on.
+    lights: RwLock<HashMap<LightId, (usize, u32)>>,
+}
+
+impl EsphomeProvider {
+    pub fn new(devices: Vec<EsphomeDevice>, timeout_ms: u64) -> Self {
+        Self {
+            devices,
+            timeout: Duration::from_millis(timeout_ms),
+            lights: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn known_light(&self, id: &LightId) -> Option<(usize, u32)> {
+        self.lights.read().expect("ESPHome light table poisoned").get(id).copied()
+    }
+
+    async fn light_for(&self, id: &LightId) -> Result<(&EsphomeDevice, u32), ProviderError> {
+        if self.known_light(id).is_none() {
+            if let Err(e) = self.discover().await {
+                tracing::debug!("ESPHome rediscovery for {} failed: {}", id.0, e);
+            }
+        }
+        let (index, key) = self.known_light(id).ok_or_else(|| ProviderError::NotFound(id.clone()))?;
+        Ok((&self.devices[index], key))
+    }
+
+    /// Sends a `LightCommandRequest`, reconnecting once if the session has gone stale.
+    async fn command(&self, id: &LightId, build: impl Fn(Message) -> Message) -> Result<(), ProviderError> {
+        let (device, key) = self.light_for(id).await?;
+        let mut retried = false;
+        loop {
+            let session = device.session(self.timeout).await?;
+            match session.send(LIGHT_COMMAND_REQUEST, build(Message::default().fixed32(1, key))).await {
+                Err(ProviderError::Network(e)) if !retried => {
+                    tracing::debug!("Reconnecting to ESPHome device {}: {}", device.host, e);
+                    device.reset().await;
+                    retried = true;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for EsphomeProvider {
+    fn name(&self) -> &'static str {
+        "esphome"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: false }
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        if self.devices.is_empty() {
+            return Err(ProviderError::NotConfigured("no ESPHome devices configured".to_string()));
+        }
+
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        let mut table = HashMap::new();
+        for (index, device) in self.devices.iter().enumerate() {
+            let session = match device.session(self.timeout).await {
+                Ok(session) => session,
+                Err(e) => {
+                    tracing::warn!("ESPHome device {} did not answer: {}", device.host, e);
+                    continue;
+                }
+            };
+            for entity in &session.entities {
+                let id = light_id_for_entity(&session.node, &entity.object_id);
+                let state = session.state(entity.key, self.timeout).await.ok();
+                let label = if entity.name.is_empty() { entity.object_id.clone() } else { entity.name.clone() };
+                table.insert(id.clone(), (index, entity.key));
+                lights.push(Box::new(EsphomeLight::new(
+                    id,
+                    label,
+                    Brightness::new(state.map_or(0.0, |s| s.brightness)),
+                    state.is_some_and(|s| s.on),
+                )));
+            }
+        }
+
+        if lights.is_empty() {
+            return Err(ProviderError::Timeout(format!(
+                "no lights found on {} ESPHome device(s)",
+                self.devices.len()
+            )));
+        }
+
+        self.lights.write().expect("ESPHome light table poisoned").extend(table);
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let (device, key) = self.light_for(id).await?;
+        let session = device.session(self.timeout).await?;
+        let state = session.state(key, self.timeout).await?;
+        let label = session
+            .entity(key)
+            .map(|entity| if entity.name.is_empty() { entity.object_id.clone() } else { entity.name.clone() })
+            .unwrap_or_else(|| id.0.clone());
+        Ok(LightState::new(id.clone(), label, Brightness::new(state.brightness), state.on))
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let level = brightness.as_f32();
+        self.command(id, |message| {
+            let message = message.bool(2, true).bool(3, level > 0.0);
+            if level > 0.0 {
+                message.bool(4, true).float(5, level)
+            } else {
+                message
+            }
+        })
+        .await
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        self.command(id, |message| message.bool(2, true).bool(3, on)).await
+    }
+
+    /// ESPHome takes color temperature in mireds.
+    async fn set_kelvin(&self, id: &LightId, kelvin: u16) -> Result<(), ProviderError> {
+        let mireds = 1_000_000.0 / f32::from(kelvin.max(1));
+        self.command(id, |message| message.bool(12, true).float(13, mireds)).await
+    }
+
+    /// RGB at full value, with the value sent as the light's brightness.
+    async fn set_color(&self, id: &LightId, color: Color) -> Result<(), ProviderError> {
+        let (r, g, b) = Color::new(color.hue, color.saturation, 100).to_rgb();
+        let level = color.brightness().as_f32();
+        self.command(id, |message| {
+            message
+                .bool(2, true)
+                .bool(3, level > 0.0)
+                .bool(4, true)
+                .float(5, level)
+                .bool(6, true)
+                .float(7, r as f32 / 255.0)
+                .float(8, g as f32 / 255.0)
+                .float(9, b as f32 / 255.0)
+        })
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        if self.devices.is_empty() {
+            return Err(ProviderError::NotConfigured("no ESPHome devices configured".to_string()));
+        }
+        for device in &self.devices {
+            device.session(self.timeout).await?;
+        }
+        Ok(())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "esphome",
+        enabled: |config| config.esphome.enabled,
+        build: |config| {
+            let devices = config
+                .esphome
+                .devices
+                .iter()
+                .map(|device| EsphomeDevice::new(device.host.clone(), device.port, device.password.clone()))
+                .collect();
+            Ok(Box::new(EsphomeProvider::new(devices, config.esphome.timeout_ms)))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_message_round_trip() {
+        let message = Message::default().string(1, "desk").fixed32(2, 0xdeadbeef).bool(3, true).float(5, 0.5).uint32(300, 1);
+        let fields = decode(&message.0).unwrap();
+        assert_eq!(fields[0], (1, Field::Bytes(b"desk")));
+        assert_eq!(fields[1].1.as_u32(), 0xdeadbeef);
+        assert_eq!(fields[2], (3, Field::Varint(1)));
+        assert_eq!(fields[3].1.as_f32(), 0.5);
+        assert_eq!(fields[4].0, 300);
+        assert!(decode(&[0x0a, 0x05, b'x']).is_err());
+    }
+
+    #[test]
+    fn test_frame() {
+        assert_eq!(frame(PING_REQUEST, Message::default()), vec![0, 0, 7]);
+        let framed = frame(CONNECT_REQUEST, Message::default().string(1, "pw"));
+        assert_eq!(framed, vec![0, 4, 3, 0x0a, 2, b'p', b'w']);
+    }
+
+    /// A fake device with one light, `office`, that records commands it receives.
+    async fn fake_device(listener: TcpListener, commands: tokio::sync::mpsc::UnboundedSender<Vec<u8>>) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        loop {
+            let Ok((message_type, payload)) = read_frame(&mut reader).await else {
+                return;
+            };
+            let reply = match message_type {
+                HELLO_REQUEST => vec![frame(HELLO_RESPONSE, Message::default().uint32(1, 1).string(4, "den"))],
+                CONNECT_REQUEST => vec![frame(CONNECT_RESPONSE, Message::default())],
+                LIST_ENTITIES_REQUEST => vec![
+                    frame(
+                        LIST_ENTITIES_LIGHT_RESPONSE,
+                        Message::default().string(1, "office").fixed32(2, 42).string(3, "Office"),
+                    ),
+                    frame(LIST_ENTITIES_DONE_RESPONSE, Message::default()),
+                ],
+                SUBSCRIBE_STATES_REQUEST => vec![
+                    frame(PING_REQUEST, Message::default()),
+                    frame(LIGHT_STATE_RESPONSE, Message::default().fixed32(1, 42).bool(2, true).float(3, 0.25)),
+                ],
+                LIGHT_COMMAND_REQUEST => {
+                    commands.send(payload).unwrap();
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            };
+            for bytes in reply {
+                writer.write_all(&bytes).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover_and_command() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, mut commands) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(fake_device(listener, sender));
+
+        let provider = EsphomeProvider::new(vec![EsphomeDevice::new("127.0.0.1".to_string(), port, None)], 1000);
+        let lights = provider.discover().await.unwrap();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].id().0, "esphome:den.office");
+        assert_eq!(lights[0].label(), "Office");
+        assert!((lights[0].state().brightness.as_f32() - 0.25).abs() < 1e-6);
+
+        provider.set_brightness(lights[0].id(), Brightness::new(0.75)).await.unwrap();
+        let command = commands.recv().await.unwrap();
+        let fields = decode(&command).unwrap();
+        assert_eq!(fields[0].1.as_u32(), 42);
+        assert!(fields.iter().any(|(field, value)| *field == 5 && value.as_f32() == 0.75));
+
+        let state = provider.get_state(lights[0].id()).await.unwrap();
+        assert!(state.power);
+    }
+}
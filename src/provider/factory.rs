@@ -0,0 +1,166 @@
+use super::error::ProviderError;
+use super::types::Provider;
+use crate::config::Config;
+
+/// How a provider is built from the config. Each provider module submits one
+/// with `inventory::submit!`, so adding a provider doesn't touch this file.
+pub struct ProviderFactory {
+    pub name: &'static str,
+    /// Whether the provider's config section turns it on.
+    pub enabled: fn(&Config) -> bool,
+    /// Constructs the provider from its config section, whether or not that
+    /// section is enabled.
+    pub build: fn(&Config) -> Result<Box<dyn Provider>, ProviderError>,
+}
+
+inventory::collect!(ProviderFactory);
+
+// Generated by build.rs from the features in Cargo.toml, so providers left
+// out of this build can still be named and reported on.
+include!(concat!(env!("OUT_DIR"), "/provider_features.rs"));
+
+/// The providers compiled into this build, sorted by name.
+pub fn factories() -> Vec<&'static ProviderFactory> {
+    let mut factories: Vec<&ProviderFactory> = inventory::iter::<ProviderFactory>.into_iter().collect();
+    factories.sort_by_key(|factory| factory.name);
+    factories
+}
+
+fn factory(name: &str) -> Option<&'static ProviderFactory> {
+    inventory::iter::<ProviderFactory>.into_iter().find(|factory| factory.name == name)
+}
+
+/// Every provider name `build` accepts: the compiled-in providers in
+/// name order, then any feature-gated ones left out of this build.
+pub fn provider_names() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = factories().iter().map(|factory| factory.name).collect();
+    for name in PROVIDER_FEATURES {
+        if !names.contains(name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Constructs the named provider from its config section, whether or not
+/// that section is enabled.
+pub fn build(name: &str, config: &Config) -> Result<Box<dyn Provider>, ProviderError> {
+    match factory(name) {
+        Some(factory) => (factory.build)(config),
+        None if PROVIDER_FEATURES.contains(&name) => Err(ProviderError::NotConfigured(
+            format!("lightwire was built without the {} feature", name),
+        )),
+        None => Err(unknown_provider(name)),
+    }
+}
+
+fn unknown_provider(name: &str) -> ProviderError {
+    ProviderError::NotConfigured(format!("unknown provider '{}' (expected one of: {})", name, provider_names().join(", ")))
+}
+
+/// Whether the provider is compiled into this build.
+pub fn is_available(name: &str) -> bool {
+    factory(name).is_some()
+}
+
+/// The providers turned on in `config`. LIFX has no `enabled` switch and is
+/// included whenever it is compiled in.
+pub fn enabled_names(config: &Config) -> Vec<&'static str> {
+    for name in PROVIDER_FEATURES.iter().filter(|name| !is_available(name)) {
+        if section_enabled(config, name) {
+            tracing::warn!("[{}] is enabled but lightwire was built without the {} feature", name, name);
+        }
+    }
+    factories()
+        .into_iter()
+        .filter(|factory| (factory.enabled)(config))
+        .map(|factory| factory.name)
+        .collect()
+}
+
+/// Whether `[name]` in `config` has `enabled = true`, for providers whose
+/// module, and so whose `enabled` check, isn't compiled in.
+fn section_enabled(config: &Config, name: &str) -> bool {
+    toml::Value::try_from(config)
+        .ok()
+        .and_then(|config| config.get(name)?.get("enabled")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Splits a comma-separated `--provider` value, rejecting unknown names.
+pub fn parse_names(list: &str) -> Result<Vec<&'static str>, ProviderError> {
+    let known = provider_names();
+    let mut names = Vec::new();
+    for requested in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let name = known
+            .iter()
+            .copied()
+            .find(|name| *name == requested)
+            .ok_or_else(|| unknown_provider(requested))?;
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        return Err(ProviderError::NotConfigured("no provider named".to_string()));
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names() {
+        assert_eq!(parse_names("lifx, wled,lifx").unwrap(), ["lifx", "wled"]);
+        assert!(matches!(parse_names("lifx,hue"), Err(ProviderError::NotConfigured(e)) if e.contains("'hue'")));
+        assert!(parse_names(" , ").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "lifx", feature = "kasa", feature = "wled"))]
+    fn test_enabled_names() {
+        let mut config = Config::default();
+        assert_eq!(enabled_names(&config), ["lifx"]);
+        config.wled.enabled = true;
+        config.kasa.enabled = true;
+        assert_eq!(enabled_names(&config), ["kasa", "lifx", "wled"]);
+    }
+
+    #[test]
+    fn test_build_every_available_provider() {
+        let config = Config::default();
+        for name in provider_names().iter().filter(|name| is_available(name)) {
+            assert_eq!(build(name, &config).unwrap().name(), *name);
+        }
+        for name in provider_names().iter().filter(|name| !is_available(name)) {
+            assert!(matches!(build(name, &config), Err(ProviderError::NotConfigured(e)) if e.contains("feature")));
+        }
+        assert!(build("hue", &config).is_err());
+    }
+
+    #[test]
+    fn test_factories_sorted_by_name() {
+        let names: Vec<&str> = factories().iter().map(|factory| factory.name).collect();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", names);
+        assert_eq!(provider_names().len(), PROVIDER_FEATURES.len());
+    }
+
+    #[test]
+    fn test_every_provider_has_a_feature() {
+        for factory in factories() {
+            assert!(PROVIDER_FEATURES.contains(&factory.name), "{} has no feature", factory.name);
+        }
+        assert!(PROVIDER_FEATURES.contains(&"govee") && !PROVIDER_FEATURES.contains(&"metrics"));
+    }
+
+    #[test]
+    fn test_section_enabled() {
+        let mut config = Config::default();
+        assert!(!section_enabled(&config, "wled"));
+        config.wled.enabled = true;
+        assert!(section_enabled(&config, "wled"));
+        assert!(!section_enabled(&config, "lifx"));
+    }
+}
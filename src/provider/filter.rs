@@ -0,0 +1,156 @@
+use super::types::Light;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a `LightFilter` term is matched against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Provider,
+    Id,
+    Label,
+    Metadata(String),
+}
+
+/// Selects lights by provider, id, label or metadata value. Parsed from
+/// comma-separated `key=pattern` terms, all of which must match, e.g.
+/// `provider=lifx,label=Office*`. A term without a key matches the label,
+/// and any key other than `provider`, `id` or `label` names a metadata entry
+/// such as LIFX's `group`. Patterns are case-sensitive globs where `*`
+/// matches any run of characters and `?` any single one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LightFilter {
+    terms: Vec<(Field, String)>,
+}
+
+impl LightFilter {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, light: &dyn Light) -> bool {
+        self.terms.iter().all(|(field, pattern)| match field {
+            Field::Provider => glob_match(pattern, light.provider_name()),
+            Field::Id => glob_match(pattern, &light.id().0),
+            Field::Label => glob_match(pattern, light.label()),
+            Field::Metadata(key) => light
+                .metadata()
+                .and_then(|metadata| metadata.get(key))
+                .is_some_and(|value| glob_match(pattern, value)),
+        })
+    }
+
+    /// Keeps only the lights the filter matches.
+    pub fn retain(&self, lights: Vec<Box<dyn Light>>) -> Vec<Box<dyn Light>> {
+        lights.into_iter().filter(|light| self.matches(light.as_ref())).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLightFilter(String);
+
+impl fmt::Display for InvalidLightFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid light filter term '{}'; expected key=pattern or a label pattern", self.0)
+    }
+}
+
+impl std::error::Error for InvalidLightFilter {}
+
+impl FromStr for LightFilter {
+    type Err = InvalidLightFilter;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = s
+            .split(',')
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(|term| {
+                let (key, pattern) = term.split_once('=').unwrap_or(("label", term));
+                let field = match key.trim() {
+                    "" => return Err(InvalidLightFilter(term.to_string())),
+                    "provider" => Field::Provider,
+                    "id" => Field::Id,
+                    "label" => Field::Label,
+                    key => Field::Metadata(key.to_string()),
+                };
+                Ok((field, pattern.trim().to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { terms })
+    }
+}
+
+/// Matches `text` against a glob of literal characters, `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // The last `*` seen and the text position it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(all(test, feature = "lifx"))]
+mod tests {
+    use super::*;
+    use crate::provider::lifx::LifxLight;
+    use crate::provider::Brightness;
+    use std::collections::HashMap;
+
+    fn light(label: &str, group: &str) -> LifxLight {
+        let addr = "127.0.0.1:56700".parse().unwrap();
+        LifxLight::new(1, addr, label.to_string(), Brightness::new(0.5), true)
+            .with_metadata(HashMap::from([("group".to_string(), group.to_string())]))
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("Office*", "Office Lamp"));
+        assert!(glob_match("*Lamp", "Office Lamp"));
+        assert!(glob_match("O?fice*a*p", "Office Lamp"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("Office*", "office lamp"));
+        assert!(!glob_match("Office", "Office Lamp"));
+        assert!(!glob_match("*Desk", "Office Lamp"));
+    }
+
+    #[test]
+    fn test_filter_terms() {
+        let filter: LightFilter = "Office*".parse().unwrap();
+        assert!(filter.matches(&light("Office Lamp", "Upstairs")));
+        assert!(!filter.matches(&light("Kitchen", "Upstairs")));
+
+        let filter: LightFilter = "provider=lifx, group=Up*".parse().unwrap();
+        assert!(filter.matches(&light("Kitchen", "Upstairs")));
+        assert!(!filter.matches(&light("Kitchen", "Downstairs")));
+
+        let filter: LightFilter = "provider=kasa,label=Kitchen".parse().unwrap();
+        assert!(!filter.matches(&light("Kitchen", "Upstairs")));
+
+        let filter: LightFilter = "location=Home".parse().unwrap();
+        assert!(!filter.matches(&light("Kitchen", "Upstairs")));
+
+        assert!("".parse::<LightFilter>().unwrap().is_empty());
+        assert!("=Office".parse::<LightFilter>().is_err());
+    }
+}
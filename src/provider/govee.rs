@@ -0,0 +1,321 @@
+use super::types::{Capabilities, Color, Light, LightState, LightId, Brightness, Provider, DISCOVERY_GRACE};
+use super::error::ProviderError;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+const MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const SCAN_PORT: u16 = 4001;
+/// Devices always answer on this port, whatever port the request came from.
+const REPLY_PORT: u16 = 4002;
+const CONTROL_PORT: u16 = 4003;
+/// How long to wait for a `devStatus` reply.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(1000);
+const KELVIN_RANGE: (u16, u16) = (2000, 9000);
+
+#[derive(Debug)]
+pub struct GoveeLight {
+    ip: IpAddr,
+    state: LightState,
+}
+
+impl GoveeLight {
+    pub fn new(device: &str, ip: IpAddr, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            ip,
+            state: LightState::new(light_id_for_device(device), label, brightness, power),
+        }
+    }
+
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+}
+
+impl Light for GoveeLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "govee"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+/// Devices report MAC-like ids such as `1F:80:C5:32:32:36:72:4E`; the colons
+/// are dropped so the id has a single provider separator.
+pub fn light_id_for_device(device: &str) -> LightId {
+    LightId(format!("govee:{}", device.replace(':', "").to_lowercase()))
+}
+
+fn command(cmd: &str, data: serde_json::Value) -> Vec<u8> {
+    serde_json::json!({ "msg": { "cmd": cmd, "data": data } }).to_string().into_bytes()
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    msg: Reply,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", content = "data", rename_all = "camelCase")]
+enum Reply {
+    Scan(ScanData),
+    DevStatus(StatusData),
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanData {
+    ip: IpAddr,
+    device: String,
+    sku: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusData {
+    on_off: u8,
+    brightness: u8,
+}
+
+/// Replies to commands other than `scan` and `devStatus` parse as `None`.
+fn parse_reply(buf: &[u8]) -> Option<Reply> {
+    serde_json::from_slice::<Envelope>(buf).ok().map(|envelope| envelope.msg)
+}
+
+/// Govee lights with the LAN API enabled in the Govee Home app.
+#[derive(Debug)]
+pub struct GoveeProvider {
+    discovery_timeout: Duration,
+    /// Addresses learned during discovery.
+    devices: RwLock<HashMap<LightId, IpAddr>>,
+    /// Replies all arrive on port 4002, so only one query may listen at a time.
+    reply_port: Mutex<()>,
+}
+
+impl GoveeProvider {
+    pub fn new(discovery_timeout_ms: u64) -> Self {
+        Self {
+            discovery_timeout: Duration::from_millis(discovery_timeout_ms),
+            devices: RwLock::new(HashMap::new()),
+            reply_port: Mutex::new(()),
+        }
+    }
+
+    pub fn default_config() -> Self {
+        Self::new(5000)
+    }
+
+    async fn reply_socket() -> Result<UdpSocket, ProviderError> {
+        let socket = UdpSocket::bind(("0.0.0.0", REPLY_PORT)).await?;
+        socket.join_multicast_v4(MULTICAST_ADDR, Ipv4Addr::UNSPECIFIED)?;
+        Ok(socket)
+    }
+
+    async fn send(ip: IpAddr, payload: &[u8]) -> Result<(), ProviderError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.send_to(payload, SocketAddr::new(ip, CONTROL_PORT)).await?;
+        Ok(())
+    }
+
+    fn known_ip(&self, id: &LightId) -> Option<IpAddr> {
+        self.devices.read().expect("Govee device table poisoned").get(id).copied()
+    }
+
+    /// Looks up a device's address, running discovery once if it isn't known yet.
+    async fn ip_for(&self, id: &LightId) -> Result<IpAddr, ProviderError> {
+        if let Some(ip) = self.known_ip(id) {
+            return Ok(ip);
+        }
+        if let Err(e) = self.discover().await {
+            tracing::debug!("Govee rediscovery for {} failed: {}", id.0, e);
+        }
+        self.known_ip(id).ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    async fn status(&self, ip: IpAddr) -> Result<StatusData, ProviderError> {
+        let _guard = self.reply_port.lock().await;
+        let socket = Self::reply_socket().await?;
+        Self::send(ip, &command("devStatus", serde_json::json!({}))).await?;
+
+        let mut buf = [0u8; 2048];
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        loop {
+            let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await else {
+                return Err(ProviderError::Timeout(format!("no status from Govee device at {}", ip)));
+            };
+            let (len, from) = received?;
+            if from.ip() != ip {
+                continue;
+            }
+            if let Some(Reply::DevStatus(status)) = parse_reply(&buf[..len]) {
+                return Ok(status);
+            }
+        }
+    }
+}
+
+impl Default for GoveeProvider {
+    fn default() -> Self {
+        Self::default_config()
+    }
+}
+
+#[async_trait]
+impl Provider for GoveeProvider {
+    fn name(&self) -> &'static str {
+        "govee"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: false }
+    }
+
+    fn discovery_timeout(&self) -> Duration {
+        self.discovery_timeout + DISCOVERY_GRACE
+    }
+
+    /// Govee's scan reply carries no state, so each device is queried once
+    /// more for its brightness.
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let found = {
+            let _guard = self.reply_port.lock().await;
+            let socket = Self::reply_socket().await?;
+            let scan = command("scan", serde_json::json!({ "account_topic": "reserve" }));
+            socket.send_to(&scan, (MULTICAST_ADDR, SCAN_PORT)).await?;
+            tracing::debug!("Sent Govee scan to {}:{}", MULTICAST_ADDR, SCAN_PORT);
+
+            let mut found: Vec<ScanData> = Vec::new();
+            let mut buf = [0u8; 2048];
+            let deadline = Instant::now() + self.discovery_timeout;
+            while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+                let (len, _) = received?;
+                if let Some(Reply::Scan(data)) = parse_reply(&buf[..len]) {
+                    if !found.iter().any(|known| known.device == data.device) {
+                        found.push(data);
+                    }
+                }
+            }
+            found
+        };
+
+        if found.is_empty() {
+            return Err(ProviderError::Timeout(format!(
+                "no Govee devices answered within {}ms (is the LAN API enabled?)",
+                self.discovery_timeout.as_millis()
+            )));
+        }
+
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        let mut devices = HashMap::new();
+        for data in found {
+            let (brightness, power) = match self.status(data.ip).await {
+                Ok(status) => (Brightness::from_percent(status.brightness), status.on_off != 0),
+                Err(e) => {
+                    tracing::warn!("Govee {} at {} found but did not report status: {}", data.sku, data.ip, e);
+                    (Brightness::default(), false)
+                }
+            };
+            let label = format!("{} {}", data.sku, &data.device[data.device.len().saturating_sub(5)..]);
+            let light = GoveeLight::new(&data.device, data.ip, label, brightness, power);
+            devices.insert(light.id().clone(), data.ip);
+            lights.push(Box::new(light));
+        }
+
+        self.devices.write().expect("Govee device table poisoned").extend(devices);
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let ip = self.ip_for(id).await?;
+        let status = self.status(ip).await?;
+        Ok(LightState::new(id.clone(), id.0.clone(), Brightness::from_percent(status.brightness), status.on_off != 0))
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let ip = self.ip_for(id).await?;
+        Self::send(ip, &command("brightness", serde_json::json!({ "value": brightness.as_percent() }))).await
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        let ip = self.ip_for(id).await?;
+        Self::send(ip, &command("turn", serde_json::json!({ "value": u8::from(on) }))).await
+    }
+
+    async fn set_kelvin(&self, id: &LightId, kelvin: u16) -> Result<(), ProviderError> {
+        let ip = self.ip_for(id).await?;
+        let data = serde_json::json!({
+            "color": { "r": 0, "g": 0, "b": 0 },
+            "colorTemInKelvin": kelvin.clamp(KELVIN_RANGE.0, KELVIN_RANGE.1),
+        });
+        Self::send(ip, &command("colorwc", data)).await
+    }
+
+    async fn set_color(&self, id: &LightId, color: Color) -> Result<(), ProviderError> {
+        let ip = self.ip_for(id).await?;
+        let (r, g, b) = Color::new(color.hue, color.saturation, 100).to_rgb();
+        let data = serde_json::json!({ "color": { "r": r, "g": g, "b": b }, "colorTemInKelvin": 0 });
+        Self::send(ip, &command("colorwc", data)).await?;
+        Self::send(ip, &command("brightness", serde_json::json!({ "value": color.value }))).await
+    }
+
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        let _guard = self.reply_port.lock().await;
+        Self::reply_socket().await.map(|_| ())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "govee",
+        enabled: |config| config.govee.enabled,
+        build: |config| Ok(Box::new(GoveeProvider::new(config.govee.discovery_timeout_ms))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scan_reply() {
+        let reply = br#"{"msg":{"cmd":"scan","data":{"ip":"192.168.1.23","device":"1F:80:C5:32:32:36:72:4E",
+            "sku":"H618E","bleVersionHard":"3.01.01","wifiVersionSoft":"1.00.10"}}}"#;
+        let Some(Reply::Scan(data)) = parse_reply(reply) else {
+            panic!("expected a scan reply");
+        };
+        assert_eq!(data.ip, "192.168.1.23".parse::<IpAddr>().unwrap());
+        assert_eq!(light_id_for_device(&data.device).0, "govee:1f80c5323236724e");
+    }
+
+    #[test]
+    fn test_parse_status_reply() {
+        let reply = br#"{"msg":{"cmd":"devStatus","data":{"onOff":1,"brightness":37,
+            "color":{"r":255,"g":0,"b":0},"colorTemInKelvin":0}}}"#;
+        let Some(Reply::DevStatus(status)) = parse_reply(reply) else {
+            panic!("expected a status reply");
+        };
+        assert_eq!((status.on_off, status.brightness), (1, 37));
+        assert!(parse_reply(br#"{"msg":{"cmd":"turn","data":{}}}"#).is_none());
+    }
+
+    #[test]
+    fn test_command() {
+        let payload = command("brightness", serde_json::json!({ "value": 20 }));
+        assert_eq!(payload, br#"{"msg":{"cmd":"brightness","data":{"value":20}}}"#);
+    }
+}
@@ -0,0 +1,173 @@
+use super::types::{Light, LightState, LightId, Brightness};
+use std::collections::HashSet;
+
+/// Provider name used for group ids; the registry handles it itself.
+pub const GROUP_PROVIDER: &str = "group";
+
+/// A named set of lights that move together behind one PipeWire node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightGroup {
+    pub name: String,
+    pub members: Vec<LightId>,
+}
+
+impl LightGroup {
+    pub fn new(name: String, members: Vec<LightId>) -> Self {
+        Self { name, members }
+    }
+
+    pub fn id(&self) -> LightId {
+        group_id(&self.name)
+    }
+}
+
+pub fn group_id(name: &str) -> LightId {
+    LightId(format!("{}:{}", GROUP_PROVIDER, name))
+}
+
+pub fn group_name(id: &LightId) -> Option<&str> {
+    id.0.strip_prefix(GROUP_PROVIDER)?.strip_prefix(':')
+}
+
+/// A group presented as a single light, reporting its members' average brightness.
+#[derive(Debug)]
+pub struct GroupLight {
+    state: LightState,
+    members: Vec<LightId>,
+}
+
+impl GroupLight {
+    pub fn new(group: &LightGroup, members: &[&dyn Light]) -> Self {
+        let brightness = average_brightness(members.iter().map(|light| light.state().brightness));
+        let power = members.iter().any(|light| light.state().power);
+        Self {
+            state: LightState::new(group.id(), group.name.clone(), brightness, power),
+            members: members.iter().map(|light| light.id().clone()).collect(),
+        }
+    }
+
+    pub fn members(&self) -> &[LightId] {
+        &self.members
+    }
+}
+
+impl Light for GroupLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        GROUP_PROVIDER
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+pub fn average_brightness(levels: impl Iterator<Item = Brightness>) -> Brightness {
+    let (sum, count) = levels.fold((0.0, 0), |(sum, count), b| (sum + b.as_f32(), count + 1));
+    Brightness::new(if count == 0 { 0.0 } else { sum / count as f32 })
+}
+
+/// Replaces discovered members of each group with one `GroupLight`. Groups
+/// with no discovered members are dropped.
+pub fn apply_groups(lights: Vec<Box<dyn Light>>, groups: &[LightGroup]) -> Vec<Box<dyn Light>> {
+    if groups.is_empty() {
+        return lights;
+    }
+
+    let mut grouped: Vec<Box<dyn Light>> = Vec::new();
+    let mut claimed = HashSet::new();
+    for group in groups {
+        let members: Vec<&dyn Light> = lights
+            .iter()
+            .filter(|light| group.members.contains(light.id()))
+            .map(|light| light.as_ref())
+            .collect();
+        if members.is_empty() {
+            tracing::warn!("No members of group {} were discovered", group.name);
+            continue;
+        }
+        if members.len() < group.members.len() {
+            tracing::warn!("Group {}: only {} of {} members discovered", group.name, members.len(), group.members.len());
+        }
+        claimed.extend(members.iter().map(|light| light.id().clone()));
+        grouped.push(Box::new(GroupLight::new(group, &members)));
+    }
+
+    lights
+        .into_iter()
+        .filter(|light| !claimed.contains(light.id()))
+        .chain(grouped)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestLight(LightState);
+
+    impl Light for TestLight {
+        fn id(&self) -> &LightId {
+            &self.0.id
+        }
+
+        fn label(&self) -> &str {
+            &self.0.label
+        }
+
+        fn provider_name(&self) -> &str {
+            "test"
+        }
+
+        fn state(&self) -> &LightState {
+            &self.0
+        }
+    }
+
+    fn light(id: &str, brightness: f32) -> Box<dyn Light> {
+        Box::new(TestLight(LightState::new(
+            LightId(id.to_string()),
+            id.to_string(),
+            Brightness::new(brightness),
+            brightness > 0.0,
+        )))
+    }
+
+    #[test]
+    fn test_group_id_round_trip() {
+        assert_eq!(group_id("desk").0, "group:desk");
+        assert_eq!(group_name(&group_id("desk")), Some("desk"));
+        assert_eq!(group_name(&LightId("lifx:abc".to_string())), None);
+    }
+
+    #[test]
+    fn test_apply_groups() {
+        let lights = vec![light("test:a", 0.2), light("test:b", 0.6), light("test:c", 1.0)];
+        let group = LightGroup::new(
+            "desk".to_string(),
+            vec![LightId("test:a".to_string()), LightId("test:b".to_string()), LightId("test:missing".to_string())],
+        );
+
+        let lights = apply_groups(lights, &[group]);
+        let ids: Vec<&str> = lights.iter().map(|light| light.id().0.as_str()).collect();
+        assert_eq!(ids, vec!["test:c", "group:desk"]);
+        assert!((lights[1].state().brightness.as_f32() - 0.4).abs() < 1e-6);
+        assert_eq!(lights[1].provider_name(), GROUP_PROVIDER);
+    }
+
+    #[test]
+    fn test_apply_groups_drops_empty_groups() {
+        let group = LightGroup::new("ghost".to_string(), vec![LightId("test:missing".to_string())]);
+        let lights = apply_groups(vec![light("test:a", 0.5)], &[group]);
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].id().0, "test:a");
+    }
+}
@@ -0,0 +1,303 @@
+use super::types::{Capabilities, Color, Light, LightState, LightId, Brightness, Provider};
+use super::error::{http_error, ProviderError};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Home Assistant reports brightness on a 0-255 scale but accepts `brightness_pct`.
+const HA_BRIGHTNESS_MAX: f32 = 255.0;
+
+#[derive(Debug)]
+pub struct HomeAssistantLight {
+    state: LightState,
+}
+
+impl HomeAssistantLight {
+    pub fn new(entity_id: &str, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            state: LightState::new(light_id_for_entity(entity_id), label, brightness, power),
+        }
+    }
+}
+
+impl Light for HomeAssistantLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "homeassistant"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+pub fn light_id_for_entity(entity_id: &str) -> LightId {
+    LightId(format!("homeassistant:{}", entity_id))
+}
+
+pub fn entity_for_light_id(id: &LightId) -> Option<&str> {
+    id.0.strip_prefix("homeassistant:").filter(|entity| entity.starts_with("light."))
+}
+
+#[derive(Debug, Deserialize)]
+struct EntityState {
+    entity_id: String,
+    state: String,
+    #[serde(default)]
+    attributes: Attributes,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Attributes {
+    /// Absent or null while the light is off.
+    brightness: Option<u8>,
+    friendly_name: Option<String>,
+    /// Hue in degrees and saturation in percent, while in a color mode.
+    hs_color: Option<[f32; 2]>,
+}
+
+impl EntityState {
+    fn power(&self) -> bool {
+        self.state == "on"
+    }
+
+    fn brightness(&self) -> Brightness {
+        match self.attributes.brightness {
+            Some(bri) if self.power() => Brightness::new(bri as f32 / HA_BRIGHTNESS_MAX),
+            _ => Brightness::new(0.0),
+        }
+    }
+
+    fn to_light_state(&self) -> LightState {
+        let label = self.attributes.friendly_name.clone().unwrap_or_else(|| self.entity_id.clone());
+        let state = LightState::new(light_id_for_entity(&self.entity_id), label, self.brightness(), self.power());
+        match self.attributes.hs_color {
+            Some([hue, saturation]) => state.with_color(Color::new(
+                hue.round() as u16,
+                saturation.round() as u8,
+                self.brightness().as_percent(),
+            )),
+            None => state,
+        }
+    }
+}
+
+/// The `light.*` entities among `/api/states`. Each is parsed on its own, so
+/// other domains' attributes, or one malformed light, can't fail the rest.
+fn light_entities(states: Vec<serde_json::Value>) -> Vec<EntityState> {
+    states
+        .into_iter()
+        .filter_map(|state| {
+            let entity_id = state["entity_id"].as_str().filter(|id| id.starts_with("light."))?.to_string();
+            serde_json::from_value(state)
+                .map_err(|e| tracing::warn!("Skipping Home Assistant entity {}: {}", entity_id, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Every `light.*` entity of a Home Assistant instance, through its REST API.
+#[derive(Debug)]
+pub struct HomeAssistantProvider {
+    url: String,
+    token: String,
+    client: reqwest::Client,
+}
+
+impl HomeAssistantProvider {
+    pub fn new(url: String, token: String, timeout_ms: u64) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(timeout_ms))
+            .build()
+            .expect("Failed to build HTTP client");
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            client,
+        }
+    }
+
+    fn entity<'a>(&self, id: &'a LightId) -> Result<&'a str, ProviderError> {
+        entity_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    fn check_token(&self) -> Result<(), ProviderError> {
+        if self.token.is_empty() {
+            return Err(ProviderError::NotConfigured("no Home Assistant token configured".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, ProviderError> {
+        self.check_token()?;
+        self.client
+            .get(format!("{}{}", self.url, path))
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(http_error)?
+            .json()
+            .await
+            .map_err(http_error)
+    }
+
+    async fn call_service(&self, service: &str, body: serde_json::Value) -> Result<(), ProviderError> {
+        self.check_token()?;
+        self.client
+            .post(format!("{}/api/services/light/{}", self.url, service))
+            .bearer_auth(&self.token)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(http_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for HomeAssistantProvider {
+    fn name(&self) -> &'static str {
+        "homeassistant"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: true }
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let states: Vec<serde_json::Value> = self.get_json("/api/states").await?;
+        let entities = states.len();
+        let lights: Vec<Box<dyn Light>> = light_entities(states)
+            .iter()
+            .filter(|entity| entity.state != "unavailable")
+            .map(|entity| {
+                let state = entity.to_light_state();
+                Box::new(HomeAssistantLight::new(&entity.entity_id, state.label, state.brightness, state.power))
+                    as Box<dyn Light>
+            })
+            .collect();
+        tracing::debug!("Home Assistant reported {} light(s) among {} entities", lights.len(), entities);
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let entity: EntityState = self.get_json(&format!("/api/states/{}", self.entity(id)?)).await?;
+        Ok(entity.to_light_state())
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let body = serde_json::json!({ "entity_id": self.entity(id)?, "brightness_pct": brightness.as_percent() });
+        self.call_service("turn_on", body).await
+    }
+
+    async fn set_brightness_with_transition(
+        &self,
+        id: &LightId,
+        brightness: Brightness,
+        duration: Duration,
+    ) -> Result<(), ProviderError> {
+        let body = serde_json::json!({
+            "entity_id": self.entity(id)?,
+            "brightness_pct": brightness.as_percent(),
+            "transition": duration.as_secs_f32(),
+        });
+        self.call_service("turn_on", body).await
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        let service = if on { "turn_on" } else { "turn_off" };
+        self.call_service(service, serde_json::json!({ "entity_id": self.entity(id)? })).await
+    }
+
+    async fn set_kelvin(&self, id: &LightId, kelvin: u16) -> Result<(), ProviderError> {
+        let body = serde_json::json!({ "entity_id": self.entity(id)?, "color_temp_kelvin": kelvin });
+        self.call_service("turn_on", body).await
+    }
+
+    async fn set_color(&self, id: &LightId, color: Color) -> Result<(), ProviderError> {
+        let body = serde_json::json!({
+            "entity_id": self.entity(id)?,
+            "hs_color": [color.hue, color.saturation],
+            "brightness_pct": color.value,
+        });
+        self.call_service("turn_on", body).await
+    }
+
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        self.get_json::<serde_json::Value>("/api/").await.map(|_| ())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "homeassistant",
+        enabled: |config| config.homeassistant.enabled,
+        build: |config| {
+            Ok(Box::new(HomeAssistantProvider::new(
+                config.homeassistant.url.clone(),
+                config.homeassistant.token.clone(),
+                config.homeassistant.timeout_ms,
+            )))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_id_round_trip() {
+        let id = light_id_for_entity("light.kitchen");
+        assert_eq!(id.0, "homeassistant:light.kitchen");
+        assert_eq!(entity_for_light_id(&id), Some("light.kitchen"));
+        assert_eq!(entity_for_light_id(&LightId("homeassistant:switch.fan".to_string())), None);
+    }
+
+    #[test]
+    fn test_parse_states() {
+        let states: Vec<EntityState> = serde_json::from_str(
+            r#"[
+                {"entity_id":"light.kitchen","state":"on","attributes":{"brightness":128,"friendly_name":"Kitchen","hs_color":[30.5,80.0]}},
+                {"entity_id":"light.porch","state":"off","attributes":{"brightness":null}}
+            ]"#,
+        )
+        .unwrap();
+
+        let kitchen = states[0].to_light_state();
+        assert_eq!(kitchen.label, "Kitchen");
+        assert!(kitchen.power);
+        assert!((kitchen.brightness.as_f32() - 0.502).abs() < 0.001);
+        assert_eq!(kitchen.color, Some(Color::new(31, 80, 50)));
+
+        let porch = states[1].to_light_state();
+        assert_eq!(porch.label, "light.porch");
+        assert!(!porch.power);
+        assert_eq!(porch.brightness.as_f32(), 0.0);
+        assert_eq!(porch.color, None);
+    }
+    #[test]
+    fn test_light_entities_skips_bad_entities() {
+        let states: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[
+                {"entity_id":"sensor.power","state":"12","attributes":{"brightness":"high","hs_color":"none"}},
+                {"entity_id":"light.broken","state":"on","attributes":{"brightness":300}},
+                {"entity_id":"light.kitchen","state":"on","attributes":{"brightness":255}},
+                {"state":"on"}
+            ]"#,
+        )
+        .unwrap();
+        let entities = light_entities(states);
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].entity_id, "light.kitchen");
+    }
+}
@@ -0,0 +1,357 @@
+use super::types::{Light, LightState, LightId, Brightness, Provider};
+use super::error::ProviderError;
+use crate::config::{HttpConfig, HttpDeviceConfig, HttpRequestConfig};
+use async_trait::async_trait;
+use reqwest::Method;
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Debug)]
+pub struct HttpLight {
+    state: LightState,
+}
+
+impl HttpLight {
+    pub fn new(device_id: &str, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            state: LightState::new(light_id_for_device(device_id), label, brightness, power),
+        }
+    }
+}
+
+impl Light for HttpLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "http"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+pub fn light_id_for_device(device_id: &str) -> LightId {
+    LightId(format!("http:{}", device_id))
+}
+
+pub fn device_for_light_id(id: &LightId) -> Option<&str> {
+    id.0.strip_prefix("http:").filter(|device| !device.is_empty())
+}
+
+/// Replaces `{name}` placeholders. Braces around anything that isn't a bare
+/// lowercase name, such as JSON objects, are left alone.
+fn render(template: &str, vars: &[(&str, &str)]) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after.find(|c: char| !(c.is_ascii_lowercase() || c == '_')).unwrap_or(after.len());
+        if name_len > 0 && after[name_len..].starts_with('}') {
+            let name = &after[..name_len];
+            let value = vars
+                .iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| format!("unknown placeholder {{{}}}", name))?;
+            out.push_str(value);
+            rest = &after[name_len + 1..];
+        } else {
+            out.push('{');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A request template, checked once so rendering at runtime can't fail on
+/// the template itself.
+#[derive(Debug, Clone)]
+struct RequestTemplate {
+    method: Method,
+    url: String,
+    body: Option<String>,
+}
+
+impl RequestTemplate {
+    fn new(config: &HttpRequestConfig, placeholders: &[&str]) -> Result<Self, String> {
+        let method = match &config.method {
+            Some(method) => Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| format!("invalid method {}", method))?,
+            None if config.body.is_some() => Method::POST,
+            None => Method::GET,
+        };
+
+        let sample: Vec<(&str, &str)> = placeholders.iter().map(|name| (*name, "1")).collect();
+        let url = render(&config.url, &sample)?;
+        reqwest::Url::parse(&url).map_err(|e| format!("invalid url {}: {}", config.url, e))?;
+        if let Some(body) = &config.body {
+            let body = render(body, &sample)?;
+            serde_json::from_str::<serde_json::Value>(&body).map_err(|e| format!("body is not valid JSON: {}", e))?;
+        }
+
+        Ok(Self { method, url: config.url.clone(), body: config.body.clone() })
+    }
+}
+
+/// Checks every template in `config`, naming the first bad one.
+pub fn validate_config(config: &HttpConfig) -> Result<(), String> {
+    templates(config).map(|_| ())
+}
+
+type Templates = (Option<RequestTemplate>, Option<RequestTemplate>, Option<RequestTemplate>);
+
+fn templates(config: &HttpConfig) -> Result<Templates, String> {
+    let build = |name: &str, request: &Option<HttpRequestConfig>, placeholders: &[&str]| {
+        request
+            .as_ref()
+            .map(|request| RequestTemplate::new(request, placeholders).map_err(|e| format!("{}: {}", name, e)))
+            .transpose()
+    };
+    Ok((
+        build("discover", &config.discover, &[])?,
+        build("get", &config.get, &["id"])?,
+        build("set", &config.set, &["id", "brightness"])?,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DiscoveredDevice {
+    Id(String),
+    Device { id: String, #[serde(alias = "name")] label: Option<String> },
+}
+
+impl From<DiscoveredDevice> for HttpDeviceConfig {
+    fn from(device: DiscoveredDevice) -> Self {
+        match device {
+            DiscoveredDevice::Id(id) => HttpDeviceConfig { id, label: None },
+            DiscoveredDevice::Device { id, label } => HttpDeviceConfig { id, label },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct HttpProvider {
+    client: reqwest::Client,
+    devices: Vec<HttpDeviceConfig>,
+    discover: Option<RequestTemplate>,
+    get: Option<RequestTemplate>,
+    set: Option<RequestTemplate>,
+    brightness_pointer: String,
+    brightness_max: f32,
+}
+
+impl HttpProvider {
+    pub fn new(config: &HttpConfig) -> Result<Self, ProviderError> {
+        let (discover, get, set) = templates(config).map_err(ProviderError::NotConfigured)?;
+        if config.brightness_max <= 0.0 {
+            return Err(ProviderError::NotConfigured("brightness_max must be positive".to_string()));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("Failed to build HTTP client");
+        Ok(Self {
+            client,
+            devices: config.devices.clone(),
+            discover,
+            get,
+            set,
+            brightness_pointer: config.brightness_pointer.clone(),
+            brightness_max: config.brightness_max,
+        })
+    }
+
+    fn format_brightness(&self, brightness: Brightness) -> String {
+        let scaled = brightness.as_f32() * self.brightness_max;
+        if self.brightness_max > 1.0 {
+            format!("{}", scaled.round() as i64)
+        } else {
+            format!("{:.3}", scaled)
+        }
+    }
+
+    async fn send(&self, template: &RequestTemplate, vars: &[(&str, &str)]) -> Result<reqwest::Response, ProviderError> {
+        let url = render(&template.url, vars).map_err(ProviderError::Protocol)?;
+        let mut request = self.client.request(template.method.clone(), &url);
+        if let Some(body) = &template.body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(render(body, vars).map_err(ProviderError::Protocol)?);
+        }
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                ProviderError::Timeout(format!("{}: {}", url, e))
+            } else {
+                ProviderError::Network(std::io::Error::other(e.to_string()))
+            }
+        })?;
+        if !response.status().is_success() {
+            return Err(ProviderError::Protocol(format!("{} {} returned {}", template.method, url, response.status())));
+        }
+        Ok(response)
+    }
+
+    async fn send_json(&self, template: &RequestTemplate, vars: &[(&str, &str)]) -> Result<serde_json::Value, ProviderError> {
+        self.send(template, vars)
+            .await?
+            .json()
+            .await
+            .map_err(|e| ProviderError::Protocol(format!("invalid JSON response: {}", e)))
+    }
+
+    async fn read_brightness(&self, device_id: &str) -> Result<Brightness, ProviderError> {
+        let get = self
+            .get
+            .as_ref()
+            .ok_or_else(|| ProviderError::Protocol("unsupported".to_string()))?;
+        let response = self.send_json(get, &[("id", device_id)]).await?;
+        let value = response
+            .pointer(&self.brightness_pointer)
+            .and_then(serde_json::Value::as_f64)
+            .ok_or_else(|| ProviderError::Protocol(format!("no number at {} in response", self.brightness_pointer)))?;
+        Ok(Brightness::new(value as f32 / self.brightness_max))
+    }
+
+    fn label_for(&self, device_id: &str) -> String {
+        self.devices
+            .iter()
+            .find(|device| device.id == device_id)
+            .and_then(|device| device.label.clone())
+            .unwrap_or_else(|| device_id.to_string())
+    }
+}
+
+#[async_trait]
+impl Provider for HttpProvider {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let devices = match &self.discover {
+            Some(discover) => {
+                let response = self.send_json(discover, &[]).await?;
+                serde_json::from_value::<Vec<DiscoveredDevice>>(response)
+                    .map_err(|e| ProviderError::Protocol(format!("unexpected discover response: {}", e)))?
+                    .into_iter()
+                    .map(HttpDeviceConfig::from)
+                    .collect()
+            }
+            None => self.devices.clone(),
+        };
+
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        for device in devices {
+            let label = device.label.unwrap_or_else(|| self.label_for(&device.id));
+            let brightness = match self.read_brightness(&device.id).await {
+                Ok(brightness) => brightness,
+                Err(e) => {
+                    tracing::debug!("Could not read HTTP device {}: {}", device.id, e);
+                    Brightness::new(0.0)
+                }
+            };
+            lights.push(Box::new(HttpLight::new(&device.id, label, brightness, brightness.as_f32() > 0.0)));
+        }
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let device_id = device_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))?;
+        let brightness = self.read_brightness(device_id).await?;
+        Ok(LightState::new(id.clone(), self.label_for(device_id), brightness, brightness.as_f32() > 0.0))
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let device_id = device_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))?;
+        let set = self
+            .set
+            .as_ref()
+            .ok_or_else(|| ProviderError::Protocol("unsupported".to_string()))?;
+        let value = self.format_brightness(brightness);
+        self.send(set, &[("id", device_id), ("brightness", &value)]).await?;
+        Ok(())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "http",
+        enabled: |config| config.http.enabled,
+        build: |config| Ok(Box::new(HttpProvider::new(&config.http)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, body: Option<&str>) -> HttpRequestConfig {
+        HttpRequestConfig { method: None, url: url.to_string(), body: body.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render(r#"{"level": {brightness}, "target": "{id}"}"#, &[("id", "lamp"), ("brightness", "42")]);
+        assert_eq!(rendered.unwrap(), r#"{"level": 42, "target": "lamp"}"#);
+        assert_eq!(render("http://x/{id}/{nope}", &[("id", "a")]).unwrap_err(), "unknown placeholder {nope}");
+        assert_eq!(render("{}", &[]).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_template_defaults_method() {
+        let get = RequestTemplate::new(&request("http://host/{id}", None), &["id"]).unwrap();
+        assert_eq!(get.method, Method::GET);
+        let set = RequestTemplate::new(&request("http://host/{id}", Some(r#"{"bri": {brightness}}"#)), &["id", "brightness"]).unwrap();
+        assert_eq!(set.method, Method::POST);
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let mut config = HttpConfig {
+            set: Some(request("http://host/{id}", Some(r#"{"bri": {brightness}}"#))),
+            ..HttpConfig::default()
+        };
+        assert!(validate_config(&config).is_ok());
+
+        config.get = Some(request("http://host/{id}?b={brightness}", None));
+        assert_eq!(validate_config(&config).unwrap_err(), "get: unknown placeholder {brightness}");
+
+        config.get = None;
+        config.set = Some(request("http://host/{id}", Some(r#"{"bri": {brightness}"#)));
+        assert!(validate_config(&config).unwrap_err().starts_with("set: body is not valid JSON"));
+
+        config.set = Some(request("not a url", None));
+        assert!(validate_config(&config).unwrap_err().starts_with("set: invalid url"));
+    }
+
+    #[test]
+    fn test_format_brightness() {
+        let mut config = HttpConfig::default();
+        let provider = HttpProvider::new(&config).unwrap();
+        assert_eq!(provider.format_brightness(Brightness::new(0.5)), "50");
+
+        config.brightness_max = 1.0;
+        let provider = HttpProvider::new(&config).unwrap();
+        assert_eq!(provider.format_brightness(Brightness::new(0.5)), "0.500");
+    }
+
+    #[test]
+    fn test_discovered_device_shapes() {
+        let devices: Vec<DiscoveredDevice> =
+            serde_json::from_str(r#"["a", {"id": "b", "name": "Bee"}, {"id": "c"}]"#).unwrap();
+        let devices: Vec<HttpDeviceConfig> = devices.into_iter().map(HttpDeviceConfig::from).collect();
+        assert_eq!(devices[0].id, "a");
+        assert_eq!(devices[1].label.as_deref(), Some("Bee"));
+        assert_eq!(devices[2].label, None);
+    }
+}
@@ -0,0 +1,387 @@
+use super::types::{Capabilities, Light, LightState, LightId, Brightness, Provider, DISCOVERY_GRACE};
+use super::error::ProviderError;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+/// Initial key of TP-Link's autokey XOR cipher.
+const XOR_KEY: u8 = 171;
+/// How long to wait for a device to answer a direct request.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(1000);
+const GET_SYSINFO: &str = r#"{"system":{"get_sysinfo":{}}}"#;
+
+#[derive(Debug)]
+pub struct KasaLight {
+    addr: SocketAddr,
+    state: LightState,
+}
+
+impl KasaLight {
+    pub fn new(device_id: &str, addr: SocketAddr, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            addr,
+            state: LightState::new(light_id_for_device(device_id), label, brightness, power),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Light for KasaLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "kasa"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+pub fn light_id_for_device(device_id: &str) -> LightId {
+    LightId(format!("kasa:{}", device_id.to_lowercase()))
+}
+
+/// Applies TP-Link's autokey XOR, where each ciphertext byte keys the next.
+fn encrypt(plain: &[u8]) -> Vec<u8> {
+    let mut key = XOR_KEY;
+    plain
+        .iter()
+        .map(|&b| {
+            key ^= b;
+            key
+        })
+        .collect()
+}
+
+fn decrypt(cipher: &[u8]) -> Vec<u8> {
+    let mut key = XOR_KEY;
+    cipher
+        .iter()
+        .map(|&c| {
+            let plain = key ^ c;
+            key = c;
+            plain
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    system: Option<SystemResponse>,
+    #[serde(rename = "smartlife.iot.smartbulb.lightingservice")]
+    lighting: Option<LightingResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SystemResponse {
+    get_sysinfo: SysInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct SysInfo {
+    alias: String,
+    #[serde(rename = "deviceId")]
+    device_id: String,
+    /// Absent on plugs and switches, which we don't drive.
+    light_state: Option<BulbState>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BulbState {
+    on_off: u8,
+    /// Only reported while the bulb is on.
+    brightness: Option<u8>,
+    /// The level the bulb will return to, reported while it is off.
+    dft_on_state: Option<DefaultOnState>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefaultOnState {
+    brightness: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct LightingResponse {
+    transition_light_state: TransitionReply,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransitionReply {
+    #[serde(default)]
+    err_code: i32,
+    err_msg: Option<String>,
+}
+
+impl BulbState {
+    fn brightness(&self) -> Brightness {
+        let percent = self
+            .brightness
+            .or_else(|| self.dft_on_state.as_ref().map(|state| state.brightness))
+            .unwrap_or(0);
+        Brightness::from_percent(percent)
+    }
+}
+
+fn parse_response(buf: &[u8]) -> Option<Response> {
+    serde_json::from_slice(&decrypt(buf)).ok()
+}
+
+#[derive(Debug)]
+pub struct KasaProvider {
+    discovery_timeout: Duration,
+    broadcast_address: String,
+    port: u16,
+    /// Addresses learned during discovery, since Kasa ids don't encode one.
+    devices: RwLock<HashMap<LightId, SocketAddr>>,
+}
+
+impl KasaProvider {
+    pub fn new(discovery_timeout_ms: u64, broadcast_address: String, port: u16) -> Self {
+        Self {
+            discovery_timeout: Duration::from_millis(discovery_timeout_ms),
+            broadcast_address,
+            port,
+            devices: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn default_config() -> Self {
+        Self::new(5000, "255.255.255.255".to_string(), 9999)
+    }
+
+    async fn bind_socket(&self) -> Result<UdpSocket, ProviderError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.set_broadcast(true)?;
+        Ok(socket)
+    }
+
+    fn known_addr(&self, id: &LightId) -> Option<SocketAddr> {
+        self.devices.read().expect("Kasa device table poisoned").get(id).copied()
+    }
+
+    /// Looks up a device's address, running discovery once if it isn't known yet.
+    async fn addr_for(&self, id: &LightId) -> Result<SocketAddr, ProviderError> {
+        if let Some(addr) = self.known_addr(id) {
+            return Ok(addr);
+        }
+        if let Err(e) = self.discover().await {
+            tracing::debug!("Kasa rediscovery for {} failed: {}", id.0, e);
+        }
+        self.known_addr(id).ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    /// Sends one request to a device and waits for its reply.
+    async fn request(&self, addr: SocketAddr, payload: &str) -> Result<Response, ProviderError> {
+        let socket = self.bind_socket().await?;
+        socket.send_to(&encrypt(payload.as_bytes()), addr).await?;
+
+        let mut buf = [0u8; 4096];
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        loop {
+            let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await else {
+                return Err(ProviderError::Timeout(format!("no reply from Kasa device at {}", addr)));
+            };
+            let (len, from) = received?;
+            if from.ip() != addr.ip() {
+                continue;
+            }
+            return parse_response(&buf[..len])
+                .ok_or_else(|| ProviderError::Protocol(format!("unreadable reply from {}", addr)));
+        }
+    }
+
+    async fn transition(&self, id: &LightId, light_state: serde_json::Value) -> Result<(), ProviderError> {
+        let addr = self.addr_for(id).await?;
+        let payload = serde_json::json!({
+            "smartlife.iot.smartbulb.lightingservice": { "transition_light_state": light_state }
+        });
+        let response = self.request(addr, &payload.to_string()).await?;
+        let reply = response
+            .lighting
+            .ok_or_else(|| ProviderError::Protocol(format!("{} sent no lighting reply", id.0)))?
+            .transition_light_state;
+        if reply.err_code != 0 {
+            return Err(ProviderError::Protocol(format!(
+                "{} rejected transition: {}",
+                id.0,
+                reply.err_msg.unwrap_or_else(|| reply.err_code.to_string())
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KasaProvider {
+    fn default() -> Self {
+        Self::default_config()
+    }
+}
+
+#[async_trait]
+impl Provider for KasaProvider {
+    fn name(&self) -> &'static str {
+        "kasa"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: false, color: false, transition: false }
+    }
+
+    fn discovery_timeout(&self) -> Duration {
+        self.discovery_timeout + DISCOVERY_GRACE
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let socket = self.bind_socket().await?;
+        let destination = format!("{}:{}", self.broadcast_address, self.port);
+        socket.send_to(&encrypt(GET_SYSINFO.as_bytes()), &destination).await?;
+        tracing::debug!("Sent Kasa get_sysinfo to {}", destination);
+
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        let mut devices = HashMap::new();
+        let mut buf = [0u8; 4096];
+        let deadline = Instant::now() + self.discovery_timeout;
+
+        while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            let (len, from) = received?;
+            let Some(sysinfo) = parse_response(&buf[..len]).and_then(|r| r.system).map(|s| s.get_sysinfo) else {
+                continue;
+            };
+            let Some(bulb) = &sysinfo.light_state else {
+                tracing::debug!("Ignoring Kasa device {} at {}: not a bulb", sysinfo.alias, from);
+                continue;
+            };
+            let light = KasaLight::new(&sysinfo.device_id, from, sysinfo.alias.clone(), bulb.brightness(), bulb.on_off != 0);
+            if devices.insert(light.id().clone(), from).is_none() {
+                tracing::debug!("Kasa bulb {} answered from {}", light.id().0, from);
+                lights.push(Box::new(light));
+            }
+        }
+
+        if lights.is_empty() {
+            return Err(ProviderError::Timeout(format!(
+                "no Kasa bulbs answered on {} within {}ms",
+                destination,
+                self.discovery_timeout.as_millis()
+            )));
+        }
+
+        self.devices.write().expect("Kasa device table poisoned").extend(devices);
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let addr = self.addr_for(id).await?;
+        let sysinfo = self
+            .request(addr, GET_SYSINFO)
+            .await?
+            .system
+            .ok_or_else(|| ProviderError::Protocol(format!("{} sent no sysinfo", id.0)))?
+            .get_sysinfo;
+        let bulb = sysinfo
+            .light_state
+            .ok_or_else(|| ProviderError::Protocol(format!("{} is not a bulb", id.0)))?;
+        Ok(LightState::new(id.clone(), sysinfo.alias, bulb.brightness(), bulb.on_off != 0))
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        self.transition(id, serde_json::json!({
+            "brightness": brightness.as_percent(),
+            "on_off": 1,
+            "transition_period": 0,
+        }))
+        .await
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        self.transition(id, serde_json::json!({
+            "on_off": u8::from(on),
+            "transition_period": 0,
+        }))
+        .await
+    }
+
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        let socket = self.bind_socket().await?;
+        socket
+            .send_to(&encrypt(GET_SYSINFO.as_bytes()), format!("{}:{}", self.broadcast_address, self.port))
+            .await?;
+        Ok(())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "kasa",
+        enabled: |config| config.kasa.enabled,
+        build: |config| {
+            Ok(Box::new(KasaProvider::new(
+                config.kasa.discovery_timeout_ms,
+                config.kasa.broadcast_address.clone(),
+                config.kasa.port,
+            )))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor_round_trip() {
+        let cipher = encrypt(GET_SYSINFO.as_bytes());
+        assert_eq!(cipher[0], b'{' ^ XOR_KEY);
+        assert_eq!(decrypt(&cipher), GET_SYSINFO.as_bytes());
+    }
+
+    #[test]
+    fn test_parse_sysinfo() {
+        let reply = r#"{"system":{"get_sysinfo":{"alias":"Desk","deviceId":"80ABCDEF","mic_type":"IOT.SMARTBULB",
+            "light_state":{"on_off":1,"brightness":42},"err_code":0}}}"#;
+        let sysinfo = parse_response(&encrypt(reply.as_bytes())).unwrap().system.unwrap().get_sysinfo;
+        assert_eq!(sysinfo.alias, "Desk");
+        assert_eq!(light_id_for_device(&sysinfo.device_id).0, "kasa:80abcdef");
+        let bulb = sysinfo.light_state.unwrap();
+        assert_eq!(bulb.brightness().as_percent(), 42);
+        assert_eq!(bulb.on_off, 1);
+    }
+
+    #[test]
+    fn test_parse_sysinfo_while_off() {
+        let reply = r#"{"system":{"get_sysinfo":{"alias":"Desk","deviceId":"80ABCDEF",
+            "light_state":{"on_off":0,"dft_on_state":{"brightness":70}}}}}"#;
+        let bulb = parse_response(&encrypt(reply.as_bytes())).unwrap().system.unwrap().get_sysinfo.light_state.unwrap();
+        assert_eq!(bulb.brightness().as_percent(), 70);
+        assert_eq!(bulb.on_off, 0);
+    }
+
+    #[test]
+    fn test_parse_plug_has_no_light_state() {
+        let reply = r#"{"system":{"get_sysinfo":{"alias":"Fan","deviceId":"800011","relay_state":1}}}"#;
+        let sysinfo = parse_response(&encrypt(reply.as_bytes())).unwrap().system.unwrap().get_sysinfo;
+        assert!(sysinfo.light_state.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_light_not_found() {
+        let provider = KasaProvider::new(50, "127.0.0.1".to_string(), 9);
+        let result = provider.get_state(&LightId("kasa:unknown".to_string())).await;
+        assert!(matches!(result, Err(ProviderError::NotFound(_))));
+    }
+}
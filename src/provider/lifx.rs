@@ -1,20 +1,58 @@
-use super::types::{Light, LightState, LightId, Brightness, Provider};
+use super::types::{Capabilities, Color, Light, LightState, LightId, Brightness, OnFound, Provider, DISCOVERY_GRACE};
 use super::error::ProviderError;
 use async_trait::async_trait;
+use lifx_core::{BuildOptions, HSBK, LifxString, Message, RawMessage, Service, Waveform};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::{oneshot, OnceCell, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Source identifier stamped on every packet so bulbs reply unicast to us.
+const LIFX_SOURCE: u32 = 0x6c77_7277;
+/// Frame + frame address + protocol header.
+const HEADER_SIZE: usize = 36;
+/// The IPv6 link-local all-nodes group, sent discovery when IPv6 is enabled.
+const IPV6_ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
 
 #[derive(Debug)]
 pub struct LifxLight {
+    target: u64,
+    addr: SocketAddr,
     state: LightState,
+    /// Product, firmware, group, location and WiFi signal, as far as the
+    /// device reported them during discovery.
+    metadata: HashMap<String, String>,
 }
 
 impl LifxLight {
-    pub fn new(label: String, brightness: Brightness, power: bool) -> Self {
-        let id = LightId(format!("lifx:{}", label));
+    pub fn new(target: u64, addr: SocketAddr, label: String, brightness: Brightness, power: bool) -> Self {
+        let id = light_id_for_target(target);
         Self {
+            target,
+            addr,
             state: LightState::new(id, label, brightness, power),
+            metadata: HashMap::new(),
         }
     }
+
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
 }
 
 impl Light for LifxLight {
@@ -33,30 +71,524 @@ impl Light for LifxLight {
     fn state(&self) -> &LightState {
         &self.state
     }
+
+    fn metadata(&self) -> Option<&HashMap<String, String>> {
+        Some(&self.metadata)
+    }
+}
+
+/// Device details asked for alongside `LightGet` during discovery.
+fn metadata_queries() -> [Message; 5] {
+    [Message::GetVersion, Message::GetHostFirmware, Message::GetGroup, Message::GetLocation, Message::GetWifiInfo]
+}
+
+/// The metadata entries a reply to one of `metadata_queries` carries.
+fn metadata_entries(message: &Message) -> Option<Vec<(&'static str, String)>> {
+    let entries = match message {
+        Message::StateVersion { vendor, product, .. } => {
+            let mut entries = vec![("product_id", format!("{}:{}", vendor, product))];
+            if let Some(info) = lifx_core::get_product_info(*vendor, *product) {
+                entries.push(("product", info.name.to_string()));
+            }
+            entries
+        }
+        Message::StateHostFirmware { version_major, version_minor, .. } => {
+            vec![("firmware", format!("{}.{}", version_major, version_minor))]
+        }
+        Message::StateGroup { label, .. } => vec![("group", label.to_string())],
+        Message::StateLocation { label, .. } => vec![("location", label.to_string())],
+        // The signal is reported in milliwatts.
+        Message::StateWifiInfo { signal, .. } => {
+            vec![("wifi_signal_dbm", format!("{:.0}", 10.0 * signal.log10()))]
+        }
+        _ => return None,
+    };
+    Some(entries)
+}
+
+/// Builds a `LightId` from the device's MAC, e.g. `lifx:d073d5123456`.
+pub fn light_id_for_target(target: u64) -> LightId {
+    let mac = target.to_le_bytes()[..6]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    LightId(format!("lifx:{}", mac))
+}
+
+/// Recovers the frame target from a `LightId` produced by `light_id_for_target`.
+pub fn target_for_light_id(id: &LightId) -> Option<u64> {
+    let mac = id.0.strip_prefix("lifx:")?;
+    if mac.len() != 12 {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    for (i, byte) in bytes.iter_mut().take(6).enumerate() {
+        *byte = u8::from_str_radix(mac.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// LIFX spreads hue and saturation over the full `u16` range.
+fn hsbk_for_color(color: Color) -> HSBK {
+    HSBK {
+        hue: (color.hue as f32 / 360.0 * 65536.0).round() as u16,
+        saturation: (color.saturation as f32 / 100.0 * 65535.0).round() as u16,
+        brightness: color.brightness().as_u16(),
+        kelvin: 0,
+    }
 }
 
+fn color_for_hsbk(hsbk: &HSBK) -> Color {
+    Color::new(
+        (hsbk.hue as f32 / 65536.0 * 360.0).round() as u16,
+        (hsbk.saturation as f32 / 65535.0 * 100.0).round() as u8,
+        Brightness::from_u16(hsbk.brightness).as_percent(),
+    )
+}
+
+/// `host:port` for a configured host, adding `port` unless it has one.
+fn host_destination(host: &str, port: u16) -> String {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return SocketAddr::new(ip, port).to_string();
+    }
+    match host.rsplit_once(':') {
+        Some((_, host_port)) if host_port.parse::<u16>().is_ok() => host.to_string(),
+        _ => format!("{}:{}", host, port),
+    }
+}
+
+/// Where requests leave from, resolved from the configured interface when
+/// the provider is built.
+#[derive(Debug, Clone, PartialEq)]
+enum Egress {
+    /// A local address to bind.
+    Address(IpAddr),
+    /// A network interface that sockets are pinned to with
+    /// `SO_BINDTODEVICE`, so the routing table can't pick another.
+    Device(String),
+}
+
+impl Egress {
+    fn resolve(interface: &str) -> Result<Self, ProviderError> {
+        if let Ok(addr) = interface.parse() {
+            return Ok(Self::Address(addr));
+        }
+        if !Path::new("/sys/class/net").join(interface).exists() {
+            return Err(ProviderError::NotConfigured(format!("no network interface named {}", interface)));
+        }
+        Ok(Self::Device(interface.to_string()))
+    }
+}
+
+/// Binds a UDP socket to `source` with `SO_BINDTODEVICE` set for `device`.
+fn bind_to_device(device: &str, source: IpAddr) -> std::io::Result<UdpSocket> {
+    let addr = SocketAddr::new(source, 0);
+    let socket = socket2::Socket::new(socket2::Domain::for_address(addr), socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    socket.bind_device(Some(device.as_bytes()))?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    UdpSocket::from_std(socket.into())
+}
+
+fn build_packet(target: Option<u64>, message: Message, res_required: bool) -> Result<Vec<u8>, ProviderError> {
+    let options = BuildOptions {
+        target,
+        res_required,
+        source: LIFX_SOURCE,
+        ..Default::default()
+    };
+    RawMessage::build(&options, message)
+        .and_then(|raw| raw.pack())
+        .map_err(|e| ProviderError::Protocol(e.to_string()))
+}
+
+/// Builds a request to one device that asks for either a response or, when
+/// `res_required` is false, an acknowledgement, both echoing `sequence`.
+fn build_request(target: u64, message: Message, res_required: bool, sequence: u8) -> Result<Vec<u8>, ProviderError> {
+    let options = BuildOptions {
+        target: Some(target),
+        ack_required: !res_required,
+        res_required,
+        sequence,
+        source: LIFX_SOURCE,
+    };
+    RawMessage::build(&options, message)
+        .and_then(|raw| raw.pack())
+        .map_err(|e| ProviderError::Protocol(e.to_string()))
+}
+
+/// Decodes a datagram, skipping anything that isn't a well-formed LIFX reply to us.
+fn decode_packet(buf: &[u8]) -> Option<(u64, Message)> {
+    decode_sequenced(buf).map(|(target, _, message)| (target, message))
+}
+
+/// Like `decode_packet`, also returning the sequence number the reply echoes.
+fn decode_sequenced(buf: &[u8]) -> Option<(u64, u8, Message)> {
+    if buf.len() < HEADER_SIZE {
+        return None;
+    }
+    let size = u16::from_le_bytes([buf[0], buf[1]]) as usize;
+    let addressable = buf[3] & 0b0001_0000 != 0;
+    if size < HEADER_SIZE || size > buf.len() || !addressable {
+        return None;
+    }
+    let raw = RawMessage::unpack(buf).ok()?;
+    if raw.frame.source != LIFX_SOURCE {
+        return None;
+    }
+    let message = Message::from_raw(&raw).ok()?;
+    Some((raw.frame_addr.target, raw.frame_addr.sequence, message))
+}
+
+/// A request waiting for its reply, keyed by target and sequence number.
+#[derive(Debug)]
+struct Pending {
+    res_required: bool,
+    reply: oneshot::Sender<(SocketAddr, Message)>,
+}
+
+type PendingRequests = Arc<Mutex<HashMap<(u64, u8), Pending>>>;
+
+/// The socket requests go out on, and the task that reads every reply from
+/// it and hands each to the request waiting for it. Replies nobody is
+/// waiting for, like stragglers from requests that timed out, are dropped.
+#[derive(Debug)]
+struct Exchange {
+    socket: Arc<UdpSocket>,
+    pending: PendingRequests,
+    reader: JoinHandle<()>,
+}
+
+impl Exchange {
+    fn new(socket: UdpSocket) -> Self {
+        let socket = Arc::new(socket);
+        let pending = PendingRequests::default();
+        let reader = tokio::spawn(read_replies(socket.clone(), pending.clone()));
+        Self { socket, pending, reader }
+    }
+}
+
+impl Drop for Exchange {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+async fn read_replies(socket: Arc<UdpSocket>, pending: PendingRequests) {
+    let mut buf = [0u8; 1024];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::debug!("LIFX socket receive failed: {}", e);
+                continue;
+            }
+        };
+        let Some((target, sequence, reply)) = decode_sequenced(&buf[..len]) else {
+            continue;
+        };
+        let mut pending = pending.lock().expect("pending LIFX requests poisoned");
+        let Some(request) = pending.get(&(target, sequence)) else {
+            continue;
+        };
+        if request.res_required && matches!(reply, Message::Acknowledgement { .. }) {
+            continue;
+        }
+        if let Some(request) = pending.remove(&(target, sequence)) {
+            let _ = request.reply.send((from, reply));
+        }
+    }
+}
+
+/// Devices are addressed directly once known, over one socket that lives as
+/// long as the provider, so repeated commands skip the broadcast and the
+/// socket setup. Requests to different bulbs run side by side; replies are
+/// matched to them by target and sequence number.
 #[derive(Debug)]
 pub struct LifxProvider {
     discovery_timeout: Duration,
     broadcast_address: String,
     port: u16,
+    /// How long to wait for a device's reply or acknowledgement.
+    timeout: Duration,
+    /// Interface or local address that sockets are bound to.
+    egress: Option<Egress>,
+    /// Bulbs asked directly during discovery, as `host` or `host:port`.
+    hosts: Vec<String>,
+    broadcast: bool,
+    ipv6_multicast: bool,
+    /// Where each device last answered from, keyed by frame target.
+    devices: RwLock<HashMap<u64, SocketAddr>>,
+    exchange: OnceCell<Exchange>,
+    sequence: AtomicU8,
 }
 
 impl LifxProvider {
-    pub fn new(discovery_timeout_ms: u64, broadcast_address: String, port: u16) -> Self {
+    pub fn new(discovery_timeout_ms: u64, broadcast_address: String, port: u16, timeout_ms: u64) -> Self {
         Self {
             discovery_timeout: Duration::from_millis(discovery_timeout_ms),
             broadcast_address,
             port,
+            timeout: Duration::from_millis(timeout_ms),
+            egress: None,
+            hosts: Vec::new(),
+            broadcast: true,
+            ipv6_multicast: false,
+            devices: RwLock::new(HashMap::new()),
+            exchange: OnceCell::new(),
+            sequence: AtomicU8::new(0),
         }
     }
 
     pub fn default_config() -> Self {
-        Self {
-            discovery_timeout: Duration::from_millis(5000),
-            broadcast_address: "255.255.255.255".to_string(),
-            port: 56700,
+        Self::new(5000, "255.255.255.255".to_string(), 56700, 1000)
+    }
+
+    /// Sends from `interface`, given as a name like `wlan0` or a local
+    /// address, instead of whichever interface the routing table picks.
+    /// Fails if there is no interface by that name.
+    pub fn with_interface(mut self, interface: &str) -> Result<Self, ProviderError> {
+        self.egress = Some(Egress::resolve(interface)?);
+        Ok(self)
+    }
+
+    /// Also asks these bulbs directly during discovery, by IP or hostname
+    /// with an optional port.
+    pub fn with_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    /// Whether discovery broadcasts; without it only `hosts` are found.
+    pub fn with_broadcast(mut self, broadcast: bool) -> Self {
+        self.broadcast = broadcast;
+        self
+    }
+
+    /// Also sends discovery to the IPv6 all-nodes group, for networks that
+    /// drop IPv4 broadcast but pass multicast.
+    pub fn with_ipv6_multicast(mut self, ipv6_multicast: bool) -> Self {
+        self.ipv6_multicast = ipv6_multicast;
+        self
+    }
+
+    /// Where discovery sends `GetService`: the broadcast address (which may
+    /// also be an IPv6 multicast group), if enabled, the IPv6 all-nodes group
+    /// if enabled, and every configured host.
+    fn discovery_destinations(&self) -> Vec<String> {
+        let broadcast = self.broadcast.then(|| host_destination(&self.broadcast_address, self.port));
+        let multicast = self.ipv6_multicast.then(|| SocketAddr::new(IpAddr::V6(IPV6_ALL_NODES), self.port).to_string());
+        broadcast
+            .into_iter()
+            .chain(multicast)
+            .chain(self.hosts.iter().map(|host| host_destination(host, self.port)))
+            .collect()
+    }
+
+    /// Whether any discovery destination is an IPv6 address.
+    fn wants_ipv6(&self) -> bool {
+        self.discovery_destinations()
+            .iter()
+            .any(|destination| destination.parse::<SocketAddr>().is_ok_and(|addr| addr.is_ipv6()))
+    }
+
+    /// The addresses to try binding, in order: the configured address if
+    /// there is one, otherwise the IPv4 and IPv6 wildcards, IPv6 first when
+    /// a destination needs it. On Linux the IPv6 wildcard is dual-stack, so
+    /// it can still reach IPv4 bulbs.
+    fn source_addrs(&self) -> Vec<IpAddr> {
+        if let Some(Egress::Address(addr)) = &self.egress {
+            return vec![*addr];
+        }
+        let (v4, v6) = (IpAddr::V4(Ipv4Addr::UNSPECIFIED), IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        if self.wants_ipv6() { vec![v6, v4] } else { vec![v4, v6] }
+    }
+
+    /// Binds the first usable source address, failing with every address's
+    /// error if none can be bound.
+    async fn bind_socket(&self) -> Result<UdpSocket, ProviderError> {
+        let mut failures = Vec::new();
+        for source in self.source_addrs() {
+            let bound = match &self.egress {
+                Some(Egress::Device(device)) => bind_to_device(device, source),
+                _ => UdpSocket::bind(SocketAddr::new(source, 0)).await,
+            };
+            match bound {
+                Ok(socket) => {
+                    socket.set_broadcast(true)?;
+                    return Ok(socket);
+                }
+                Err(e) => failures.push(format!("{}: {}", source, e)),
+            }
+        }
+        Err(ProviderError::NotConfigured(format!("cannot send from {}", failures.join("; "))))
+    }
+
+    async fn exchange(&self) -> Result<&Exchange, ProviderError> {
+        self.exchange.get_or_try_init(|| async { Ok(Exchange::new(self.bind_socket().await?)) }).await
+    }
+
+    /// The device's last known address, or the broadcast address if it
+    /// hasn't been seen yet.
+    async fn addr_for(&self, target: u64) -> String {
+        match self.devices.read().await.get(&target) {
+            Some(addr) => addr.to_string(),
+            None => host_destination(&self.broadcast_address, self.port),
+        }
+    }
+
+    /// Sends `message` to one device and waits for the reply echoing its
+    /// sequence number: the response if `res_required`, else an
+    /// acknowledgement. A device that stays silent is forgotten, so the
+    /// next request goes out by broadcast in case it has moved.
+    async fn request(&self, target: u64, message: Message, res_required: bool) -> Result<Option<Message>, ProviderError> {
+        let exchange = self.exchange().await?;
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let packet = build_request(target, message, res_required, sequence)?;
+        let key = (target, sequence);
+        let (reply_tx, reply_rx) = oneshot::channel();
+        exchange
+            .pending
+            .lock()
+            .expect("pending LIFX requests poisoned")
+            .insert(key, Pending { res_required, reply: reply_tx });
+
+        let sent = exchange.socket.send_to(&packet, self.addr_for(target).await).await;
+        let reply = match sent {
+            Ok(_) => tokio::time::timeout(self.timeout, reply_rx).await.ok().and_then(Result::ok),
+            Err(_) => None,
+        };
+        exchange.pending.lock().expect("pending LIFX requests poisoned").remove(&key);
+        sent?;
+
+        if let Some((from, reply)) = reply {
+            self.devices.write().await.insert(target, from);
+            return Ok(match reply {
+                Message::Acknowledgement { .. } => None,
+                reply => Some(reply),
+            });
+        }
+        self.devices.write().await.remove(&target);
+        Err(ProviderError::Timeout(format!(
+            "{} did not answer within {}ms",
+            light_id_for_target(target).0,
+            self.timeout.as_millis()
+        )))
+    }
+
+    /// Sends a message to one device and waits for its acknowledgement.
+    async fn send_to_light(&self, id: &LightId, message: Message) -> Result<(), ProviderError> {
+        let target = target_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))?;
+        self.request(target, message, false).await.map(|_| ())
+    }
+
+    /// Broadcasts `GetService`, and sends it to each configured host, then
+    /// collects every device that answers within the timeout, telling
+    /// `found` of each as it does. A bulb that answers both ways is kept
+    /// once, by its MAC.
+    async fn find_devices(&self, socket: &UdpSocket, found: &OnFound<'_>) -> Result<HashMap<u64, SocketAddr>, ProviderError> {
+        let packet = build_packet(None, Message::GetService, true)?;
+        for destination in self.discovery_destinations() {
+            if let Err(e) = socket.send_to(&packet, &destination).await {
+                tracing::warn!("Failed to send LIFX GetService to {}: {}", destination, e);
+                continue;
+            }
+            tracing::debug!("Sent LIFX GetService to {}", destination);
         }
+
+        let mut devices = HashMap::new();
+        let mut buf = [0u8; 1024];
+        let deadline = Instant::now() + self.discovery_timeout;
+
+        while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            let (len, from) = received?;
+            if let Some((target, Message::StateService { service: Service::UDP, port })) = decode_packet(&buf[..len]) {
+                if port == 0 {
+                    continue;
+                }
+                // Replies to a dual-stack socket arrive as IPv4-mapped addresses.
+                let addr = SocketAddr::new(from.ip().to_canonical(), port as u16);
+                if devices.insert(target, addr).is_none() {
+                    let id = light_id_for_target(target);
+                    tracing::debug!("LIFX device {} answered from {}", id.0, addr);
+                    found(&id, None);
+                }
+            }
+        }
+
+        Ok(devices)
+    }
+
+    /// Asks every device for its `LightState` and metadata and turns the
+    /// replies into lights. Metadata a device doesn't send in time is left out.
+    async fn query_devices(
+        &self,
+        socket: &UdpSocket,
+        devices: &HashMap<u64, SocketAddr>,
+    ) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let mut outstanding: HashMap<u64, usize> = HashMap::new();
+        for (&target, addr) in devices {
+            let queries = metadata_queries();
+            outstanding.insert(target, queries.len() + 1);
+            for message in std::iter::once(Message::LightGet).chain(queries) {
+                let packet = build_packet(Some(target), message, true)?;
+                socket.send_to(&packet, addr).await?;
+            }
+        }
+
+        let mut lights: Vec<LifxLight> = Vec::new();
+        let mut metadata: HashMap<u64, HashMap<String, String>> = HashMap::new();
+        let mut pending: HashMap<u64, SocketAddr> = devices.clone();
+        let mut buf = [0u8; 1024];
+        let deadline = Instant::now() + self.timeout;
+
+        while !outstanding.is_empty() {
+            let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await else {
+                break;
+            };
+            let (len, _) = received?;
+            let Some((target, message)) = decode_packet(&buf[..len]) else {
+                continue;
+            };
+            if let Message::LightState { color, power, label, .. } = &message {
+                let Some(addr) = pending.remove(&target) else {
+                    continue;
+                };
+                lights.push(LifxLight::new(
+                    target,
+                    addr,
+                    label.to_string(),
+                    Brightness::from_u16(color.brightness),
+                    *power > 0,
+                ));
+            } else if let Some(entries) = metadata_entries(&message) {
+                let light_metadata = metadata.entry(target).or_default();
+                if light_metadata.contains_key(entries[0].0) {
+                    continue;
+                }
+                light_metadata.extend(entries.into_iter().map(|(key, value)| (key.to_string(), value)));
+            } else {
+                continue;
+            }
+            if let Some(remaining) = outstanding.get_mut(&target) {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    outstanding.remove(&target);
+                }
+            }
+        }
+
+        for target in pending.keys() {
+            tracing::warn!("LIFX device {} did not report its state", light_id_for_target(*target).0);
+        }
+
+        Ok(lights
+            .into_iter()
+            .map(|light| {
+                let light_metadata = metadata.remove(&light.target).unwrap_or_default();
+                Box::new(light.with_metadata(light_metadata)) as Box<dyn Light>
+            })
+            .collect())
     }
 }
 
@@ -72,29 +604,466 @@ impl Provider for LifxProvider {
         "lifx"
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: true }
+    }
+
+    fn discovery_timeout(&self) -> Duration {
+        self.discovery_timeout + DISCOVERY_GRACE
+    }
+
     async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
-        tracing::info!("LIFX discovery not yet implemented - returning stub lights");
+        self.discover_streaming(&|_, _| {}).await
+    }
+
+    /// Reports each bulb as its `StateService` reply arrives; labels only
+    /// come with the state queried once the listen window closes.
+    async fn discover_streaming(&self, found: &OnFound<'_>) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let socket = self.bind_socket().await?;
+        match (&self.egress, socket.local_addr()) {
+            (Some(Egress::Device(device)), Ok(local)) => tracing::info!("LIFX discovery sending from {} ({})", local, device),
+            (Some(Egress::Address(_)), Ok(local)) => tracing::info!("LIFX discovery sending from {}", local),
+            (None, Ok(local)) => tracing::debug!("LIFX discovery sending from {}", local),
+            (_, Err(e)) => tracing::debug!("LIFX discovery socket has no local address: {}", e),
+        }
+        let devices = self.find_devices(&socket, found).await?;
+
+        if devices.is_empty() {
+            return Err(ProviderError::Timeout(format!(
+                "no LIFX devices answered on {} within {}ms",
+                self.discovery_destinations().join(", "),
+                self.discovery_timeout.as_millis()
+            )));
+        }
 
-        Ok(vec![
-            Box::new(LifxLight::new("Stub Light 1".to_string(), Brightness::new(0.75), true)),
-            Box::new(LifxLight::new("Stub Light 2".to_string(), Brightness::new(0.5), true)),
-        ])
+        tracing::info!("Found {} LIFX device(s), querying state", devices.len());
+        self.devices.write().await.extend(devices.iter().map(|(&target, &addr)| (target, addr)));
+        self.query_devices(&socket, &devices).await
     }
 
+    /// Asks the device for its `LightState`. An id that isn't a LIFX MAC, or
+    /// one no device answers for even by broadcast, is `NotFound`; a known
+    /// device that misses a reply is a `Timeout`, and is looked up by
+    /// broadcast on the next request.
     async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
-        Ok(LightState::new(
-            id.clone(),
-            "LIFX Light".to_string(),
-            Brightness::new(0.5),
-            true,
-        ))
+        let target = target_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))?;
+        let known = self.devices.read().await.contains_key(&target);
+        match self.request(target, Message::LightGet, true).await {
+            Ok(Some(Message::LightState { color, power, label, .. })) => Ok(LightState::new(
+                id.clone(),
+                label.to_string(),
+                Brightness::from_u16(color.brightness),
+                power > 0,
+            )
+            .with_color(color_for_hsbk(&color))),
+            Ok(other) => Err(ProviderError::Protocol(format!("unexpected reply to LightGet: {:?}", other))),
+            Err(ProviderError::Timeout(_)) if !known => Err(ProviderError::NotFound(id.clone())),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        self.set_brightness_with_transition(id, brightness, Duration::ZERO).await
+    }
+
+    /// `SetColor` would also overwrite hue and kelvin, so this uses a
+    /// single non-transient saw cycle, which fades only the brightness over
+    /// its period.
+    async fn set_brightness_with_transition(
+        &self,
+        id: &LightId,
+        brightness: Brightness,
+        duration: Duration,
+    ) -> Result<(), ProviderError> {
+        let message = Message::SetWaveformOptional {
+            reserved: 0,
+            transient: false,
+            color: HSBK { hue: 0, saturation: 0, brightness: brightness.as_u16(), kelvin: 0 },
+            period: u32::try_from(duration.as_millis()).unwrap_or(u32::MAX),
+            cycles: 1.0,
+            skew_ratio: 0,
+            waveform: Waveform::Saw,
+            set_hue: false,
+            set_saturation: false,
+            set_brightness: true,
+            set_kelvin: false,
+        };
+        self.send_to_light(id, message).await
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        let level = if on { 65535 } else { 0 };
+        self.send_to_light(id, Message::LightSetPower { level, duration: 0 }).await
     }
 
-    async fn set_brightness(&self, _id: &LightId, _brightness: Brightness) -> Result<(), ProviderError> {
-        Ok(())
+    async fn set_kelvin(&self, id: &LightId, kelvin: u16) -> Result<(), ProviderError> {
+        // Only kelvin and saturation are applied, so brightness is left alone
+        // and a bulb showing a color drops back to white.
+        let message = Message::SetWaveformOptional {
+            reserved: 0,
+            transient: false,
+            color: HSBK { hue: 0, saturation: 0, brightness: 0, kelvin },
+            period: 0,
+            cycles: 1.0,
+            skew_ratio: 0,
+            waveform: Waveform::Saw,
+            set_hue: false,
+            set_saturation: true,
+            set_brightness: false,
+            set_kelvin: true,
+        };
+        self.send_to_light(id, message).await
     }
 
+    /// Sets hue, saturation and brightness together, leaving kelvin alone.
+    async fn set_color(&self, id: &LightId, color: Color) -> Result<(), ProviderError> {
+        let message = Message::SetWaveformOptional {
+            reserved: 0,
+            transient: false,
+            color: hsbk_for_color(color),
+            period: 0,
+            cycles: 1.0,
+            skew_ratio: 0,
+            waveform: Waveform::Saw,
+            set_hue: true,
+            set_saturation: true,
+            set_brightness: true,
+            set_kelvin: false,
+        };
+        self.send_to_light(id, message).await
+    }
+
+    /// Bulbs keep at most 32 bytes of label; longer ones are truncated.
+    async fn set_label(&self, id: &LightId, label: &str) -> Result<(), ProviderError> {
+        let label = std::ffi::CString::new(label)
+            .map_err(|_| ProviderError::Protocol("label contains a NUL byte".to_string()))?;
+        self.send_to_light(id, Message::SetLabel { label: LifxString::new(&label) }).await
+    }
+
+    /// Confirms a broadcast socket can be bound and a `GetService` sent,
+    /// without waiting for any device to answer.
     async fn health_check(&self) -> Result<(), ProviderError> {
-        Ok(())
+        let socket = self.bind_socket().await?;
+        let packet = build_packet(None, Message::GetService, true)?;
+        let mut last_error = None;
+        for destination in self.discovery_destinations() {
+            match socket.send_to(&packet, &destination).await {
+                Ok(_) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match last_error {
+            Some(e) => Err(e.into()),
+            None => Err(ProviderError::NotConfigured("no LIFX discovery destinations".to_string())),
+        }
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "lifx",
+        enabled: |_| true,
+        build: |config| {
+            let mut provider = LifxProvider::new(
+                config.lifx.discovery_timeout_ms,
+                config.lifx.broadcast_address.clone(),
+                config.lifx.port,
+                config.lifx.timeout_ms,
+            )
+            .with_hosts(config.lifx.hosts.clone())
+            .with_broadcast(config.lifx.broadcast)
+            .with_ipv6_multicast(config.lifx.ipv6_multicast);
+            if let Some(interface) = &config.lifx.interface {
+                provider = provider.with_interface(interface)?;
+            }
+            Ok(Box::new(provider))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lifx_core::LifxIdent;
+
+    #[test]
+    fn test_light_id_from_target() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x56, 0, 0]);
+        assert_eq!(light_id_for_target(target).0, "lifx:d073d5123456");
+    }
+
+    #[test]
+    fn test_target_round_trip() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x01, 0xab, 0xff, 0, 0]);
+        let id = light_id_for_target(target);
+        assert_eq!(target_for_light_id(&id), Some(target));
+    }
+
+    #[test]
+    fn test_target_rejects_foreign_ids() {
+        assert_eq!(target_for_light_id(&LightId("hue:abc".to_string())), None);
+        assert_eq!(target_for_light_id(&LightId("lifx:zz73d5123456".to_string())), None);
+        assert_eq!(target_for_light_id(&LightId("lifx:d073".to_string())), None);
+    }
+
+    #[test]
+    fn test_hsbk_color_round_trip() {
+        for color in [Color::new(0, 100, 100), Color::new(120, 50, 40), Color::new(359, 0, 1)] {
+            assert_eq!(color_for_hsbk(&hsbk_for_color(color)), color);
+        }
+        assert_eq!(hsbk_for_color(Color::new(180, 100, 100)).hue, 32768);
+    }
+
+    #[test]
+    fn test_host_destination() {
+        assert_eq!(host_destination("10.0.5.20", 56700), "10.0.5.20:56700");
+        assert_eq!(host_destination("10.0.5.20:56701", 56700), "10.0.5.20:56701");
+        assert_eq!(host_destination("bulb.lan", 56700), "bulb.lan:56700");
+        assert_eq!(host_destination("bulb.lan:56701", 56700), "bulb.lan:56701");
+        assert_eq!(host_destination("fe80::1", 56700), "[fe80::1]:56700");
+    }
+
+    #[tokio::test]
+    async fn test_discover_known_hosts_without_broadcast() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x58, 0, 0]);
+        let bulb = spawn_fake_bulb(target);
+        let provider = LifxProvider::new(200, "127.0.0.1".to_string(), 9, 100)
+            .with_hosts(vec![bulb.to_string(), format!("localhost:{}", bulb.port())])
+            .with_broadcast(false);
+        assert_eq!(provider.discovery_destinations(), vec![bulb.to_string(), format!("localhost:{}", bulb.port())]);
+
+        let lights = provider.discover().await.unwrap();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].id(), &light_id_for_target(target));
+    }
+
+    #[tokio::test]
+    async fn test_discover_streaming_reports_each_answer() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x5b, 0, 0]);
+        let bulb = spawn_fake_bulb(target);
+        let provider = LifxProvider::new(200, "127.0.0.1".to_string(), bulb.port(), 100);
+
+        let found = std::sync::Mutex::new(Vec::new());
+        let lights = provider
+            .discover_streaming(&|id, label| found.lock().unwrap().push((id.clone(), label.map(str::to_string))))
+            .await
+            .unwrap();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(found.into_inner().unwrap(), [(light_id_for_target(target), None)]);
+    }
+
+    #[tokio::test]
+    async fn test_discover_over_dual_stack_socket() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x59, 0, 0]);
+        let bulb = spawn_fake_bulb(target);
+        let provider = LifxProvider::new(200, "::1".to_string(), bulb.port(), 100)
+            .with_hosts(vec![bulb.to_string()])
+            .with_ipv6_multicast(true);
+        assert_eq!(
+            provider.discovery_destinations(),
+            vec![format!("[::1]:{}", bulb.port()), format!("[ff02::1]:{}", bulb.port()), bulb.to_string()]
+        );
+        assert!(provider.wants_ipv6());
+
+        // Falls back to IPv4 on hosts without IPv6; either way the bulb is
+        // recorded under its plain IPv4 address.
+        let lights = provider.discover().await.unwrap();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(provider.devices.read().await.get(&target), Some(&bulb));
+    }
+
+    #[tokio::test]
+    async fn test_bind_to_interface_address() {
+        let provider = LifxProvider::default_config().with_interface("127.0.0.1").unwrap();
+        let socket = provider.bind_socket().await.unwrap();
+        assert_eq!(socket.local_addr().unwrap().ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let missing = LifxProvider::default_config().with_interface("lightwire-missing0");
+        assert!(matches!(missing, Err(ProviderError::NotConfigured(_))));
+    }
+
+    #[tokio::test]
+    async fn test_bind_to_interface_device() {
+        let provider = LifxProvider::default_config().with_interface("lo").unwrap();
+        assert_eq!(provider.egress, Some(Egress::Device("lo".to_string())));
+        assert!(provider.bind_socket().await.is_ok());
+    }
+
+    #[test]
+    fn test_decode_ignores_garbage() {
+        assert!(decode_packet(&[0u8; 8]).is_none());
+        assert!(decode_packet(&[0xffu8; 64]).is_none());
+    }
+
+    #[test]
+    fn test_decode_state_service() {
+        let options = BuildOptions {
+            target: Some(42),
+            source: LIFX_SOURCE,
+            ..Default::default()
+        };
+        let raw = RawMessage::build(&options, Message::StateService { service: Service::UDP, port: 56700 }).unwrap();
+        let bytes = raw.pack().unwrap();
+
+        match decode_packet(&bytes) {
+            Some((42, Message::StateService { port, .. })) => assert_eq!(port, 56700),
+            other => panic!("unexpected decode result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_state_unknown_light_is_not_found() {
+        // A bound socket that never answers, so nothing replies to the query.
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let provider = LifxProvider::new(100, "127.0.0.1".to_string(), silent.local_addr().unwrap().port(), 100);
+
+        for id in ["hue:abc", "lifx:d073", "lifx:d073d5123456"] {
+            let id = LightId(id.to_string());
+            match provider.get_state(&id).await {
+                Err(ProviderError::NotFound(missing)) => assert_eq!(missing, id),
+                other => panic!("expected NotFound for {}, got {:?}", id.0, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_state_known_light_times_out() {
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let provider = LifxProvider::new(100, "127.0.0.1".to_string(), 9, 100);
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x5c, 0, 0]);
+        provider.devices.write().await.insert(target, silent.local_addr().unwrap());
+
+        let id = light_id_for_target(target);
+        assert!(matches!(provider.get_state(&id).await, Err(ProviderError::Timeout(_))));
+        // Forgotten after the miss, so the next attempt asks by broadcast.
+        assert!(!provider.devices.read().await.contains_key(&target));
+    }
+
+    /// Answers each request like a bulb would, after first sending a stale
+    /// reply that must be ignored.
+    fn spawn_fake_bulb(target: u64) -> SocketAddr {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                let request = RawMessage::unpack(&buf[..len]).unwrap();
+                let sequence = request.frame_addr.sequence;
+                let reply = match Message::from_raw(&request).unwrap() {
+                    Message::GetService => Message::StateService { service: Service::UDP, port: addr.port() as u32 },
+                    Message::LightGet => Message::LightState {
+                        color: HSBK { hue: 0, saturation: 0, brightness: 32768, kelvin: 3500 },
+                        reserved: 0,
+                        power: 65535,
+                        label: LifxString::new(c"Desk"),
+                        reserved2: 0,
+                    },
+                    Message::GetVersion => Message::StateVersion { vendor: 1, product: 1, reserved: 0 },
+                    Message::GetHostFirmware => {
+                        Message::StateHostFirmware { build: 0, reserved: 0, version_minor: 77, version_major: 3 }
+                    }
+                    Message::GetGroup => Message::StateGroup {
+                        group: LifxIdent([0; 16]),
+                        label: LifxString::new(c"Office"),
+                        updated_at: 0,
+                    },
+                    Message::GetLocation => Message::StateLocation {
+                        location: LifxIdent([0; 16]),
+                        label: LifxString::new(c"Home"),
+                        updated_at: 0,
+                    },
+                    Message::GetWifiInfo => Message::StateWifiInfo { signal: 0.000_01, reserved6: 0, reserved7: 0, reserved: 0 },
+                    _ => Message::Acknowledgement { seq: sequence },
+                };
+                for sequence in [sequence.wrapping_sub(1), sequence] {
+                    let options = BuildOptions { target: Some(target), sequence, source: LIFX_SOURCE, ..Default::default() };
+                    let bytes = RawMessage::build(&options, reply.clone()).unwrap().pack().unwrap();
+                    socket.send_to(&bytes, from).unwrap();
+                }
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_set_brightness_times_out_without_ack() {
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let provider = LifxProvider::new(100, "127.0.0.1".to_string(), silent.local_addr().unwrap().port(), 50);
+        let id = LightId("lifx:d073d5123456".to_string());
+
+        let started = Instant::now();
+        let result = provider.set_brightness(&id, Brightness::new(0.5)).await;
+        assert!(matches!(result, Err(ProviderError::Timeout(_))), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        let mut buf = [0u8; 1024];
+        let (len, _) = silent.recv_from(&mut buf).unwrap();
+        let request = RawMessage::unpack(&buf[..len]).unwrap();
+        assert!(request.frame_addr.ack_required);
+        assert!(!request.frame_addr.res_required);
+    }
+
+    #[tokio::test]
+    async fn test_requests_reuse_socket_and_remember_device() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x56, 0, 0]);
+        let bulb = spawn_fake_bulb(target);
+        let provider = LifxProvider::new(100, "127.0.0.1".to_string(), bulb.port(), 100);
+        let id = light_id_for_target(target);
+
+        let state = provider.get_state(&id).await.unwrap();
+        assert_eq!(state.label, "Desk");
+        assert_eq!(state.brightness, Brightness::from_u16(32768));
+        assert!(state.power);
+        assert_eq!(state.color, Some(Color::new(0, 0, 50)));
+        assert_eq!(provider.devices.read().await.get(&target), Some(&bulb));
+
+        let local = provider.exchange().await.unwrap().socket.local_addr().unwrap();
+        provider.set_brightness(&id, Brightness::new(0.25)).await.unwrap();
+        provider.set_power(&id, false).await.unwrap();
+        provider.set_label(&id, "Desk Lamp").await.unwrap();
+        assert_eq!(provider.exchange().await.unwrap().socket.local_addr().unwrap(), local);
+        assert_eq!(provider.sequence.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn test_silent_bulb_does_not_stall_others() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x5a, 0, 0]);
+        let bulb = spawn_fake_bulb(target);
+        let provider = LifxProvider::new(100, "127.0.0.1".to_string(), bulb.port(), 500);
+        // The fake bulb only answers for its own target, so this one stays silent.
+        let silent = LightId("lifx:d073d5ffffff".to_string());
+
+        let (silent_result, elapsed) = tokio::join!(provider.set_power(&silent, true), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let started = Instant::now();
+            provider.get_state(&light_id_for_target(target)).await.unwrap();
+            started.elapsed()
+        });
+        assert!(matches!(silent_result, Err(ProviderError::Timeout(_))), "{:?}", silent_result);
+        assert!(elapsed < Duration::from_millis(250), "waited {:?} behind the silent bulb", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_discovery_collects_metadata() {
+        let target = u64::from_le_bytes([0xd0, 0x73, 0xd5, 0x12, 0x34, 0x57, 0, 0]);
+        let bulb = spawn_fake_bulb(target);
+        let provider = LifxProvider::new(100, "127.0.0.1".to_string(), bulb.port(), 100);
+        let socket = provider.bind_socket().await.unwrap();
+
+        let lights = provider.query_devices(&socket, &HashMap::from([(target, bulb)])).await.unwrap();
+        assert_eq!(lights.len(), 1);
+        let metadata = lights[0].metadata().unwrap();
+        assert_eq!(metadata["product_id"], "1:1");
+        assert_eq!(metadata["product"], "LIFX Original 1000");
+        assert_eq!(metadata["firmware"], "3.77");
+        assert_eq!(metadata["group"], "Office");
+        assert_eq!(metadata["location"], "Home");
+        assert_eq!(metadata["wifi_signal_dbm"], "-50");
+    }
+
+    #[test]
+    fn test_metadata_entries_ignore_other_messages() {
+        assert_eq!(metadata_entries(&Message::GetService), None);
+        assert_eq!(metadata_entries(&Message::StateVersion { vendor: 99, product: 1, reserved: 0 }), Some(vec![("product_id", "99:1".to_string())]));
     }
 }
@@ -1,9 +1,52 @@
 pub mod types;
 pub mod error;
 pub mod registry;
+pub mod group;
+pub mod filter;
+#[cfg(feature = "lifx")]
 pub mod lifx;
+#[cfg(feature = "kasa")]
+pub mod kasa;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "wled")]
+pub mod wled;
+#[cfg(feature = "yeelight")]
+pub mod yeelight;
+#[cfg(feature = "esphome")]
+pub mod esphome;
+#[cfg(feature = "govee")]
+pub mod govee;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod factory;
+#[cfg(feature = "homeassistant")]
+pub mod homeassistant;
+#[cfg(feature = "sim")]
+pub mod sim;
 
-pub use types::{LightId, Brightness, LightState, Light, Provider};
+pub use types::{LightId, Brightness, Capabilities, Color, LightState, Light, OnFound, Provider, DEFAULT_DISCOVERY_TIMEOUT};
 pub use error::ProviderError;
-pub use registry::ProviderRegistry;
+pub use registry::{DiscoveryProgress, ProviderRegistry};
+pub use group::{GroupLight, LightGroup};
+pub use filter::LightFilter;
+#[cfg(feature = "lifx")]
 pub use lifx::LifxProvider;
+#[cfg(feature = "kasa")]
+pub use kasa::KasaProvider;
+#[cfg(feature = "mqtt")]
+pub use mqtt::MqttProvider;
+#[cfg(feature = "wled")]
+pub use wled::WledProvider;
+#[cfg(feature = "yeelight")]
+pub use yeelight::YeelightProvider;
+#[cfg(feature = "esphome")]
+pub use esphome::EsphomeProvider;
+#[cfg(feature = "govee")]
+pub use govee::GoveeProvider;
+#[cfg(feature = "http")]
+pub use http::HttpProvider;
+#[cfg(feature = "homeassistant")]
+pub use homeassistant::HomeAssistantProvider;
+#[cfg(feature = "sim")]
+pub use sim::SimProvider;
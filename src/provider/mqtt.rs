@@ -0,0 +1,560 @@
+use super::types::{Capabilities, Light, LightState, LightId, Brightness, Provider, DISCOVERY_GRACE};
+use super::error::ProviderError;
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Outgoing, Packet, Publish, QoS};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Zigbee2MQTT reports and accepts brightness on a 0-254 scale.
+const Z2M_BRIGHTNESS_MAX: f32 = 254.0;
+/// How long to wait for a single device's state or a publish acknowledgement.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(2000);
+/// State updates queued for a slow subscriber before the subscription waits.
+const SUBSCRIBE_BUFFER: usize = 64;
+/// How long a finished session waits for its DISCONNECT to go out.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Numbers the connections this process makes, keeping their client ids
+/// apart: a broker drops the existing client when another connects with the
+/// same id, so a shared id would have every command kick the subscription.
+static NEXT_CLIENT: AtomicU32 = AtomicU32::new(0);
+
+fn client_id() -> String {
+    format!("lightwire-{}-{}", std::process::id(), NEXT_CLIENT.fetch_add(1, Ordering::Relaxed))
+}
+
+#[derive(Debug)]
+pub struct MqttLight {
+    state: LightState,
+}
+
+impl MqttLight {
+    pub fn new(friendly_name: &str, brightness: Brightness, power: bool) -> Self {
+        Self {
+            state: LightState::new(light_id_for_name(friendly_name), friendly_name.to_string(), brightness, power),
+        }
+    }
+}
+
+impl Light for MqttLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "mqtt"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+/// Zigbee2MQTT addresses devices by friendly name, so the id carries it directly.
+pub fn light_id_for_name(friendly_name: &str) -> LightId {
+    LightId(format!("mqtt:{}", friendly_name))
+}
+
+pub fn name_for_light_id(id: &LightId) -> Option<&str> {
+    id.0.strip_prefix("mqtt:").filter(|name| !name.is_empty())
+}
+
+/// The device a `<base>/#` publish reports state for, if it is a state
+/// topic. Friendly names may contain `/`, so anything under the base is a
+/// device except the bridge's own topics and a device's `set`, `get` and
+/// `availability` subtopics.
+fn device_for_topic<'a>(topic: &'a str, base_topic: &str) -> Option<&'a str> {
+    let name = topic.strip_prefix(base_topic)?.strip_prefix('/')?;
+    if name.is_empty() || name == "bridge" || name.starts_with("bridge/") {
+        return None;
+    }
+    match name.rsplit_once('/') {
+        Some((_, "set" | "get" | "availability")) => None,
+        _ => Some(name),
+    }
+}
+
+/// An entry of the retained `<base>/bridge/devices` list.
+#[derive(Debug, Deserialize)]
+struct Device {
+    friendly_name: String,
+    #[serde(default)]
+    definition: Option<Definition>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Definition {
+    #[serde(default)]
+    exposes: Vec<Expose>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Expose {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+impl Device {
+    fn is_light(&self) -> bool {
+        self.definition
+            .as_ref()
+            .is_some_and(|definition| definition.exposes.iter().any(|expose| expose.kind == "light"))
+    }
+}
+
+/// A device's state as published on `<base>/<friendly_name>`.
+#[derive(Debug, Deserialize)]
+struct DeviceState {
+    brightness: Option<u8>,
+    state: Option<String>,
+}
+
+impl DeviceState {
+    fn brightness(&self) -> Brightness {
+        Brightness::new(self.brightness.unwrap_or(0) as f32 / Z2M_BRIGHTNESS_MAX)
+    }
+
+    fn power(&self) -> bool {
+        self.state.as_deref().is_some_and(|state| state.eq_ignore_ascii_case("ON"))
+    }
+}
+
+fn z2m_brightness(brightness: Brightness) -> u8 {
+    (brightness.as_f32() * Z2M_BRIGHTNESS_MAX).round() as u8
+}
+
+fn parse_devices(payload: &[u8]) -> Result<Vec<Device>, ProviderError> {
+    serde_json::from_slice(payload).map_err(|e| ProviderError::Protocol(format!("invalid bridge/devices payload: {}", e)))
+}
+
+fn parse_state(payload: &[u8]) -> Option<DeviceState> {
+    serde_json::from_slice(payload).ok()
+}
+
+fn connection_error(e: impl std::fmt::Display) -> ProviderError {
+    ProviderError::Network(std::io::Error::other(e.to_string()))
+}
+
+/// One short-lived broker connection. rumqttc only makes progress while its
+/// event loop is polled, so every wait goes through `next_event`.
+struct Session {
+    client: AsyncClient,
+    eventloop: EventLoop,
+}
+
+impl Session {
+    async fn next_event(&mut self, deadline: Instant) -> Result<Option<Event>, ProviderError> {
+        match tokio::time::timeout_at(deadline, self.eventloop.poll()).await {
+            Ok(Ok(event)) => Ok(Some(event)),
+            Ok(Err(e)) => Err(connection_error(e)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    async fn next_publish(&mut self, deadline: Instant) -> Result<Option<Publish>, ProviderError> {
+        while let Some(event) = self.next_event(deadline).await? {
+            if let Event::Incoming(Packet::Publish(publish)) = event {
+                return Ok(Some(publish));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn subscribe(&mut self, topic: &str) -> Result<(), ProviderError> {
+        self.client.subscribe(topic, QoS::AtLeastOnce).await.map_err(connection_error)
+    }
+
+    async fn publish(&mut self, topic: &str, payload: String) -> Result<(), ProviderError> {
+        self.client
+            .publish(topic, QoS::AtLeastOnce, false, payload)
+            .await
+            .map_err(connection_error)
+    }
+
+    /// Publishes and keeps polling until the broker acknowledges it.
+    async fn publish_confirmed(&mut self, topic: &str, payload: String) -> Result<(), ProviderError> {
+        self.publish(topic, payload).await?;
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        while let Some(event) = self.next_event(deadline).await? {
+            if let Event::Incoming(Packet::PubAck(_)) = event {
+                return Ok(());
+            }
+        }
+        Err(ProviderError::Timeout(format!("broker did not acknowledge publish to {}", topic)))
+    }
+
+    /// Sends DISCONNECT so the broker ends the session now rather than
+    /// waiting out the keep-alive.
+    async fn close(mut self) {
+        if self.client.try_disconnect().is_err() {
+            return;
+        }
+        let deadline = Instant::now() + DISCONNECT_TIMEOUT;
+        while let Ok(Some(event)) = self.next_event(deadline).await {
+            if let Event::Outgoing(Outgoing::Disconnect) = event {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttProvider {
+    host: String,
+    port: u16,
+    base_topic: String,
+    credentials: Option<(String, String)>,
+    discovery_timeout: Duration,
+}
+
+impl MqttProvider {
+    pub fn new(
+        host: String,
+        port: u16,
+        base_topic: String,
+        credentials: Option<(String, String)>,
+        discovery_timeout_ms: u64,
+    ) -> Self {
+        Self {
+            host,
+            port,
+            base_topic: base_topic.trim_end_matches('/').to_string(),
+            credentials,
+            discovery_timeout: Duration::from_millis(discovery_timeout_ms),
+        }
+    }
+
+    pub fn default_config() -> Self {
+        Self::new("localhost".to_string(), 1883, "zigbee2mqtt".to_string(), None, 5000)
+    }
+
+    fn connect(&self) -> Session {
+        let mut options = MqttOptions::new(client_id(), &self.host, self.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some((username, password)) = &self.credentials {
+            options.set_credentials(username, password);
+        }
+        let (client, eventloop) = AsyncClient::new(options, 16);
+        Session { client, eventloop }
+    }
+
+    fn topic(&self, suffix: &str) -> String {
+        format!("{}/{}", self.base_topic, suffix)
+    }
+
+    fn device_name<'a>(&self, id: &'a LightId) -> Result<&'a str, ProviderError> {
+        name_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    /// Reads the device list and each light's state on `session`.
+    async fn discover_in(&self, session: &mut Session) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let devices_topic = self.topic("bridge/devices");
+        session.subscribe(&devices_topic).await?;
+        let deadline = Instant::now() + self.discovery_timeout;
+
+        let devices = loop {
+            let Some(publish) = session.next_publish(deadline).await? else {
+                return Err(ProviderError::Timeout(format!(
+                    "no device list on {} within {}ms",
+                    devices_topic,
+                    self.discovery_timeout.as_millis()
+                )));
+            };
+            if publish.topic == devices_topic {
+                break parse_devices(&publish.payload)?;
+            }
+        };
+
+        let names: Vec<&str> = devices
+            .iter()
+            .filter(|device| device.is_light())
+            .map(|device| device.friendly_name.as_str())
+            .collect();
+        tracing::info!("Zigbee2MQTT reports {} light(s), querying state", names.len());
+        self.request_states(session, &names);
+
+        let mut states: HashMap<String, DeviceState> = HashMap::new();
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        while states.len() < names.len() {
+            let Some(publish) = session.next_publish(deadline).await? else {
+                break;
+            };
+            let Some(name) = publish.topic.strip_prefix(&format!("{}/", self.base_topic)) else {
+                continue;
+            };
+            if names.contains(&name) {
+                if let Some(state) = parse_state(&publish.payload) {
+                    states.insert(name.to_string(), state);
+                }
+            }
+        }
+
+        let lights = names
+            .iter()
+            .map(|name| -> Box<dyn Light> {
+                match states.get(*name) {
+                    Some(state) => Box::new(MqttLight::new(name, state.brightness(), state.power())),
+                    None => {
+                        tracing::warn!("Zigbee2MQTT light {} did not report its state", name);
+                        Box::new(MqttLight::new(name, Brightness::new(0.0), false))
+                    }
+                }
+            })
+            .collect();
+        Ok(lights)
+    }
+
+    /// Asks for one device's state on `session` and waits for it.
+    async fn query_state(&self, session: &mut Session, id: &LightId, name: &str) -> Result<LightState, ProviderError> {
+        self.request_states(session, &[name]);
+
+        let state_topic = self.topic(name);
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        while let Some(publish) = session.next_publish(deadline).await? {
+            if publish.topic != state_topic {
+                continue;
+            }
+            if let Some(state) = parse_state(&publish.payload) {
+                return Ok(LightState::new(id.clone(), name.to_string(), state.brightness(), state.power()));
+            }
+        }
+        Err(ProviderError::Timeout(format!("no state on {}", state_topic)))
+    }
+
+    /// Subscribes to each device's state topic and asks Zigbee2MQTT to
+    /// publish it, for devices whose state isn't retained. The requests are
+    /// queued from a separate task so the caller can keep polling the event
+    /// loop; otherwise a long device list would fill the request channel.
+    fn request_states(&self, session: &Session, names: &[&str]) {
+        let client = session.client.clone();
+        let topics: Vec<String> = names.iter().map(|name| self.topic(name)).collect();
+        tokio::spawn(async move {
+            for topic in topics {
+                let result = async {
+                    client.subscribe(topic.as_str(), QoS::AtLeastOnce).await?;
+                    client
+                        .publish(format!("{}/get", topic), QoS::AtLeastOnce, false, r#"{"state":"","brightness":""}"#)
+                        .await
+                }
+                .await;
+                if let Err(e) = result {
+                    tracing::debug!("Failed to request state on {}: {}", topic, e);
+                    break;
+                }
+            }
+        });
+    }
+}
+
+impl Default for MqttProvider {
+    fn default() -> Self {
+        Self::default_config()
+    }
+}
+
+#[async_trait]
+impl Provider for MqttProvider {
+    fn name(&self) -> &'static str {
+        "mqtt"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: false, color: false, transition: true }
+    }
+
+    fn discovery_timeout(&self) -> Duration {
+        self.discovery_timeout + DISCOVERY_GRACE
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let mut session = self.connect();
+        let result = self.discover_in(&mut session).await;
+        session.close().await;
+        result
+    }
+
+    /// Follows every device's state topic on a dedicated connection for as
+    /// long as the receiver is kept.
+    async fn subscribe(&self) -> Result<mpsc::Receiver<LightState>, ProviderError> {
+        let mut session = self.connect();
+        session.subscribe(&self.topic("#")).await?;
+        let base_topic = self.base_topic.clone();
+        let (tx, rx) = mpsc::channel(SUBSCRIBE_BUFFER);
+
+        tokio::spawn(async move {
+            loop {
+                let publish = match session.eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::Publish(publish))) => publish,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        // rumqttc reconnects on the next poll.
+                        tracing::warn!("Zigbee2MQTT subscription interrupted: {}", e);
+                        tokio::time::sleep(QUERY_TIMEOUT).await;
+                        continue;
+                    }
+                };
+                let Some(name) = device_for_topic(&publish.topic, &base_topic) else {
+                    continue;
+                };
+                let Some(state) = parse_state(&publish.payload) else {
+                    continue;
+                };
+                let state = LightState::new(light_id_for_name(name), name.to_string(), state.brightness(), state.power());
+                if tx.send(state).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let name = self.device_name(id)?;
+        let mut session = self.connect();
+        let result = self.query_state(&mut session, id, name).await;
+        session.close().await;
+        result
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let name = self.device_name(id)?;
+        let mut session = self.connect();
+        let payload = serde_json::json!({ "brightness": z2m_brightness(brightness) }).to_string();
+        let result = session.publish_confirmed(&self.topic(&format!("{}/set", name)), payload).await;
+        session.close().await;
+        result
+    }
+
+    async fn set_brightness_with_transition(
+        &self,
+        id: &LightId,
+        brightness: Brightness,
+        duration: Duration,
+    ) -> Result<(), ProviderError> {
+        let name = self.device_name(id)?;
+        let mut session = self.connect();
+        let payload = serde_json::json!({
+            "brightness": z2m_brightness(brightness),
+            "transition": duration.as_secs_f32(),
+        })
+        .to_string();
+        let result = session.publish_confirmed(&self.topic(&format!("{}/set", name)), payload).await;
+        session.close().await;
+        result
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        let name = self.device_name(id)?;
+        let mut session = self.connect();
+        let payload = serde_json::json!({ "state": if on { "ON" } else { "OFF" } }).to_string();
+        let result = session.publish_confirmed(&self.topic(&format!("{}/set", name)), payload).await;
+        session.close().await;
+        result
+    }
+
+    /// Confirms the broker accepts a connection.
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        let mut session = self.connect();
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let result = loop {
+            match session.next_event(deadline).await {
+                Ok(Some(Event::Incoming(Packet::ConnAck(_)))) => break Ok(()),
+                Ok(Some(_)) => continue,
+                Ok(None) => break Err(ProviderError::Timeout(format!("no CONNACK from {}:{}", self.host, self.port))),
+                Err(e) => break Err(e),
+            }
+        };
+        session.close().await;
+        result
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "mqtt",
+        enabled: |config| config.mqtt.enabled,
+        build: |config| {
+            Ok(Box::new(MqttProvider::new(
+                config.mqtt.host.clone(),
+                config.mqtt.port,
+                config.mqtt.base_topic.clone(),
+                config.mqtt.credentials(),
+                config.mqtt.discovery_timeout_ms,
+            )))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_id_round_trip() {
+        let id = light_id_for_name("living room/lamp");
+        assert_eq!(id.0, "mqtt:living room/lamp");
+        assert_eq!(name_for_light_id(&id), Some("living room/lamp"));
+        assert_eq!(name_for_light_id(&LightId("lifx:d073d5123456".to_string())), None);
+        assert_eq!(name_for_light_id(&LightId("mqtt:".to_string())), None);
+    }
+
+    #[test]
+    fn test_device_for_topic() {
+        assert_eq!(device_for_topic("zigbee2mqtt/desk", "zigbee2mqtt"), Some("desk"));
+        assert_eq!(device_for_topic("zigbee2mqtt/living room/lamp", "zigbee2mqtt"), Some("living room/lamp"));
+        assert_eq!(device_for_topic("zigbee2mqtt/living room/lamp/set", "zigbee2mqtt"), None);
+        assert_eq!(device_for_topic("zigbee2mqtt/desk/get", "zigbee2mqtt"), None);
+        assert_eq!(device_for_topic("zigbee2mqtt/desk/availability", "zigbee2mqtt"), None);
+        assert_eq!(device_for_topic("zigbee2mqtt/bridge", "zigbee2mqtt"), None);
+        assert_eq!(device_for_topic("zigbee2mqtt/bridge/devices", "zigbee2mqtt"), None);
+        assert_eq!(device_for_topic("zigbee2mqtt2/desk", "zigbee2mqtt"), None);
+    }
+
+    #[test]
+    fn test_client_ids_are_unique() {
+        let (first, second) = (client_id(), client_id());
+        assert_ne!(first, second);
+        assert!(first.starts_with(&format!("lightwire-{}-", std::process::id())));
+    }
+
+    #[test]
+    fn test_parse_devices_keeps_lights() {
+        let payload = br#"[
+            {"friendly_name": "Coordinator", "type": "Coordinator", "definition": null},
+            {"friendly_name": "desk", "definition": {"exposes": [
+                {"type": "light", "features": [{"name": "state"}, {"name": "brightness", "value_max": 254}]},
+                {"type": "numeric", "name": "linkquality"}
+            ]}},
+            {"friendly_name": "door", "definition": {"exposes": [{"type": "binary", "name": "contact"}]}}
+        ]"#;
+        let devices = parse_devices(payload).unwrap();
+        let lights: Vec<&str> = devices.iter().filter(|d| d.is_light()).map(|d| d.friendly_name.as_str()).collect();
+        assert_eq!(lights, vec!["desk"]);
+    }
+
+    #[test]
+    fn test_parse_state() {
+        let state = parse_state(br#"{"brightness": 127, "state": "ON", "linkquality": 90}"#).unwrap();
+        assert!((state.brightness().as_f32() - 0.5).abs() < 0.01);
+        assert!(state.power());
+
+        let state = parse_state(br#"{"state": "OFF"}"#).unwrap();
+        assert_eq!(state.brightness().as_f32(), 0.0);
+        assert!(!state.power());
+    }
+
+    #[test]
+    fn test_z2m_brightness() {
+        assert_eq!(z2m_brightness(Brightness::new(0.0)), 0);
+        assert_eq!(z2m_brightness(Brightness::new(0.5)), 127);
+        assert_eq!(z2m_brightness(Brightness::new(1.0)), 254);
+    }
+}
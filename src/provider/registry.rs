@@ -1,60 +1,518 @@
 use std::collections::HashMap;
-use super::types::{Light, LightId, Brightness, LightState, Provider};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use crate::curves::Curve;
+use crate::metrics;
+use super::types::{Capabilities, Color, Light, LightId, Brightness, LightState, Provider};
 use super::error::ProviderError as Error;
+use super::filter::LightFilter;
+use super::group::{self, LightGroup, GROUP_PROVIDER};
+
+/// How close the observed brightness must be for a reliable set to count as applied.
+const BRIGHTNESS_TOLERANCE: f32 = 0.01;
+/// First retry delay for reliable sets; doubles after every attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+/// Time between steps of a software brightness ramp.
+const RAMP_STEP: Duration = Duration::from_millis(50);
+
+/// What `discover_all_with_progress` reports as each provider runs. Lights
+/// are reported as their provider hears from them, which for some is only
+/// once it finishes; see `Provider::discover_streaming`. The label is left
+/// out when a device answers before it has said what it is called.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscoveryProgress {
+    Started { provider: String },
+    Found { provider: String, id: LightId, label: Option<String> },
+    Finished { provider: String, count: usize },
+    Failed { provider: String, error: String },
+}
 
 #[derive(Debug)]
 pub struct ProviderRegistry {
-    providers: HashMap<String, Box<dyn Provider>>,
+    providers: HashMap<String, Arc<dyn Provider>>,
+    groups: Vec<LightGroup>,
+}
+
+/// A command fanned out to every member of a group.
+#[derive(Clone, Copy)]
+enum GroupCommand {
+    Brightness(Brightness),
+    Transition(Brightness, Duration),
+    Power(bool),
+    Kelvin(u16),
+    Color(Color),
 }
 
 impl ProviderRegistry {
     pub fn new() -> Self {
-        Self { providers: HashMap::new() }
+        Self { providers: HashMap::new(), groups: Vec::new() }
+    }
+
+    /// Groups are presented by discovery as single lights under the `group`
+    /// provider, and commands to them are fanned out to their members.
+    pub fn set_groups(&mut self, groups: Vec<LightGroup>) {
+        self.groups = groups;
+    }
+
+    pub fn groups(&self) -> &[LightGroup] {
+        &self.groups
     }
 
-    pub fn register(&mut self, provider: Box<dyn Provider>) {
+    /// Adds a provider, refusing to shadow one already registered under the same name.
+    pub fn register(&mut self, provider: Box<dyn Provider>) -> Result<(), Error> {
         let name = provider.name().to_string();
         if self.providers.contains_key(&name) {
-            tracing::warn!("Provider '{}' already registered, replacing", name);
+            return Err(Error::AlreadyRegistered(name));
         }
-        self.providers.insert(name, provider);
+        self.providers.insert(name, Arc::from(provider));
+        Ok(())
+    }
+
+    /// Adds a provider, replacing any existing one with the same name.
+    pub fn register_or_replace(&mut self, provider: Box<dyn Provider>) {
+        let name = provider.name().to_string();
+        if self.providers.contains_key(&name) {
+            tracing::debug!("Replacing provider '{}'", name);
+        }
+        self.providers.insert(name, Arc::from(provider));
     }
 
     pub fn get(&self, name: &str) -> Option<&dyn Provider> {
         self.providers.get(name).map(|p| p.as_ref())
     }
 
+    /// What commands a light under `provider_name` accepts. A group supports
+    /// only what all of its members' providers do.
+    pub fn capabilities(&self, provider_name: &str, id: &LightId) -> Result<Capabilities, Error> {
+        if provider_name != GROUP_PROVIDER {
+            return self
+                .get(provider_name)
+                .map(|provider| provider.capabilities())
+                .ok_or_else(|| Error::NotConfigured(format!("Provider '{}' not found", provider_name)));
+        }
+        let all = Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: true };
+        self.group(id)?.members.iter().try_fold(all, |capabilities, member| {
+            Ok(capabilities.intersect(self.member_provider(member)?.capabilities()))
+        })
+    }
+
     pub async fn discover_all(&self) -> Result<Vec<Box<dyn Light>>, Error> {
-        let mut all_lights = Vec::new();
+        let (lights, _errors) = self.discover_all_detailed().await;
+        Ok(lights)
+    }
+
+    /// Like `discover_all`, keeping only the lights `filter` matches.
+    pub async fn discover_filtered(&self, filter: &LightFilter) -> Result<Vec<Box<dyn Light>>, Error> {
+        Ok(filter.retain(self.discover_all().await?))
+    }
+
+    /// Discovers from every provider, returning the lights found alongside
+    /// the name and error of each provider that failed. Each provider is
+    /// cut off after its `discovery_timeout`, so this always returns.
+    pub async fn discover_all_detailed(&self) -> (Vec<Box<dyn Light>>, Vec<(String, Error)>) {
+        self.discover_all_with_progress(None).await
+    }
+
+    /// Like `discover_all_detailed`, sending progress events to `progress`
+    /// as providers start, find lights, and finish or fail. Events are
+    /// dropped once the receiver is gone.
+    pub async fn discover_all_with_progress(
+        &self,
+        progress: Option<&mpsc::Sender<DiscoveryProgress>>,
+    ) -> (Vec<Box<dyn Light>>, Vec<(String, Error)>) {
+        let report = |event: DiscoveryProgress| async move {
+            if let Some(progress) = progress {
+                let _ = progress.send(event).await;
+            }
+        };
+
+        let (found_tx, mut found_rx) = mpsc::unbounded_channel();
+        let mut tasks = JoinSet::new();
+        let mut task_names = HashMap::new();
         for (name, provider) in &self.providers {
             tracing::info!("Discovering lights from provider: {}", name);
-            match provider.discover().await {
-                Ok(lights) => {
+            report(DiscoveryProgress::Started { provider: name.clone() }).await;
+            let provider = Arc::clone(provider);
+            let task_name = name.clone();
+            let found_tx = found_tx.clone();
+            let found_name = name.clone();
+            let handle = tasks.spawn(async move {
+                let found = move |id: &LightId, label: Option<&str>| {
+                    let _ = found_tx.send(DiscoveryProgress::Found {
+                        provider: found_name.clone(),
+                        id: id.clone(),
+                        label: label.map(str::to_string),
+                    });
+                };
+                let timeout = provider.discovery_timeout();
+                let result = match tokio::time::timeout(timeout, provider.discover_streaming(&found)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(Error::Timeout(format!("discovery took longer than {}ms", timeout.as_millis()))),
+                };
+                (task_name, result)
+            });
+            task_names.insert(handle.id(), name.as_str());
+        }
+        drop(found_tx);
+
+        let mut all_lights = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            let joined = tokio::select! {
+                Some(found) = found_rx.recv() => {
+                    report(found).await;
+                    continue;
+                }
+                joined = tasks.join_next() => joined,
+            };
+            let Some(joined) = joined else {
+                break;
+            };
+            // Whatever the finished provider found comes before its result.
+            while let Ok(found) = found_rx.try_recv() {
+                report(found).await;
+            }
+            match joined {
+                Ok((name, Ok(lights))) => {
                     tracing::info!("Found {} lights from {}", lights.len(), name);
+                    report(DiscoveryProgress::Finished { provider: name, count: lights.len() }).await;
                     all_lights.extend(lights);
                 }
-                Err(e) => {
+                Ok((name, Err(e))) => {
                     tracing::error!("Failed to discover from {}: {}", name, e);
+                    report(DiscoveryProgress::Failed { provider: name.clone(), error: e.to_string() }).await;
+                    errors.push((name, e));
+                }
+                Err(e) => {
+                    let name = task_names.get(&e.id()).copied().unwrap_or("unknown");
+                    tracing::error!("Discovery task for {} failed: {}", name, e);
+                    report(DiscoveryProgress::Failed { provider: name.to_string(), error: e.to_string() }).await;
+                    errors.push((name.to_string(), Error::DiscoveryFailed(e.to_string())));
                 }
             }
         }
-        Ok(all_lights)
+        (group::apply_groups(all_lights, &self.groups), errors)
+    }
+
+    /// Runs every provider's health check concurrently, keyed by provider name.
+    pub async fn health_check_all(&self) -> HashMap<String, Result<(), Error>> {
+        let mut tasks = JoinSet::new();
+        let mut task_names = HashMap::new();
+        for (name, provider) in &self.providers {
+            let provider = Arc::clone(provider);
+            let task_name = name.clone();
+            let handle = tasks.spawn(async move { (task_name, provider.health_check().await) });
+            task_names.insert(handle.id(), name.as_str());
+        }
+
+        let mut results = HashMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((name, result)) => {
+                    results.insert(name, result);
+                }
+                Err(e) => {
+                    let name = task_names.get(&e.id()).copied().unwrap_or("unknown");
+                    results.insert(name.to_string(), Err(Error::Protocol(format!("health check panicked: {}", e))));
+                }
+            }
+        }
+        results
     }
 
     pub async fn get_state(&self, provider_name: &str, id: &LightId) -> Result<LightState, Error> {
+        let result = if provider_name == GROUP_PROVIDER {
+            self.group_state(id).await
+        } else {
+            match self.get(provider_name) {
+                Some(provider) => provider.get_state(id).await,
+                None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
+            }
+        };
+        if let Ok(state) = &result {
+            metrics::brightness(provider_name, id, state.brightness);
+        }
+        result
+    }
+
+    /// Reads the light's state along with the volume `curve` maps to its
+    /// brightness.
+    pub async fn get_state_curved(
+        &self,
+        provider_name: &str,
+        id: &LightId,
+        curve: &dyn Curve,
+    ) -> Result<(LightState, f32), Error> {
+        let state = self.get_state(provider_name, id).await?;
+        let volume = curve.inverse(state.brightness.as_f32());
+        Ok((state, volume))
+    }
+
+    /// Sets the brightness `curve` maps `volume` to, returning it.
+    pub async fn set_brightness_curved(
+        &self,
+        provider_name: &str,
+        id: &LightId,
+        volume: f32,
+        curve: &dyn Curve,
+    ) -> Result<Brightness, Error> {
+        let brightness = Brightness::new(curve.apply(volume));
+        self.set_brightness(provider_name, id, brightness).await?;
+        Ok(brightness)
+    }
+
+    pub async fn set_brightness(&self, provider_name: &str, id: &LightId, brightness: Brightness) -> Result<(), Error> {
+        let result = if provider_name == GROUP_PROVIDER {
+            self.group_command(id, GroupCommand::Brightness(brightness)).await
+        } else {
+            match self.get(provider_name) {
+                Some(provider) => provider.set_brightness(id, brightness).await,
+                None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
+            }
+        };
+        metrics::command(provider_name, "set_brightness", &result);
+        if result.is_ok() {
+            metrics::brightness(provider_name, id, brightness);
+        }
+        result
+    }
+
+    /// Sets the brightness unless the light already reports one within
+    /// `tolerance` of it, returning whether a command was sent. If the state
+    /// can't be read the command is sent anyway.
+    pub async fn set_brightness_if_changed(
+        &self,
+        provider_name: &str,
+        id: &LightId,
+        brightness: Brightness,
+        tolerance: f32,
+    ) -> Result<bool, Error> {
+        match self.get_state(provider_name, id).await {
+            Ok(state) if state.brightness.approx_eq(&brightness, tolerance) => {
+                tracing::trace!("{} is already at {:.2}; not sending {:.2}", id.0, state.brightness.as_f32(), brightness.as_f32());
+                return Ok(false);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("Failed to read {} before setting its brightness: {}", id.0, e),
+        }
+        self.set_brightness(provider_name, id, brightness).await?;
+        Ok(true)
+    }
+
+    pub async fn set_brightness_with_transition(
+        &self,
+        provider_name: &str,
+        id: &LightId,
+        brightness: Brightness,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let result = if provider_name == GROUP_PROVIDER {
+            self.group_command(id, GroupCommand::Transition(brightness, duration)).await
+        } else {
+            match self.get(provider_name) {
+                Some(provider) => provider.set_brightness_with_transition(id, brightness, duration).await,
+                None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
+            }
+        };
+        metrics::command(provider_name, "set_brightness", &result);
+        if result.is_ok() {
+            metrics::brightness(provider_name, id, brightness);
+        }
+        result
+    }
+
+    /// Fades from `from` to `to` in software, one `set_brightness` per step,
+    /// for lights that can't fade on their own; see `Brightness::ramp`.
+    pub async fn ramp_brightness(
+        &self,
+        provider_name: &str,
+        id: &LightId,
+        from: Brightness,
+        to: Brightness,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let steps = (duration.as_millis() / RAMP_STEP.as_millis()).max(1) as u32;
+        for (step, brightness) in Brightness::ramp(from, to, steps).enumerate() {
+            if step > 0 {
+                tokio::time::sleep(RAMP_STEP).await;
+            }
+            self.set_brightness(provider_name, id, brightness).await?;
+        }
+        Ok(())
+    }
+
+    /// State changes pushed by a provider; see `Provider::subscribe`. Groups
+    /// have no state of their own to push.
+    pub async fn subscribe(&self, provider_name: &str) -> Result<tokio::sync::mpsc::Receiver<LightState>, Error> {
+        if provider_name == GROUP_PROVIDER {
+            return Err(Error::Protocol("unsupported".to_string()));
+        }
         match self.get(provider_name) {
-            Some(provider) => provider.get_state(id).await,
+            Some(provider) => provider.subscribe().await,
             None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
         }
     }
 
-    pub async fn set_brightness(&self, provider_name: &str, id: &LightId, brightness: Brightness) -> Result<(), Error> {
+    /// Sets brightness and reads it back, retrying with exponential backoff
+    /// until the light reports the requested level. Useful for providers whose
+    /// commands can be silently dropped.
+    pub async fn set_brightness_reliable(
+        &self,
+        provider_name: &str,
+        id: &LightId,
+        brightness: Brightness,
+        retries: u32,
+    ) -> Result<(), Error> {
+        let mut delay = RETRY_BASE_DELAY;
+        let mut attempt = 0;
+        loop {
+            let error = match self.set_brightness(provider_name, id, brightness).await {
+                Ok(()) => match self.get_state(provider_name, id).await {
+                    Ok(state) if state.brightness.approx_eq(&brightness, BRIGHTNESS_TOLERANCE) => {
+                        return Ok(());
+                    }
+                    Ok(state) => Error::SetBrightnessFailed(format!(
+                        "{} reports {:.2}, expected {:.2}",
+                        id.0,
+                        state.brightness.as_f32(),
+                        brightness.as_f32()
+                    )),
+                    Err(e) => e,
+                },
+                Err(e @ Error::NotConfigured(_)) => return Err(e),
+                Err(e) => e,
+            };
+
+            if attempt >= retries {
+                return Err(error);
+            }
+            attempt += 1;
+            tracing::debug!("Retrying brightness of {} in {:?} ({}/{}): {}", id.0, delay, attempt, retries, error);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    pub async fn set_power(&self, provider_name: &str, id: &LightId, on: bool) -> Result<(), Error> {
+        let result = if provider_name == GROUP_PROVIDER {
+            self.group_command(id, GroupCommand::Power(on)).await
+        } else {
+            match self.get(provider_name) {
+                Some(provider) => provider.set_power(id, on).await,
+                None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
+            }
+        };
+        metrics::command(provider_name, "set_power", &result);
+        result
+    }
+
+    pub async fn set_kelvin(&self, provider_name: &str, id: &LightId, kelvin: u16) -> Result<(), Error> {
+        let result = if provider_name == GROUP_PROVIDER {
+            self.group_command(id, GroupCommand::Kelvin(kelvin)).await
+        } else {
+            match self.get(provider_name) {
+                Some(provider) => provider.set_kelvin(id, kelvin).await,
+                None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
+            }
+        };
+        metrics::command(provider_name, "set_kelvin", &result);
+        result
+    }
+
+    pub async fn set_color(&self, provider_name: &str, id: &LightId, color: Color) -> Result<(), Error> {
+        let result = if provider_name == GROUP_PROVIDER {
+            self.group_command(id, GroupCommand::Color(color)).await
+        } else {
+            match self.get(provider_name) {
+                Some(provider) => provider.set_color(id, color).await,
+                None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
+            }
+        };
+        metrics::command(provider_name, "set_color", &result);
+        result
+    }
+
+    /// Groups have no label of their own to set.
+    pub async fn set_label(&self, provider_name: &str, id: &LightId, label: &str) -> Result<(), Error> {
+        if provider_name == GROUP_PROVIDER {
+            return Err(Error::Protocol("unsupported".to_string()));
+        }
         match self.get(provider_name) {
-            Some(provider) => provider.set_brightness(id, brightness).await,
+            Some(provider) => provider.set_label(id, label).await,
             None => Err(Error::NotConfigured(format!("Provider '{}' not found", provider_name))),
         }
     }
 
+    fn group(&self, id: &LightId) -> Result<&LightGroup, Error> {
+        group::group_name(id)
+            .and_then(|name| self.groups.iter().find(|group| group.name == name))
+            .ok_or_else(|| Error::NotFound(id.clone()))
+    }
+
+    /// The provider owning a group member, chosen by the member's id prefix.
+    fn member_provider(&self, member: &LightId) -> Result<&dyn Provider, Error> {
+        let provider_name = member.provider().ok_or_else(|| Error::NotFound(member.clone()))?;
+        self.get(provider_name)
+            .ok_or_else(|| Error::NotConfigured(format!("Provider '{}' not found", provider_name)))
+    }
+
+    /// Averages the brightness of the members that answer.
+    async fn group_state(&self, id: &LightId) -> Result<LightState, Error> {
+        let group = self.group(id)?;
+        let mut states = Vec::new();
+        let mut first_error = None;
+        for member in &group.members {
+            let result = match self.member_provider(member) {
+                Ok(provider) => provider.get_state(member).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(state) => states.push(state),
+                Err(e) => {
+                    tracing::warn!("Failed to read {} in group {}: {}", member.0, group.name, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if states.is_empty() {
+            return Err(first_error.unwrap_or_else(|| Error::NotFound(id.clone())));
+        }
+        Ok(LightState::new(
+            id.clone(),
+            group.name.clone(),
+            group::average_brightness(states.iter().map(|state| state.brightness)),
+            states.iter().any(|state| state.power),
+        ))
+    }
+
+    /// Sends the command to every member, returning the first failure after
+    /// all have been tried.
+    async fn group_command(&self, id: &LightId, command: GroupCommand) -> Result<(), Error> {
+        let group = self.group(id)?;
+        let mut first_error = None;
+        for member in &group.members {
+            let result = match self.member_provider(member) {
+                Ok(provider) => match command {
+                    GroupCommand::Brightness(brightness) => provider.set_brightness(member, brightness).await,
+                    GroupCommand::Transition(brightness, duration) => {
+                        provider.set_brightness_with_transition(member, brightness, duration).await
+                    }
+                    GroupCommand::Power(on) => provider.set_power(member, on).await,
+                    GroupCommand::Kelvin(kelvin) => provider.set_kelvin(member, kelvin).await,
+                    GroupCommand::Color(color) => provider.set_color(member, color).await,
+                },
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to update {} in group {}: {}", member.0, group.name, e);
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
     pub fn provider_names(&self) -> Vec<&str> {
         self.providers.keys().map(|s| s.as_str()).collect()
     }
@@ -77,7 +535,7 @@ impl Default for ProviderRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::provider::types::{Light, LightState, Brightness, LightId};
+    use crate::provider::types::{Light, LightState, Brightness, LightId, OnFound};
     use crate::provider::error::ProviderError;
     use async_trait::async_trait;
 
@@ -122,6 +580,131 @@ mod tests {
         name: &'static str,
     }
 
+    #[derive(Debug)]
+    struct FailingProvider;
+
+    #[async_trait]
+    impl Provider for FailingProvider {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+            Err(ProviderError::Timeout("no reply".to_string()))
+        }
+
+        async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+            Err(ProviderError::NotFound(id.clone()))
+        }
+
+        async fn set_brightness(&self, id: &LightId, _brightness: Brightness) -> Result<(), ProviderError> {
+            Err(ProviderError::NotFound(id.clone()))
+        }
+
+        async fn health_check(&self) -> Result<(), ProviderError> {
+            Err(ProviderError::Protocol("unreachable".to_string()))
+        }
+    }
+
+    /// Reports a light straight away, then keeps listening for a while.
+    #[derive(Debug)]
+    struct StreamingProvider;
+
+    #[async_trait]
+    impl Provider for StreamingProvider {
+        fn name(&self) -> &'static str {
+            "streaming"
+        }
+
+        async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+            self.discover_streaming(&|_, _| {}).await
+        }
+
+        async fn discover_streaming(&self, found: &OnFound<'_>) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+            found(&LightId("streaming:1".to_string()), None);
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok(vec![Box::new(MockLight::new("streaming:1", "Early", 0.5))])
+        }
+
+        async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+            Err(ProviderError::NotFound(id.clone()))
+        }
+
+        async fn set_brightness(&self, id: &LightId, _brightness: Brightness) -> Result<(), ProviderError> {
+            Err(ProviderError::NotFound(id.clone()))
+        }
+    }
+
+    /// Never finishes discovery.
+    #[derive(Debug)]
+    struct HangingProvider;
+
+    #[async_trait]
+    impl Provider for HangingProvider {
+        fn name(&self) -> &'static str {
+            "hanging"
+        }
+
+        fn discovery_timeout(&self) -> Duration {
+            Duration::from_millis(20)
+        }
+
+        async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+            std::future::pending().await
+        }
+
+        async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+            Err(ProviderError::NotFound(id.clone()))
+        }
+
+        async fn set_brightness(&self, id: &LightId, _brightness: Brightness) -> Result<(), ProviderError> {
+            Err(ProviderError::NotFound(id.clone()))
+        }
+    }
+
+    /// Drops the first `drops` brightness commands, like a lossy UDP link.
+    #[derive(Debug)]
+    struct FlakyProvider {
+        drops: std::sync::atomic::AtomicU32,
+        brightness: std::sync::Mutex<f32>,
+    }
+
+    impl FlakyProvider {
+        fn new(drops: u32) -> Self {
+            Self { drops: drops.into(), brightness: std::sync::Mutex::new(0.0) }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for FlakyProvider {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities { brightness: true, power: true, kelvin: false, color: false, transition: false }
+        }
+
+        async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+            Ok(Vec::new())
+        }
+
+        async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+            let brightness = *self.brightness.lock().unwrap();
+            Ok(LightState::new(id.clone(), "Flaky".to_string(), Brightness::new(brightness), true))
+        }
+
+        async fn set_brightness(&self, _id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+            use std::sync::atomic::Ordering;
+            if self.drops.load(Ordering::SeqCst) > 0 {
+                self.drops.fetch_sub(1, Ordering::SeqCst);
+            } else {
+                *self.brightness.lock().unwrap() = brightness.as_f32();
+            }
+            Ok(())
+        }
+    }
+
     #[async_trait]
     impl Provider for MockProvider {
         fn name(&self) -> &'static str {
@@ -161,7 +744,7 @@ mod tests {
         let mut registry = ProviderRegistry::new();
         let provider = Box::new(MockProvider { name: "test" });
 
-        registry.register(provider);
+        registry.register(provider).unwrap();
         assert_eq!(registry.count(), 1);
         assert!(registry.get("test").is_some());
     }
@@ -169,17 +752,27 @@ mod tests {
     #[tokio::test]
     async fn test_registry_register_replace() {
         let mut registry = ProviderRegistry::new();
-        registry.register(Box::new(MockProvider { name: "test" }));
-        registry.register(Box::new(MockProvider { name: "test" }));
+        registry.register_or_replace(Box::new(MockProvider { name: "test" }));
+        registry.register_or_replace(Box::new(MockProvider { name: "test" }));
 
         assert_eq!(registry.count(), 1);
     }
 
+    #[tokio::test]
+    async fn test_registry_register_duplicate() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "test" })).unwrap();
+
+        let result = registry.register(Box::new(MockProvider { name: "test" }));
+        assert!(matches!(result, Err(ProviderError::AlreadyRegistered(name)) if name == "test"));
+        assert_eq!(registry.count(), 1);
+    }
+
     #[tokio::test]
     async fn test_registry_provider_names() {
         let mut registry = ProviderRegistry::new();
-        registry.register(Box::new(MockProvider { name: "lifx" }));
-        registry.register(Box::new(MockProvider { name: "hue" }));
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(MockProvider { name: "hue" })).unwrap();
 
         let names = registry.provider_names();
         assert_eq!(names.len(), 2);
@@ -190,17 +783,122 @@ mod tests {
     #[tokio::test]
     async fn test_registry_discover_all() {
         let mut registry = ProviderRegistry::new();
-        registry.register(Box::new(MockProvider { name: "lifx" }));
-        registry.register(Box::new(MockProvider { name: "hue" }));
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(MockProvider { name: "hue" })).unwrap();
 
         let lights = registry.discover_all().await.unwrap();
         assert_eq!(lights.len(), 4); // 2 per provider
     }
 
+    #[tokio::test]
+    async fn test_registry_discover_all_with_progress() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(FailingProvider)).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let (lights, errors) = registry.discover_all_with_progress(Some(&tx)).await;
+        drop(tx);
+        assert_eq!((lights.len(), errors.len()), (2, 1));
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 6);
+        assert!(events[..2].iter().all(|event| matches!(event, DiscoveryProgress::Started { .. })));
+        assert!(events.contains(&DiscoveryProgress::Found {
+            provider: "lifx".to_string(),
+            id: LightId("id2".to_string()),
+            label: Some("Light 2".to_string()),
+        }));
+        assert!(events.contains(&DiscoveryProgress::Finished { provider: "lifx".to_string(), count: 2 }));
+        assert!(events.iter().any(|event| matches!(event, DiscoveryProgress::Failed { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_registry_discover_reports_lights_as_they_answer() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(StreamingProvider)).unwrap();
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let discovery = registry.discover_all_with_progress(Some(&tx));
+        let early = async {
+            assert!(matches!(rx.recv().await, Some(DiscoveryProgress::Started { .. })));
+            tokio::time::timeout(Duration::from_millis(200), rx.recv()).await
+        };
+        let ((lights, _), early) = tokio::join!(discovery, early);
+        assert_eq!(lights.len(), 1);
+        assert_eq!(
+            early.expect("light was only reported once discovery finished"),
+            Some(DiscoveryProgress::Found { provider: "streaming".to_string(), id: LightId("streaming:1".to_string()), label: None })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registry_discover_filtered() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+
+        let lights = registry.discover_filtered(&"Light 2".parse().unwrap()).await.unwrap();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].id().0, "id2");
+        let lights = registry.discover_filtered(&"provider=kasa".parse().unwrap()).await.unwrap();
+        assert!(lights.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_registry_discover_all_detailed() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(FailingProvider)).unwrap();
+
+        let (lights, errors) = registry.discover_all_detailed().await;
+        assert_eq!(lights.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "failing");
+        assert!(matches!(errors[0].1, ProviderError::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_registry_discover_all_times_out() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(HangingProvider)).unwrap();
+
+        let (lights, errors) = registry.discover_all_detailed().await;
+        assert_eq!(lights.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "hanging");
+        assert!(matches!(errors[0].1, ProviderError::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_registry_discover_all_ignores_failures() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(FailingProvider)).unwrap();
+
+        let lights = registry.discover_all().await.unwrap();
+        assert_eq!(lights.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_registry_health_check_all() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "lifx" })).unwrap();
+        registry.register(Box::new(FailingProvider)).unwrap();
+
+        let results = registry.health_check_all().await;
+        assert_eq!(results.len(), 2);
+        assert!(results["lifx"].is_ok());
+        assert!(matches!(results["failing"], Err(ProviderError::Protocol(_))));
+    }
+
     #[tokio::test]
     async fn test_registry_get_state() {
         let mut registry = ProviderRegistry::new();
-        registry.register(Box::new(MockProvider { name: "test" }));
+        registry.register(Box::new(MockProvider { name: "test" })).unwrap();
 
         let result = registry.get_state("test", &LightId("any".to_string())).await;
         assert!(result.is_ok());
@@ -217,9 +915,145 @@ mod tests {
     #[tokio::test]
     async fn test_registry_set_brightness() {
         let mut registry = ProviderRegistry::new();
-        registry.register(Box::new(MockProvider { name: "test" }));
+        registry.register(Box::new(MockProvider { name: "test" })).unwrap();
 
         let result = registry.set_brightness("test", &LightId("any".to_string()), Brightness::new(0.5)).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_registry_set_brightness_reliable_retries() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(2))).unwrap();
+
+        let id = LightId("flaky:1".to_string());
+        registry.set_brightness_reliable("flaky", &id, Brightness::new(0.8), 2).await.unwrap();
+        let state = registry.get_state("flaky", &id).await.unwrap();
+        assert_eq!(state.brightness.as_f32(), 0.8);
+    }
+
+    #[tokio::test]
+    async fn test_registry_curved_round_trip() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(0))).unwrap();
+        let curve = crate::curves::GammaCurve::default();
+
+        let id = LightId("flaky:1".to_string());
+        let brightness = registry.set_brightness_curved("flaky", &id, 0.5, &curve).await.unwrap();
+        assert_eq!(brightness, Brightness::new(curve.apply(0.5)));
+        let (state, volume) = registry.get_state_curved("flaky", &id, &curve).await.unwrap();
+        assert_eq!(state.brightness, brightness);
+        assert!((volume - 0.5).abs() < 1e-3);
+    }
+
+    #[tokio::test]
+    async fn test_registry_set_brightness_if_changed() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(0))).unwrap();
+        let id = LightId("flaky:1".to_string());
+
+        assert!(registry.set_brightness_if_changed("flaky", &id, Brightness::new(0.5), 0.02).await.unwrap());
+        assert!(!registry.set_brightness_if_changed("flaky", &id, Brightness::new(0.51), 0.02).await.unwrap());
+        assert_eq!(registry.get_state("flaky", &id).await.unwrap().brightness, Brightness::new(0.5));
+        assert!(registry.set_brightness_if_changed("flaky", &id, Brightness::new(0.6), 0.02).await.unwrap());
+        assert_eq!(registry.get_state("flaky", &id).await.unwrap().brightness, Brightness::new(0.6));
+    }
+
+    #[tokio::test]
+    async fn test_registry_set_brightness_reliable_gives_up() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(3))).unwrap();
+
+        let id = LightId("flaky:1".to_string());
+        let result = registry.set_brightness_reliable("flaky", &id, Brightness::new(0.8), 1).await;
+        assert!(matches!(result, Err(ProviderError::SetBrightnessFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registry_group_fans_out() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(0))).unwrap();
+        registry.set_groups(vec![LightGroup::new(
+            "desk".to_string(),
+            vec![LightId("flaky:a".to_string()), LightId("flaky:b".to_string())],
+        )]);
+
+        let id = LightId("group:desk".to_string());
+        registry.set_brightness(GROUP_PROVIDER, &id, Brightness::new(0.7)).await.unwrap();
+        let state = registry.get_state(GROUP_PROVIDER, &id).await.unwrap();
+        assert_eq!(state.label, "desk");
+        assert!((state.brightness.as_f32() - 0.7).abs() < 1e-6);
+
+        let missing = registry.get_state(GROUP_PROVIDER, &LightId("group:none".to_string())).await;
+        assert!(matches!(missing, Err(ProviderError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registry_group_reports_member_failures() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(0))).unwrap();
+        registry.set_groups(vec![LightGroup::new(
+            "desk".to_string(),
+            vec![LightId("flaky:a".to_string()), LightId("gone:b".to_string())],
+        )]);
+
+        let id = LightId("group:desk".to_string());
+        let result = registry.set_brightness(GROUP_PROVIDER, &id, Brightness::new(0.3)).await;
+        assert!(matches!(result, Err(ProviderError::NotConfigured(_))));
+        let state = registry.get_state("flaky", &LightId("flaky:a".to_string())).await.unwrap();
+        assert!((state.brightness.as_f32() - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_registry_capabilities() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "test" })).unwrap();
+        registry.register(Box::new(FlakyProvider::new(0))).unwrap();
+        registry.set_groups(vec![LightGroup::new(
+            "desk".to_string(),
+            vec![LightId("flaky:a".to_string()), LightId("test:b".to_string())],
+        )]);
+
+        let id = LightId("flaky:a".to_string());
+        assert!(registry.capabilities("flaky", &id).unwrap().power);
+        let group = registry.capabilities(GROUP_PROVIDER, &LightId("group:desk".to_string())).unwrap();
+        assert_eq!(group, Capabilities::default());
+        assert!(registry.capabilities("missing", &id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registry_ramp_brightness() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(FlakyProvider::new(0))).unwrap();
+        let id = LightId("flaky:1".to_string());
+
+        registry
+            .ramp_brightness("flaky", &id, Brightness::new(0.0), Brightness::new(0.8), Duration::from_millis(120))
+            .await
+            .unwrap();
+        let state = registry.get_state("flaky", &id).await.unwrap();
+        assert!(state.brightness.approx_eq(&Brightness::new(0.8), Brightness::LSB));
+    }
+
+    #[tokio::test]
+    async fn test_registry_subscribe_unsupported() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "test" })).unwrap();
+
+        assert!(matches!(registry.subscribe("test").await, Err(ProviderError::Protocol(_))));
+        assert!(matches!(registry.subscribe(GROUP_PROVIDER).await, Err(ProviderError::Protocol(_))));
+        assert!(matches!(registry.subscribe("missing").await, Err(ProviderError::NotConfigured(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registry_set_power_unsupported() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(MockProvider { name: "test" })).unwrap();
+
+        let result = registry.set_power("test", &LightId("any".to_string()), false).await;
+        assert!(matches!(result, Err(ProviderError::Protocol(_))));
+
+        let result = registry.set_power("missing", &LightId("any".to_string()), false).await;
+        assert!(matches!(result, Err(ProviderError::NotConfigured(_))));
+    }
 }
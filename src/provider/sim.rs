@@ -0,0 +1,183 @@
+use super::types::{Capabilities, Color, Light, LightState, LightId, Brightness, Provider};
+use super::error::ProviderError;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+#[derive(Debug)]
+pub struct SimLight {
+    state: LightState,
+}
+
+impl Light for SimLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "sim"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+pub fn light_id_for_index(index: usize) -> LightId {
+    LightId(format!("sim:{}", index))
+}
+
+/// In-memory lights for exercising the sync loops without hardware. Every
+/// command succeeds and is reflected by the next `get_state`.
+#[derive(Debug)]
+pub struct SimProvider {
+    /// Kept in seed order so discovery is stable.
+    lights: RwLock<Vec<LightState>>,
+    kelvin: RwLock<HashMap<LightId, u16>>,
+}
+
+impl SimProvider {
+    /// Seeds `count` lights, `sim:1` to `sim:<count>`, powered on at half brightness.
+    pub fn new(count: usize) -> Self {
+        Self::from_states((1..=count).map(|index| {
+            LightState::new(light_id_for_index(index), format!("Sim Light {}", index), Brightness::new(0.5), true)
+        }))
+    }
+
+    pub fn from_states(states: impl IntoIterator<Item = LightState>) -> Self {
+        Self {
+            lights: RwLock::new(states.into_iter().collect()),
+            kelvin: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The last color temperature set on a light, if any.
+    pub fn kelvin(&self, id: &LightId) -> Option<u16> {
+        self.kelvin.read().expect("sim kelvin table poisoned").get(id).copied()
+    }
+
+    fn update(&self, id: &LightId, apply: impl FnOnce(&mut LightState)) -> Result<(), ProviderError> {
+        let mut lights = self.lights.write().expect("sim light table poisoned");
+        let state = lights.iter_mut().find(|state| &state.id == id).ok_or_else(|| ProviderError::NotFound(id.clone()))?;
+        apply(state);
+        Ok(())
+    }
+}
+
+impl Default for SimProvider {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+#[async_trait]
+impl Provider for SimProvider {
+    fn name(&self) -> &'static str {
+        "sim"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: false }
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let lights = self.lights.read().expect("sim light table poisoned");
+        Ok(lights
+            .iter()
+            .map(|state| Box::new(SimLight { state: state.clone() }) as Box<dyn Light>)
+            .collect())
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let lights = self.lights.read().expect("sim light table poisoned");
+        lights.iter().find(|state| &state.id == id).cloned().ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        tracing::debug!("sim: {} brightness {:.2}", id.0, brightness.as_f32());
+        self.update(id, |state| state.brightness = brightness)
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        tracing::debug!("sim: {} power {}", id.0, on);
+        self.update(id, |state| state.power = on)
+    }
+
+    async fn set_kelvin(&self, id: &LightId, kelvin: u16) -> Result<(), ProviderError> {
+        tracing::debug!("sim: {} color temperature {}K", id.0, kelvin);
+        self.update(id, |_| {})?;
+        self.kelvin.write().expect("sim kelvin table poisoned").insert(id.clone(), kelvin);
+        Ok(())
+    }
+
+    async fn set_color(&self, id: &LightId, color: Color) -> Result<(), ProviderError> {
+        tracing::debug!("sim: {} color {:?}", id.0, color);
+        self.update(id, |state| {
+            state.brightness = color.brightness();
+            state.color = Some(color);
+        })
+    }
+
+    async fn set_label(&self, id: &LightId, label: &str) -> Result<(), ProviderError> {
+        tracing::debug!("sim: {} renamed to {}", id.0, label);
+        self.update(id, |state| state.label = label.to_string())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "sim",
+        enabled: |config| config.sim.enabled,
+        build: |config| Ok(Box::new(SimProvider::new(config.sim.lights))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_discover_seeded_lights() {
+        let provider = SimProvider::new(3);
+        let lights = provider.discover().await.unwrap();
+        let ids: Vec<&str> = lights.iter().map(|light| light.id().0.as_str()).collect();
+        assert_eq!(ids, ["sim:1", "sim:2", "sim:3"]);
+        assert_eq!(lights[0].label(), "Sim Light 1");
+        assert_eq!(lights[0].provider_name(), "sim");
+    }
+
+    #[tokio::test]
+    async fn test_commands_update_state() {
+        let provider = SimProvider::new(1);
+        let id = light_id_for_index(1);
+
+        provider.set_brightness(&id, Brightness::new(0.8)).await.unwrap();
+        provider.set_power(&id, false).await.unwrap();
+        provider.set_kelvin(&id, 2700).await.unwrap();
+        provider.set_label(&id, "Desk").await.unwrap();
+
+        let state = provider.get_state(&id).await.unwrap();
+        assert_eq!(state.label, "Desk");
+        assert_eq!(state.brightness.as_f32(), 0.8);
+        assert_eq!(state.color, None);
+        assert!(!state.power);
+        assert_eq!(provider.kelvin(&id), Some(2700));
+
+        provider.set_color(&id, Color::new(30, 100, 40)).await.unwrap();
+        let state = provider.get_state(&id).await.unwrap();
+        assert_eq!(state.color, Some(Color::new(30, 100, 40)));
+        assert_eq!(state.brightness.as_percent(), 40);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_light() {
+        let provider = SimProvider::new(1);
+        let id = light_id_for_index(2);
+        assert!(matches!(provider.get_state(&id).await, Err(ProviderError::NotFound(_))));
+        assert!(matches!(provider.set_brightness(&id, Brightness::new(0.1)).await, Err(ProviderError::NotFound(_))));
+    }
+}
@@ -1,27 +1,68 @@
 use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use super::error::ProviderError;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct LightId(pub String);
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+impl LightId {
+    /// The provider prefix of ids shaped like `lifx:d073d5123456`.
+    pub fn provider(&self) -> Option<&str> {
+        self.0.split_once(':').map(|(provider, _)| provider)
+    }
+}
+
+/// Serialized as a bare number; out-of-range values are clamped on the way in.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(from = "f32", into = "f32")]
 pub struct Brightness(pub f32);
 
 impl Brightness {
+    /// One step of the 16-bit scale most bulbs use on the wire.
+    pub const LSB: f32 = 1.0 / 65535.0;
+
     pub fn new(value: f32) -> Self {
         Self(value.clamp(0.0, 1.0))
     }
 
+    pub fn from_u16(value: u16) -> Self {
+        Self(value as f32 / 65535.0)
+    }
+
+    pub fn from_percent(percent: u8) -> Self {
+        Self::new(percent as f32 / 100.0)
+    }
+
     pub fn as_f32(&self) -> f32 {
         self.0
     }
 
     pub fn as_u16(&self) -> u16 {
-        (self.0 * 65535.0) as u16
+        (self.0 * 65535.0).round() as u16
     }
 
     pub fn as_percent(&self) -> u8 {
-        (self.0 * 100.0) as u8
+        (self.0 * 100.0).round() as u8
+    }
+
+    /// Whether the two levels differ by at most `tolerance`, e.g. `Brightness::LSB`.
+    pub fn approx_eq(&self, other: &Brightness, tolerance: f32) -> bool {
+        (self.0 - other.0).abs() <= tolerance
+    }
+
+    /// The `steps` levels of a software fade from `from` to `to`, ending at
+    /// `to`. Both ends are already the curve's output, so the steps are even
+    /// between them: the fade follows the curve's shaping of the levels
+    /// rather than being reshaped by it.
+    pub fn ramp(from: Brightness, to: Brightness, steps: u32) -> impl Iterator<Item = Brightness> {
+        (1..=steps).map(move |step| {
+            if step == steps {
+                return to;
+            }
+            let progress = step as f32 / steps as f32;
+            Brightness::new(from.0 + (to.0 - from.0) * progress)
+        })
     }
 }
 
@@ -31,17 +72,92 @@ impl Default for Brightness {
     }
 }
 
-#[derive(Clone, Debug)]
+impl From<f32> for Brightness {
+    fn from(value: f32) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<Brightness> for f32 {
+    fn from(brightness: Brightness) -> Self {
+        brightness.0
+    }
+}
+
+/// Hue in degrees (0-359), saturation and value in percent (0-100).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Color {
+    pub hue: u16,
+    pub saturation: u8,
+    pub value: u8,
+}
+
+impl Color {
+    /// Wraps the hue into 0-359 and clamps the percentages.
+    pub fn new(hue: u16, saturation: u8, value: u8) -> Self {
+        Self { hue: hue % 360, saturation: saturation.min(100), value: value.min(100) }
+    }
+
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
+        let (r, g, b) = (red as f32 / 255.0, green as f32 / 255.0, blue as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        Self::new(hue.round() as u16, (saturation * 100.0).round() as u8, (max * 100.0).round() as u8)
+    }
+
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        let value = self.value as f32 / 100.0;
+        let chroma = value * self.saturation as f32 / 100.0;
+        let sector = self.hue as f32 / 60.0;
+        let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match sector as u16 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+        (channel(r), channel(g), channel(b))
+    }
+
+    /// The value as a brightness, for providers that set it separately.
+    pub fn brightness(&self) -> Brightness {
+        Brightness::from_percent(self.value)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LightState {
     pub id: LightId,
     pub label: String,
     pub brightness: Brightness,
     pub power: bool,
+    /// The current color, for providers that report one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
 }
 
 impl LightState {
     pub fn new(id: LightId, label: String, brightness: Brightness, power: bool) -> Self {
-        Self { id, label, brightness, power }
+        Self { id, label, brightness, power, color: None }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
     }
 }
 
@@ -60,15 +176,134 @@ pub trait Light: Send + Sync + std::fmt::Debug {
     }
 }
 
+/// What a provider can do beyond reading state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub brightness: bool,
+    pub power: bool,
+    pub kelvin: bool,
+    pub color: bool,
+    /// Fades brightness natively; see `Provider::set_brightness_with_transition`.
+    pub transition: bool,
+}
+
+impl Capabilities {
+    /// Only what both sides support.
+    pub fn intersect(self, other: Self) -> Self {
+        Self {
+            brightness: self.brightness && other.brightness,
+            power: self.power && other.power,
+            kelvin: self.kelvin && other.kelvin,
+            color: self.color && other.color,
+            transition: self.transition && other.transition,
+        }
+    }
+}
+
+/// Brightness only, which every provider must support.
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self { brightness: true, power: false, kelvin: false, color: false, transition: false }
+    }
+}
+
+impl std::fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = [
+            (self.brightness, "brightness"),
+            (self.power, "power"),
+            (self.kelvin, "kelvin"),
+            (self.color, "color"),
+            (self.transition, "transition"),
+        ]
+        .into_iter()
+        .filter_map(|(supported, name)| supported.then_some(name))
+        .collect();
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(", "))
+        }
+    }
+}
+
 use async_trait::async_trait;
+use std::time::Duration;
+
+/// How long the registry waits for a provider that doesn't say otherwise.
+pub const DEFAULT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Extra time allowed after a provider's listen window, for the state
+/// queries most providers make once the replies are in.
+pub const DISCOVERY_GRACE: Duration = Duration::from_secs(2);
+
+/// Told of each device as discovery hears from it: its id, and its label if
+/// already known.
+pub type OnFound<'a> = dyn Fn(&LightId, Option<&str>) + Send + Sync + 'a;
 
 #[async_trait]
 pub trait Provider: Send + Sync + std::fmt::Debug {
     fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// The longest `discover` may run before the registry abandons it.
+    fn discovery_timeout(&self) -> Duration {
+        DEFAULT_DISCOVERY_TIMEOUT
+    }
+
     async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError>;
+
+    /// Like `discover`, calling `found` for each device as soon as it answers
+    /// so progress can be shown during the listen window. Providers that
+    /// only learn of devices all at once report them when `discover` returns.
+    async fn discover_streaming(&self, found: &OnFound<'_>) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let lights = self.discover().await?;
+        for light in &lights {
+            found(light.id(), Some(light.label()));
+        }
+        Ok(lights)
+    }
+
     async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError>;
     async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError>;
 
+    /// Fades to `brightness` over `duration`. Providers that can't fade
+    /// natively ignore the duration.
+    async fn set_brightness_with_transition(
+        &self,
+        id: &LightId,
+        brightness: Brightness,
+        _duration: Duration,
+    ) -> Result<(), ProviderError> {
+        self.set_brightness(id, brightness).await
+    }
+
+    async fn set_power(&self, _id: &LightId, _on: bool) -> Result<(), ProviderError> {
+        Err(ProviderError::Protocol("unsupported".to_string()))
+    }
+
+    async fn set_kelvin(&self, _id: &LightId, _kelvin: u16) -> Result<(), ProviderError> {
+        Err(ProviderError::Protocol("unsupported".to_string()))
+    }
+
+    async fn set_color(&self, _id: &LightId, _color: Color) -> Result<(), ProviderError> {
+        Err(ProviderError::Protocol("unsupported".to_string()))
+    }
+
+    /// Renames the light on the device itself.
+    async fn set_label(&self, _id: &LightId, _label: &str) -> Result<(), ProviderError> {
+        Err(ProviderError::Protocol("unsupported".to_string()))
+    }
+
+    /// Streams state changes as the provider learns of them, so callers can
+    /// react to pushes instead of polling `get_state`. Providers that can't
+    /// push updates leave this unsupported.
+    async fn subscribe(&self) -> Result<tokio::sync::mpsc::Receiver<LightState>, ProviderError> {
+        Err(ProviderError::Protocol("unsupported".to_string()))
+    }
+
     async fn health_check(&self) -> Result<(), ProviderError> {
         Ok(())
     }
@@ -102,6 +337,12 @@ mod tests {
         assert!(!set.contains(&id3));
     }
 
+    #[test]
+    fn test_light_id_provider() {
+        assert_eq!(LightId("lifx:d073d5123456".to_string()).provider(), Some("lifx"));
+        assert_eq!(LightId("no-prefix".to_string()).provider(), None);
+    }
+
     #[test]
     fn test_brightness_new_clamps() {
         assert_eq!(Brightness::new(1.5).as_f32(), 1.0);
@@ -114,10 +355,51 @@ mod tests {
         let b = Brightness::new(0.5);
 
         assert_eq!(b.as_f32(), 0.5);
-        assert_eq!(b.as_u16(), 32767);
+        assert_eq!(b.as_u16(), 32768);
         assert_eq!(b.as_percent(), 50);
     }
 
+    #[test]
+    fn test_brightness_conversions_endpoints() {
+        assert_eq!(Brightness::new(0.0).as_u16(), 0);
+        assert_eq!(Brightness::new(1.0).as_u16(), 65535);
+        assert_eq!(Brightness::new(0.0).as_percent(), 0);
+        assert_eq!(Brightness::new(1.0).as_percent(), 100);
+    }
+
+    #[test]
+    fn test_brightness_conversions_round() {
+        assert_eq!(Brightness::new(0.99999).as_u16(), 65534);
+        assert_eq!(Brightness::new(1.0 / 65535.0).as_u16(), 1);
+        assert_eq!(Brightness::new(0.29).as_percent(), 29);
+        assert_eq!(Brightness::new(0.996).as_percent(), 100);
+    }
+
+    #[test]
+    fn test_brightness_from_u16_round_trip() {
+        for raw in [0u16, 1, 255, 1000, 32767, 32768, 50000, 65534, 65535] {
+            assert_eq!(Brightness::from_u16(raw).as_u16(), raw);
+        }
+
+        for i in 0..=100 {
+            let b = Brightness::new(i as f32 / 100.0);
+            let round_trip = Brightness::from_u16(b.as_u16());
+            assert!((round_trip.as_f32() - b.as_f32()).abs() <= 1.0 / 65535.0);
+        }
+    }
+
+    #[test]
+    fn test_brightness_from_percent() {
+        assert_eq!(Brightness::from_percent(0).as_f32(), 0.0);
+        assert_eq!(Brightness::from_percent(50).as_f32(), 0.5);
+        assert_eq!(Brightness::from_percent(100).as_f32(), 1.0);
+        assert_eq!(Brightness::from_percent(250).as_f32(), 1.0);
+
+        for p in 0..=100u8 {
+            assert_eq!(Brightness::from_percent(p).as_percent(), p);
+        }
+    }
+
     #[test]
     fn test_brightness_default() {
         let b = Brightness::default();
@@ -139,4 +421,82 @@ mod tests {
         assert_eq!(state.brightness.as_f32(), 0.75);
         assert!(state.power);
     }
+
+    #[test]
+    fn test_capabilities() {
+        let all = Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: true };
+        assert_eq!(all.intersect(Capabilities::default()), Capabilities::default());
+        assert_eq!(Capabilities::default().to_string(), "brightness");
+        assert_eq!(all.to_string(), "brightness, power, kelvin, color, transition");
+    }
+
+    #[test]
+    fn test_brightness_approx_eq() {
+        let a = Brightness::new(0.5);
+        assert!(a.approx_eq(&Brightness::from_u16(a.as_u16()), Brightness::LSB));
+        assert!(!a.approx_eq(&Brightness::new(0.51), Brightness::LSB));
+        assert!(a.approx_eq(&Brightness::new(0.51), 0.01 + f32::EPSILON));
+    }
+
+    #[test]
+    fn test_brightness_ramp() {
+        let up: Vec<f32> = Brightness::ramp(Brightness::new(0.0), Brightness::new(0.8), 4)
+            .map(|b| b.as_f32())
+            .collect();
+        assert_eq!(up.len(), 4);
+        for (got, want) in up.iter().zip([0.2, 0.4, 0.6, 0.8]) {
+            assert!((got - want).abs() < 1e-6, "{:?}", up);
+        }
+        assert_eq!(Brightness::ramp(Brightness::new(0.0), Brightness::new(1.0), 0).count(), 0);
+
+        let to = Brightness::new(0.1);
+        let down: Vec<f32> = Brightness::ramp(Brightness::new(0.7), to, 3).map(|b| b.as_f32()).collect();
+        for (got, want) in down.iter().zip([0.5, 0.3, 0.1]) {
+            assert!((got - want).abs() < 1e-6, "{:?}", down);
+        }
+        assert_eq!(Brightness::ramp(Brightness::new(0.7), to, 3).last(), Some(to));
+    }
+
+    #[test]
+    fn test_brightness_serde() {
+        assert_eq!(serde_json::to_string(&Brightness::new(0.25)).unwrap(), "0.25");
+        assert_eq!(serde_json::from_str::<Brightness>("0.5").unwrap(), Brightness::new(0.5));
+        assert_eq!(serde_json::from_str::<Brightness>("1.5").unwrap(), Brightness::new(1.0));
+        assert_eq!(serde_json::from_str::<Brightness>("-2").unwrap(), Brightness::new(0.0));
+        assert!(Brightness::new(0.2) < Brightness::new(0.3));
+    }
+
+    #[test]
+    fn test_color_rgb_round_trip() {
+        assert_eq!(Color::from_rgb(255, 0, 0), Color::new(0, 100, 100));
+        assert_eq!(Color::from_rgb(0, 255, 0), Color::new(120, 100, 100));
+        assert_eq!(Color::from_rgb(0, 0, 255), Color::new(240, 100, 100));
+        assert_eq!(Color::from_rgb(255, 255, 0), Color::new(60, 100, 100));
+        assert_eq!(Color::from_rgb(0, 0, 0), Color::new(0, 0, 0));
+        assert_eq!(Color::new(300, 100, 100).to_rgb(), (255, 0, 255));
+        assert_eq!(Color::new(0, 0, 50).to_rgb(), (128, 128, 128));
+        assert_eq!(Color::new(400, 150, 120), Color::new(40, 100, 100));
+
+        for rgb in [(255, 128, 0), (12, 200, 99), (64, 64, 255), (200, 10, 180)] {
+            let (r, g, b) = Color::from_rgb(rgb.0, rgb.1, rgb.2).to_rgb();
+            assert!(r.abs_diff(rgb.0) <= 3 && g.abs_diff(rgb.1) <= 3 && b.abs_diff(rgb.2) <= 3, "{:?}", rgb);
+        }
+    }
+
+    #[test]
+    fn test_light_state_serde_round_trip() {
+        let state = LightState::new(LightId("lifx:abc".to_string()), "Desk".to_string(), Brightness::new(0.4), true);
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(json, r#"{"id":"lifx:abc","label":"Desk","brightness":0.4,"power":true}"#);
+        let back: LightState = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.id, state.id);
+        assert_eq!(back.brightness, state.brightness);
+        assert!(back.power);
+        assert_eq!(back.color, None);
+
+        let colored = state.with_color(Color::new(120, 50, 40));
+        let json = serde_json::to_string(&colored).unwrap();
+        assert!(json.ends_with(r#""color":{"hue":120,"saturation":50,"value":40}}"#));
+        assert_eq!(serde_json::from_str::<LightState>(&json).unwrap().color, colored.color);
+    }
 }
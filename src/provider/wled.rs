@@ -0,0 +1,205 @@
+use super::types::{Capabilities, Light, LightState, LightId, Brightness, Provider};
+use super::error::{http_error, ProviderError};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// WLED reports and accepts brightness on a 0-255 scale.
+const WLED_BRIGHTNESS_MAX: f32 = 255.0;
+
+#[derive(Debug)]
+pub struct WledLight {
+    state: LightState,
+}
+
+impl WledLight {
+    pub fn new(host: &str, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            state: LightState::new(light_id_for_host(host), label, brightness, power),
+        }
+    }
+}
+
+impl Light for WledLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "wled"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+/// WLED controllers are addressed by host, so the id carries it directly.
+pub fn light_id_for_host(host: &str) -> LightId {
+    LightId(format!("wled:{}", host))
+}
+
+pub fn host_for_light_id(id: &LightId) -> Option<&str> {
+    id.0.strip_prefix("wled:").filter(|host| !host.is_empty())
+}
+
+#[derive(Debug, Deserialize)]
+struct WledInfo {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WledState {
+    on: bool,
+    bri: u8,
+}
+
+impl WledState {
+    fn brightness(&self) -> Brightness {
+        Brightness::new(self.bri as f32 / WLED_BRIGHTNESS_MAX)
+    }
+}
+
+fn wled_brightness(brightness: Brightness) -> u8 {
+    (brightness.as_f32() * WLED_BRIGHTNESS_MAX).round() as u8
+}
+
+#[derive(Debug)]
+pub struct WledProvider {
+    hosts: Vec<String>,
+    client: reqwest::Client,
+}
+
+impl WledProvider {
+    pub fn new(hosts: Vec<String>, timeout_ms: u64) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(timeout_ms))
+            .build()
+            .expect("Failed to build HTTP client");
+        Self { hosts, client }
+    }
+
+    fn host<'a>(&self, id: &'a LightId) -> Result<&'a str, ProviderError> {
+        host_for_light_id(id).ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, host: &str, path: &str) -> Result<T, ProviderError> {
+        self.client
+            .get(format!("http://{}{}", host, path))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(http_error)?
+            .json()
+            .await
+            .map_err(http_error)
+    }
+
+    async fn post_state(&self, host: &str, body: serde_json::Value) -> Result<(), ProviderError> {
+        self.client
+            .post(format!("http://{}/json/state", host))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(http_error)?;
+        Ok(())
+    }
+
+    async fn read(&self, host: &str) -> Result<LightState, ProviderError> {
+        let info: WledInfo = self.get_json(host, "/json/info").await?;
+        let state: WledState = self.get_json(host, "/json/state").await?;
+        Ok(LightState::new(light_id_for_host(host), info.name, state.brightness(), state.on))
+    }
+}
+
+#[async_trait]
+impl Provider for WledProvider {
+    fn name(&self) -> &'static str {
+        "wled"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: false, color: false, transition: false }
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        if self.hosts.is_empty() {
+            return Err(ProviderError::NotConfigured("no WLED hosts configured".to_string()));
+        }
+
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        for host in &self.hosts {
+            match self.read(host).await {
+                Ok(state) => lights.push(Box::new(WledLight::new(host, state.label, state.brightness, state.power))),
+                Err(e) => tracing::warn!("WLED controller {} did not answer: {}", host, e),
+            }
+        }
+
+        if lights.is_empty() {
+            return Err(ProviderError::Timeout(format!("none of {} WLED host(s) answered", self.hosts.len())));
+        }
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        self.read(self.host(id)?).await
+    }
+
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let bri = wled_brightness(brightness);
+        self.post_state(self.host(id)?, serde_json::json!({ "on": bri > 0, "bri": bri })).await
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        self.post_state(self.host(id)?, serde_json::json!({ "on": on })).await
+    }
+
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        if self.hosts.is_empty() {
+            return Err(ProviderError::NotConfigured("no WLED hosts configured".to_string()));
+        }
+        for host in &self.hosts {
+            self.get_json::<WledInfo>(host, "/json/info").await?;
+        }
+        Ok(())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "wled",
+        enabled: |config| config.wled.enabled,
+        build: |config| Ok(Box::new(WledProvider::new(config.wled.hosts.clone(), config.wled.timeout_ms))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_id_round_trip() {
+        let id = light_id_for_host("192.168.1.40");
+        assert_eq!(id.0, "wled:192.168.1.40");
+        assert_eq!(host_for_light_id(&id), Some("192.168.1.40"));
+        assert_eq!(host_for_light_id(&LightId("lifx:d073d5123456".to_string())), None);
+    }
+
+    #[test]
+    fn test_parse_state() {
+        let state: WledState = serde_json::from_str(r#"{"on":true,"bri":128,"transition":7,"seg":[]}"#).unwrap();
+        assert!(state.on);
+        assert!((state.brightness().as_f32() - 0.502).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_wled_brightness() {
+        assert_eq!(wled_brightness(Brightness::new(0.0)), 0);
+        assert_eq!(wled_brightness(Brightness::new(1.0)), 255);
+    }
+}
@@ -0,0 +1,450 @@
+use super::types::{Capabilities, Color, Light, LightState, LightId, Brightness, Provider, DISCOVERY_GRACE};
+use super::error::ProviderError;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+const SSDP_ADDR: &str = "239.255.255.250:1982";
+const SEARCH: &str = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1982\r\nMAN: \"ssdp:discover\"\r\nST: wifi_bulb\r\n";
+/// How long to wait to connect to a bulb, or for it to answer a command.
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(2000);
+/// The shortest `smooth` transition the bulbs accept.
+const TRANSITION_MS: u32 = 30;
+/// Color temperature range of the white and color bulbs.
+const KELVIN_RANGE: (u16, u16) = (1700, 6500);
+
+#[derive(Debug)]
+pub struct YeelightLight {
+    addr: SocketAddr,
+    state: LightState,
+}
+
+impl YeelightLight {
+    pub fn new(device_id: &str, addr: SocketAddr, label: String, brightness: Brightness, power: bool) -> Self {
+        Self {
+            addr,
+            state: LightState::new(light_id_for_device(device_id), label, brightness, power),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Light for YeelightLight {
+    fn id(&self) -> &LightId {
+        &self.state.id
+    }
+
+    fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    fn provider_name(&self) -> &str {
+        "yeelight"
+    }
+
+    fn state(&self) -> &LightState {
+        &self.state
+    }
+}
+
+/// Bulbs advertise ids like `0x000000000015243f`; the prefix is dropped.
+pub fn light_id_for_device(device_id: &str) -> LightId {
+    let id = device_id.trim_start_matches("0x").to_lowercase();
+    LightId(format!("yeelight:{}", id))
+}
+
+/// One bulb's answer to the SSDP search.
+#[derive(Debug, PartialEq)]
+struct Advertisement {
+    id: String,
+    addr: SocketAddr,
+    name: String,
+    power: bool,
+    bright: u8,
+}
+
+impl Advertisement {
+    fn label(&self) -> String {
+        if self.name.is_empty() {
+            let id = self.id.trim_start_matches("0x");
+            format!("Yeelight {}", &id[id.len().saturating_sub(6)..])
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+fn parse_advertisement(text: &str) -> Option<Advertisement> {
+    let mut headers = HashMap::new();
+    for line in text.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            headers.insert(key.trim().to_lowercase(), value.trim());
+        }
+    }
+    let addr = headers.get("location")?.strip_prefix("yeelight://")?.parse().ok()?;
+    Some(Advertisement {
+        id: headers.get("id")?.to_string(),
+        addr,
+        name: headers.get("name").unwrap_or(&"").to_string(),
+        power: headers.get("power") == Some(&"on"),
+        bright: headers.get("bright")?.parse().ok()?,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct Reply {
+    id: Option<u64>,
+    result: Option<Vec<serde_json::Value>>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// A control connection to one bulb. Bulbs push `props` notifications on the
+/// same stream, so replies are matched to requests by id.
+#[derive(Debug)]
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Connection {
+    async fn open(addr: SocketAddr) -> Result<Self, ProviderError> {
+        let stream = tokio::time::timeout(REQUEST_TIMEOUT, TcpStream::connect(addr))
+            .await
+            .map_err(|_| ProviderError::Timeout(format!("could not connect to Yeelight at {}", addr)))??;
+        let (reader, writer) = stream.into_split();
+        Ok(Self { reader: BufReader::new(reader), writer, next_id: 1 })
+    }
+
+    async fn call(&mut self, method: &str, params: serde_json::Value) -> Result<Vec<serde_json::Value>, ProviderError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = serde_json::json!({ "id": id, "method": method, "params": params });
+        self.writer.write_all(format!("{}\r\n", request).as_bytes()).await?;
+
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = tokio::time::timeout_at(deadline, self.reader.read_line(&mut line))
+                .await
+                .map_err(|_| ProviderError::Timeout(format!("no reply to {}", method)))??;
+            if read == 0 {
+                return Err(ProviderError::Network(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+            let Ok(reply) = serde_json::from_str::<Reply>(&line) else {
+                tracing::debug!("Ignoring unreadable Yeelight line: {}", line.trim());
+                continue;
+            };
+            if reply.id != Some(id) {
+                continue;
+            }
+            if let Some(error) = reply.error {
+                return Err(ProviderError::Protocol(format!("{} failed ({}): {}", method, error.code, error.message)));
+            }
+            return Ok(reply.result.unwrap_or_default());
+        }
+    }
+}
+
+/// Yeelight bulbs with LAN Control enabled. Bulbs allow about 60 commands a
+/// minute per connection, so fast volume changes are best rate limited.
+#[derive(Debug)]
+pub struct YeelightProvider {
+    discovery_timeout: Duration,
+    /// Control addresses learned during discovery.
+    devices: RwLock<HashMap<LightId, SocketAddr>>,
+    /// One lazily opened connection per bulb, dropped after a failure.
+    connections: RwLock<HashMap<LightId, Arc<Mutex<Option<Connection>>>>>,
+}
+
+impl YeelightProvider {
+    pub fn new(discovery_timeout_ms: u64) -> Self {
+        Self {
+            discovery_timeout: Duration::from_millis(discovery_timeout_ms),
+            devices: RwLock::new(HashMap::new()),
+            connections: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn default_config() -> Self {
+        Self::new(5000)
+    }
+
+    async fn search(&self) -> Result<UdpSocket, ProviderError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.send_to(SEARCH.as_bytes(), SSDP_ADDR).await?;
+        tracing::debug!("Sent Yeelight M-SEARCH to {}", SSDP_ADDR);
+        Ok(socket)
+    }
+
+    fn known_addr(&self, id: &LightId) -> Option<SocketAddr> {
+        self.devices.read().expect("Yeelight device table poisoned").get(id).copied()
+    }
+
+    /// Looks up a bulb's address, running discovery once if it isn't known yet.
+    async fn addr_for(&self, id: &LightId) -> Result<SocketAddr, ProviderError> {
+        if let Some(addr) = self.known_addr(id) {
+            return Ok(addr);
+        }
+        if let Err(e) = self.discover().await {
+            tracing::debug!("Yeelight rediscovery for {} failed: {}", id.0, e);
+        }
+        self.known_addr(id).ok_or_else(|| ProviderError::NotFound(id.clone()))
+    }
+
+    fn connection(&self, id: &LightId) -> Arc<Mutex<Option<Connection>>> {
+        if let Some(slot) = self.connections.read().expect("Yeelight connection table poisoned").get(id) {
+            return slot.clone();
+        }
+        self.connections
+            .write()
+            .expect("Yeelight connection table poisoned")
+            .entry(id.clone())
+            .or_default()
+            .clone()
+    }
+
+    /// Sends a command over the bulb's connection, reconnecting once if the
+    /// connection has gone stale.
+    async fn call(&self, id: &LightId, method: &str, params: serde_json::Value) -> Result<Vec<serde_json::Value>, ProviderError> {
+        let addr = self.addr_for(id).await?;
+        let slot = self.connection(id);
+        let mut connection = slot.lock().await;
+        let mut retried = false;
+        loop {
+            if connection.is_none() {
+                *connection = Some(Connection::open(addr).await?);
+            }
+            let result = connection.as_mut().expect("connection just opened").call(method, params.clone()).await;
+            match result {
+                Err(ProviderError::Network(_) | ProviderError::Timeout(_)) if !retried => {
+                    tracing::debug!("Reconnecting to Yeelight {} at {}", id.0, addr);
+                    *connection = None;
+                    retried = true;
+                }
+                Err(e @ (ProviderError::Network(_) | ProviderError::Timeout(_))) => {
+                    *connection = None;
+                    return Err(e);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for YeelightProvider {
+    fn default() -> Self {
+        Self::default_config()
+    }
+}
+
+fn prop_str(values: &[serde_json::Value], index: usize) -> &str {
+    values.get(index).and_then(|value| value.as_str()).unwrap_or("")
+}
+
+#[async_trait]
+impl Provider for YeelightProvider {
+    fn name(&self) -> &'static str {
+        "yeelight"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { brightness: true, power: true, kelvin: true, color: true, transition: false }
+    }
+
+    fn discovery_timeout(&self) -> Duration {
+        self.discovery_timeout + DISCOVERY_GRACE
+    }
+
+    async fn discover(&self) -> Result<Vec<Box<dyn Light>>, ProviderError> {
+        let socket = self.search().await?;
+
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        let mut devices = HashMap::new();
+        let mut buf = [0u8; 2048];
+        let deadline = Instant::now() + self.discovery_timeout;
+
+        while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            let (len, from) = received?;
+            let Some(ad) = parse_advertisement(&String::from_utf8_lossy(&buf[..len])) else {
+                tracing::debug!("Ignoring unreadable SSDP reply from {}", from);
+                continue;
+            };
+            let light = YeelightLight::new(&ad.id, ad.addr, ad.label(), Brightness::from_percent(ad.bright), ad.power);
+            if devices.insert(light.id().clone(), ad.addr).is_none() {
+                tracing::debug!("Yeelight {} answered from {}", light.id().0, ad.addr);
+                lights.push(Box::new(light));
+            }
+        }
+
+        if lights.is_empty() {
+            return Err(ProviderError::Timeout(format!(
+                "no Yeelight bulbs answered within {}ms (is LAN Control enabled?)",
+                self.discovery_timeout.as_millis()
+            )));
+        }
+
+        self.devices.write().expect("Yeelight device table poisoned").extend(devices);
+        Ok(lights)
+    }
+
+    async fn get_state(&self, id: &LightId) -> Result<LightState, ProviderError> {
+        let props = self.call(id, "get_prop", serde_json::json!(["power", "bright", "name"])).await?;
+        let bright: u8 = prop_str(&props, 1)
+            .parse()
+            .map_err(|_| ProviderError::Protocol(format!("{} reported brightness {:?}", id.0, props.get(1))))?;
+        let label = match prop_str(&props, 2) {
+            "" => id.0.clone(),
+            name => name.to_string(),
+        };
+        Ok(LightState::new(id.clone(), label, Brightness::from_percent(bright), prop_str(&props, 0) == "on"))
+    }
+
+    /// Bulbs only accept 1-100, so zero turns the bulb off instead. Bulbs
+    /// reject `set_bright` while off, so that case powers on first.
+    async fn set_brightness(&self, id: &LightId, brightness: Brightness) -> Result<(), ProviderError> {
+        let percent = brightness.as_percent();
+        if percent == 0 {
+            return self.set_power(id, false).await;
+        }
+        let params = serde_json::json!([percent.min(100), "smooth", TRANSITION_MS]);
+        match self.call(id, "set_bright", params.clone()).await {
+            Err(ProviderError::Protocol(e)) => {
+                tracing::debug!("{} refused set_bright ({}); powering on", id.0, e);
+                self.set_power(id, true).await?;
+                self.call(id, "set_bright", params).await.map(|_| ())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn set_power(&self, id: &LightId, on: bool) -> Result<(), ProviderError> {
+        let params = serde_json::json!([if on { "on" } else { "off" }, "smooth", TRANSITION_MS]);
+        self.call(id, "set_power", params).await.map(|_| ())
+    }
+
+    async fn set_kelvin(&self, id: &LightId, kelvin: u16) -> Result<(), ProviderError> {
+        let kelvin = kelvin.clamp(KELVIN_RANGE.0, KELVIN_RANGE.1);
+        self.call(id, "set_ct_abx", serde_json::json!([kelvin, "smooth", TRANSITION_MS])).await.map(|_| ())
+    }
+
+    /// `set_hsv` leaves brightness alone, so the value is set separately.
+    async fn set_color(&self, id: &LightId, color: Color) -> Result<(), ProviderError> {
+        let params = serde_json::json!([color.hue, color.saturation, "smooth", TRANSITION_MS]);
+        self.call(id, "set_hsv", params).await?;
+        self.set_brightness(id, color.brightness()).await
+    }
+
+    async fn health_check(&self) -> Result<(), ProviderError> {
+        self.search().await.map(|_| ())
+    }
+}
+
+inventory::submit! {
+    super::factory::ProviderFactory {
+        name: "yeelight",
+        enabled: |config| config.yeelight.enabled,
+        build: |config| Ok(Box::new(YeelightProvider::new(config.yeelight.discovery_timeout_ms))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_advertisement() {
+        let reply = "HTTP/1.1 200 OK\r\nCache-Control: max-age=3600\r\nLocation: yeelight://192.168.1.239:55443\r\n\
+            Server: POSIX UPnP/1.0 YGLC/1\r\nid: 0x000000000015243f\r\nmodel: color\r\npower: on\r\nbright: 42\r\nname: \r\n";
+        let ad = parse_advertisement(reply).unwrap();
+        assert_eq!(ad.addr, "192.168.1.239:55443".parse().unwrap());
+        assert!(ad.power);
+        assert_eq!(ad.bright, 42);
+        assert_eq!(ad.label(), "Yeelight 15243f");
+        assert_eq!(light_id_for_device(&ad.id).0, "yeelight:000000000015243f");
+        assert!(parse_advertisement("NOTIFY * HTTP/1.1\r\n").is_none());
+    }
+
+    /// Answers each command line with `reply(id)`, after a `props` notification.
+    async fn serve_once(listener: &TcpListener, reply: impl Fn(u64) -> String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 1024];
+        while let Ok(len) = stream.read(&mut buf).await {
+            if len == 0 {
+                break;
+            }
+            let request: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+            let id = request["id"].as_u64().unwrap();
+            let notification = r#"{"method":"props","params":{"bright":"10"}}"#;
+            let out = format!("{}\r\n{}\r\n", notification, reply(id));
+            stream.write_all(out.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn provider_for(addr: SocketAddr) -> (YeelightProvider, LightId) {
+        let provider = YeelightProvider::new(50);
+        let id = light_id_for_device("0x1");
+        provider.devices.write().unwrap().insert(id.clone(), addr);
+        (provider, id)
+    }
+
+    #[tokio::test]
+    async fn test_get_state_skips_notifications() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (provider, id) = provider_for(listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            serve_once(&listener, |id| format!(r#"{{"id":{},"result":["on","64","Desk"]}}"#, id)).await;
+        });
+
+        let state = provider.get_state(&id).await.unwrap();
+        assert_eq!(state.label, "Desk");
+        assert_eq!(state.brightness.as_percent(), 64);
+        assert!(state.power);
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_connection_drops() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (provider, id) = provider_for(listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            serve_once(&listener, |id| format!(r#"{{"id":{},"result":["ok"]}}"#, id)).await;
+        });
+
+        provider.set_power(&id, true).await.unwrap();
+        provider.set_brightness(&id, Brightness::new(0.5)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rpc_error_is_protocol_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (provider, id) = provider_for(listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            serve_once(&listener, |id| format!(r#"{{"id":{},"error":{{"code":-1,"message":"unsupported method"}}}}"#, id)).await;
+        });
+
+        let result = provider.set_kelvin(&id, 2700).await;
+        assert!(matches!(result, Err(ProviderError::Protocol(e)) if e.contains("unsupported method")));
+    }
+}